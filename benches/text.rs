@@ -0,0 +1,63 @@
+//! [\file]: # (benches/text.rs)
+//!
+//! Throughput of reading ion text with the streaming `TextReader`, over a
+//! string and over an `io::Read`.
+//!
+//! The pest grammar `TextReader` replaced read the same text at 15.2 MB/s,
+//! recognising it only, against 32.5 MB/s for `TextReader` over a string;
+//! see the README.
+//!
+//! Run with `cargo bench`.
+
+extern crate anion;
+
+use anion::TextReader;
+
+use std::time::{Duration, Instant};
+
+/// Text of `count` records with a mix of value types
+fn sample(count: usize) -> String
+{
+  let mut text = String::new();
+  for i in 0..count {
+    text.push_str(&format!(
+      "{{id: {}, name: \"record {}\", tags: [alpha, 'beta gamma', \"delta\"], score: {}.25, \
+       ratio: {}e-3, point: (x {} y -{}), flag: {}, note: null.string}}\n",
+      i, i, i % 1000, i, i % 17, i % 13, i % 2 == 0,
+    ));
+  }
+  text
+}
+
+/// Run `f` repeatedly for about a second and report MB/s
+fn bench<F: FnMut() -> usize>(name: &str, bytes: usize, mut f: F)
+{
+  // warm up, and check the reader saw every value
+  let values = f();
+
+  let start = Instant::now();
+  let mut runs = 0;
+  while start.elapsed() < Duration::from_secs(1) {
+    f();
+    runs += 1;
+  }
+  let elapsed = start.elapsed();
+  let seconds = elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9;
+  let megabytes = (bytes * runs) as f64 / (1024.0 * 1024.0);
+
+  println!("{:<24} {:>8.1} MB/s  ({} values, {} runs)", name, megabytes / seconds, values, runs);
+}
+
+fn main()
+{
+  let text = sample(10_000);
+  println!("{} bytes of text", text.len());
+
+  bench("TextReader (str)", text.len(), || {
+    TextReader::from_text(&text).map(|value| value.unwrap()).count()
+  });
+
+  bench("TextReader (io::Read)", text.len(), || {
+    TextReader::from_reader(text.as_bytes()).map(|value| value.unwrap()).count()
+  });
+}
@@ -0,0 +1,353 @@
+//! Reading and writing ion asynchronously
+//!
+//! With the `async` feature, `TextStream` and `BinaryStream` are streams
+//! of the top level values read from a tokio `AsyncRead`, and
+//! `TextWriter` writes values as ion text to an `AsyncWrite`.
+//!
+//! The streams feed what they read to the incremental readers
+//! (`TextReader::incremental` and `BinaryReader`), so a value is yielded
+//! once all of it has arrived. A value split across reads is parsed from
+//! its start when the read which may finish it arrives, not after every
+//! read; parsing doesn't resume part way through a value.
+
+use super::AnionValue;
+use binary::{BinaryError, BinaryReader};
+use lexer::ChunkSource;
+use reader::{ParseError, ReaderOptions, TextReader};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Bytes asked for in each read
+const READ_SIZE: usize = 8 * 1024;
+
+/// Read the next chunk into `buffer`; an empty chunk is the end of input
+fn poll_chunk<'b, R>(reader: &mut R, buffer: &'b mut [u8], cx: &mut Context) -> Poll<io::Result<&'b [u8]>>
+  where R: AsyncRead + Unpin
+{
+  let mut read = ReadBuf::new(buffer);
+  match Pin::new(reader).poll_read(cx, &mut read) {
+    Poll::Ready(Ok(())) => {
+      let length = read.filled().len();
+      Poll::Ready(Ok(&buffer[..length]))
+    },
+    Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+    Poll::Pending => Poll::Pending,
+  }
+}
+
+/// The top level values of ion text read from an `AsyncRead`
+pub struct TextStream<R> {
+  reader: R,
+  text: TextReader<'static, ChunkSource>,
+  buffer: Vec<u8>,
+  done: bool,
+}
+
+impl<R: AsyncRead + Unpin> TextStream<R> {
+  pub fn new(reader: R) -> TextStream<R>
+  {
+    TextStream::with_options(reader, ReaderOptions::default())
+  }
+
+  pub fn with_options(reader: R, options: ReaderOptions) -> TextStream<R>
+  {
+    TextStream {
+      reader: reader,
+      text: TextReader::incremental(options),
+      buffer: vec![0; READ_SIZE],
+      done: false,
+    }
+  }
+
+  pub fn into_inner(self) -> R
+  {
+    self.reader
+  }
+}
+
+/// The stream ends after the first error
+impl<R: AsyncRead + Unpin> Stream for TextStream<R> {
+  type Item = Result<AnionValue, ParseError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>>
+  {
+    let stream = self.get_mut();
+    while !stream.done {
+      match stream.text.next_value() {
+        Ok(Some(value)) => return Poll::Ready(Some(Ok(value.into_owned()))),
+        Ok(None) => stream.done = true,
+        Err(ParseError::Incomplete) => {
+          match poll_chunk(&mut stream.reader, &mut stream.buffer, cx) {
+            Poll::Ready(Ok(chunk)) if chunk.is_empty() => stream.text.finish(),
+            Poll::Ready(Ok(chunk)) => stream.text.feed(chunk),
+            Poll::Ready(Err(error)) => {
+              stream.done = true;
+              return Poll::Ready(Some(Err(ParseError::Io(error.kind(), error.to_string()))));
+            },
+            Poll::Pending => return Poll::Pending,
+          }
+        },
+        Err(error) => {
+          stream.done = true;
+          return Poll::Ready(Some(Err(error)));
+        },
+      }
+    }
+    return Poll::Ready(None);
+  }
+}
+
+/// The top level values of binary ion read from an `AsyncRead`
+pub struct BinaryStream<R> {
+  reader: R,
+  binary: BinaryReader,
+  buffer: Vec<u8>,
+  done: bool,
+}
+
+impl<R: AsyncRead + Unpin> BinaryStream<R> {
+  pub fn new(reader: R) -> BinaryStream<R>
+  {
+    BinaryStream::with_options(reader, ReaderOptions::default())
+  }
+
+  pub fn with_options(reader: R, options: ReaderOptions) -> BinaryStream<R>
+  {
+    BinaryStream {
+      reader: reader,
+      binary: BinaryReader::with_options(options),
+      buffer: vec![0; READ_SIZE],
+      done: false,
+    }
+  }
+
+  pub fn into_inner(self) -> R
+  {
+    self.reader
+  }
+}
+
+/// The stream ends after the first error
+impl<R: AsyncRead + Unpin> Stream for BinaryStream<R> {
+  type Item = Result<AnionValue, BinaryError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>>
+  {
+    let stream = self.get_mut();
+    while !stream.done {
+      match stream.binary.next_value() {
+        Ok(Some(value)) => return Poll::Ready(Some(Ok(value))),
+        Ok(None) => stream.done = true,
+        Err(BinaryError::Incomplete) => {
+          match poll_chunk(&mut stream.reader, &mut stream.buffer, cx) {
+            Poll::Ready(Ok(chunk)) if chunk.is_empty() => stream.binary.finish(),
+            Poll::Ready(Ok(chunk)) => stream.binary.feed(chunk),
+            Poll::Ready(Err(error)) => {
+              stream.done = true;
+              return Poll::Ready(Some(Err(BinaryError::Io(error.kind(), error.to_string()))));
+            },
+            Poll::Pending => return Poll::Pending,
+          }
+        },
+        Err(error) => {
+          stream.done = true;
+          return Poll::Ready(Some(Err(error)));
+        },
+      }
+    }
+    return Poll::Ready(None);
+  }
+}
+
+/// Writes top level values as ion text to an `AsyncWrite`, one per line
+pub struct TextWriter<W> {
+  writer: W,
+}
+
+impl<W: AsyncWrite + Unpin> TextWriter<W> {
+  pub fn new(writer: W) -> TextWriter<W>
+  {
+    TextWriter { writer: writer }
+  }
+
+  /// Write a value; the returned future finishes once all of it has been
+  /// passed to the writer
+  pub fn write_value<'w>(&'w mut self, value: &AnionValue) -> WriteValue<'w, W>
+  {
+    WriteValue {
+      writer: &mut self.writer,
+      text: format!("{}\n", value).into_bytes(),
+      written: 0,
+    }
+  }
+
+  /// Flush the writer
+  pub fn flush<'w>(&'w mut self) -> Flush<'w, W>
+  {
+    Flush { writer: &mut self.writer }
+  }
+
+  pub fn get_ref(&self) -> &W
+  {
+    &self.writer
+  }
+
+  pub fn into_inner(self) -> W
+  {
+    self.writer
+  }
+}
+
+/// Future of `TextWriter::write_value`
+pub struct WriteValue<'w, W: 'w> {
+  writer: &'w mut W,
+  text: Vec<u8>,
+
+  /// Bytes of `text` written so far
+  written: usize,
+}
+
+impl<'w, W: AsyncWrite + Unpin> Future for WriteValue<'w, W> {
+  type Output = io::Result<()>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>>
+  {
+    let write = self.get_mut();
+    while write.written < write.text.len() {
+      match Pin::new(&mut *write.writer).poll_write(cx, &write.text[write.written..]) {
+        Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write value"))),
+        Poll::Ready(Ok(length)) => write.written += length,
+        Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+        Poll::Pending => return Poll::Pending,
+      }
+    }
+    return Poll::Ready(Ok(()));
+  }
+}
+
+/// Future of `TextWriter::flush`
+pub struct Flush<'w, W: 'w> {
+  writer: &'w mut W,
+}
+
+impl<'w, W: AsyncWrite + Unpin> Future for Flush<'w, W> {
+  type Output = io::Result<()>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>>
+  {
+    Pin::new(&mut *self.get_mut().writer).poll_flush(cx)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use binary::VERSION_MARKER;
+
+  use std::task::{RawWaker, RawWakerVTable, Waker};
+
+  fn noop_waker() -> Waker
+  {
+    fn clone(_: *const ()) -> RawWaker
+    {
+      RawWaker::new(::std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(clone(::std::ptr::null())) }
+  }
+
+  /// Poll a future or stream until it's ready, as data keeps arriving
+  fn block_on<T, F: FnMut(&mut Context) -> Poll<T>>(mut poll: F) -> T
+  {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+      if let Poll::Ready(value) = poll(&mut cx) {
+        return value;
+      }
+    }
+  }
+
+  fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item>
+  {
+    let mut items = Vec::new();
+    while let Some(item) = block_on(|cx| Pin::new(&mut stream).poll_next(cx)) {
+      items.push(item);
+    }
+    items
+  }
+
+  /// Hands out a few bytes at a time, and is pending between them
+  struct Trickle {
+    data: Vec<u8>,
+    offset: usize,
+    ready: bool,
+  }
+
+  impl Trickle {
+    fn new(data: &[u8]) -> Trickle
+    {
+      Trickle { data: data.to_vec(), offset: 0, ready: false }
+    }
+  }
+
+  impl AsyncRead for Trickle {
+    fn poll_read(self: Pin<&mut Self>, _: &mut Context, buf: &mut ReadBuf) -> Poll<io::Result<()>>
+    {
+      let trickle = self.get_mut();
+      trickle.ready = !trickle.ready;
+      if !trickle.ready {
+        return Poll::Pending;
+      }
+      let end = (trickle.offset + 3).min(trickle.data.len());
+      buf.put_slice(&trickle.data[trickle.offset..end]);
+      trickle.offset = end;
+      Poll::Ready(Ok(()))
+    }
+  }
+
+  #[test]
+  fn text_streams()
+  {
+    let values = collect(TextStream::new(Trickle::new(b"{a: 1, b: \"two\"} [3.5e0, c] 'd' $ion_1_0 ")));
+    assert_eq!(values, vec![Ok(ion!({ a: 1, b: "two" })), Ok(ion!([3.5, c])), Ok(ion!(d))]);
+
+    let values = collect(TextStream::new(Trickle::new(b"1 [2")));
+    assert_eq!(values, vec![Ok(ion!(1)), Err(ParseError::UnexpectedEnd)]);
+  }
+
+  #[test]
+  fn binary_streams()
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    data.extend(&[0xd5, 0x84, 0x21, 0x07, 0x80, 0x00, 0x83, b'a', b'b', b'c']);
+    let values = collect(BinaryStream::new(Trickle::new(&data)));
+    assert_eq!(values, vec![Ok(ion!({ name: 7 })), Ok(ion!("abc"))]);
+
+    data.pop();
+    let values = collect(BinaryStream::new(Trickle::new(&data)));
+    assert_eq!(values, vec![Ok(ion!({ name: 7 })), Err(BinaryError::UnexpectedEnd)]);
+  }
+
+  #[test]
+  fn writing_text()
+  {
+    let mut writer = TextWriter::new(Vec::new());
+    for value in &[ion!({ a: [1, "two"] }), ion!(b::c)] {
+      let mut write = writer.write_value(value);
+      block_on(|cx| Pin::new(&mut write).poll(cx)).unwrap();
+    }
+    block_on(|cx| Pin::new(&mut writer.flush()).poll(cx)).unwrap();
+
+    let text = writer.into_inner();
+    assert_eq!(String::from_utf8(text.clone()).unwrap(), "{a: [1, \"two\"]}\nb::c\n");
+    assert_eq!(collect(TextStream::new(&text[..])), vec![Ok(ion!({ a: [1, "two"] })), Ok(ion!(b::c))]);
+  }
+}
@@ -1,29 +1,28 @@
 
-#[macro_use]
-extern crate pest;
 extern crate anion;
 
 use anion::AnionValue;
-use anion::parser::parse_string;
+use anion::reader::parse_string;
 
 fn main()
 {
   for s in std::env::args().skip(1) {
     let anion_value = parse_string(s.as_str());
 
-    if let Some(val) = anion_value {
-      match val {
-        AnionValue::Boolean(Some(x)) => println!("Bool {}", x),
-        AnionValue::Boolean(None) => println!("Bool NULL"),
-        AnionValue::Integer(Some(x)) => println!("Int {}", x),
-        AnionValue::Integer(None) => println!("Int NULL"),
-        AnionValue::Float(Some(x)) => println!("Float {}", x),
-        AnionValue::Float(None) => println!("Float NULL"),
-        AnionValue::Decimal(Some(x)) => println!("Decimal {}", x),
-        AnionValue::Decimal(None) => println!("Decimal NULL"),
-        AnionValue::String(Some(x)) => println!("String {}", x),
-        AnionValue::String(None) => println!("String NULL"),
-      }
+    match anion_value {
+      Ok(AnionValue::Boolean(Some(x))) => println!("Bool {}", x),
+      Ok(AnionValue::Boolean(None)) => println!("Bool NULL"),
+      Ok(AnionValue::Integer(Some(x))) => println!("Int {}", x),
+      Ok(AnionValue::Integer(None)) => println!("Int NULL"),
+      Ok(AnionValue::Float(Some(x))) => println!("Float {}", AnionValue::from(x)),
+      Ok(AnionValue::Float(None)) => println!("Float NULL"),
+      Ok(AnionValue::Decimal(Some(x))) => println!("Decimal {}", x),
+      Ok(AnionValue::Decimal(None)) => println!("Decimal NULL"),
+      Ok(AnionValue::String(Some(x))) => println!("String {}", x),
+      Ok(AnionValue::String(None)) => println!("String NULL"),
+      Ok(AnionValue::Null) => println!("NULL"),
+      Ok(other) => println!("{}", other),
+      Err(err) => println!("Error: {}", err),
     }
   }
 }
@@ -0,0 +1,1168 @@
+//! Ion binary encoding
+//!
+//! Binary values start with a type descriptor byte: the type code in the
+//! high nibble and the length of the representation in the low nibble
+//! (14 means the length follows as a VarUInt, 15 means null).
+//!
+//! Values of every type can be decoded, and `BinaryReader` reads a
+//! stream of them as data arrives. Only decimals can be encoded so far.
+
+use super::{AnionValue, Decimal, Int, Timestamp, TimestampPrecision};
+use reader::{Limit, ParseError, ReaderOptions};
+use symbols::{SymbolTable, symbol_table_fields};
+use timestamp::days_in_month;
+
+use num_bigint::{BigInt, Sign};
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+pub(crate) const NULL_TYPE: u8 = 0;
+pub(crate) const BOOL_TYPE: u8 = 1;
+pub(crate) const POS_INT_TYPE: u8 = 2;
+pub(crate) const NEG_INT_TYPE: u8 = 3;
+pub(crate) const FLOAT_TYPE: u8 = 4;
+pub(crate) const DECIMAL_TYPE: u8 = 5;
+pub(crate) const TIMESTAMP_TYPE: u8 = 6;
+pub(crate) const SYMBOL_TYPE: u8 = 7;
+pub(crate) const STRING_TYPE: u8 = 8;
+pub(crate) const CLOB_TYPE: u8 = 9;
+pub(crate) const BLOB_TYPE: u8 = 10;
+pub(crate) const LIST_TYPE: u8 = 11;
+pub(crate) const SEXP_TYPE: u8 = 12;
+pub(crate) const STRUCT_TYPE: u8 = 13;
+pub(crate) const ANNOTATION_TYPE: u8 = 14;
+
+const LENGTH_FOLLOWS: u8 = 14;
+const NULL_LENGTH: u8 = 15;
+
+/// Start of every binary ion stream; it may appear again between top
+/// level values, resetting the symbol table
+pub const VERSION_MARKER: [u8; 4] = [0xe0, 0x01, 0x00, 0xea];
+
+/// Error decoding binary ion
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum BinaryError {
+  /// The data ends part way through a value
+  UnexpectedEnd,
+
+  /// The type descriptor isn't the one expected: (expected type, found byte)
+  WrongType(u8, u8),
+
+  /// A field is too large for this implementation
+  Overflow,
+
+  /// The data isn't valid binary ion: (the reason)
+  Invalid(String),
+
+  /// A symbol ID with no symbol defined for it
+  UnknownSymbolId(usize),
+
+  /// A local symbol table which can't be used: (the reason)
+  InvalidSymbolTable(String),
+
+  /// More data is needed to finish the next value; not a failure while
+  /// data is still arriving (see `BinaryReader`)
+  Incomplete,
+
+  /// Reading the input failed: (the error's kind, its message)
+  Io(io::ErrorKind, String),
+
+  /// The input goes beyond one of the limits in `ReaderOptions`
+  LimitExceeded(Limit),
+}
+
+impl fmt::Display for BinaryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    match *self {
+      BinaryError::UnexpectedEnd => write!(f, "unexpected end of binary data"),
+      BinaryError::WrongType(expected, found) => {
+        write!(f, "expected type {} value, found type descriptor 0x{:02x}", expected, found)
+      },
+      BinaryError::Overflow => write!(f, "binary field too large"),
+      BinaryError::Invalid(ref reason) => write!(f, "invalid binary ion: {}", reason),
+      BinaryError::UnknownSymbolId(sid) => write!(f, "no symbol defined for symbol ID ${}", sid),
+      BinaryError::InvalidSymbolTable(ref reason) => write!(f, "invalid symbol table: {}", reason),
+      BinaryError::Incomplete => write!(f, "more data is needed to finish the value"),
+      BinaryError::Io(_, ref message) => write!(f, "error reading input: {}", message),
+      BinaryError::LimitExceeded(limit) => write!(f, "input exceeds the limit on {}", limit),
+    }
+  }
+}
+
+impl Error for BinaryError {
+  fn description(&self) -> &str
+  {
+    match *self {
+      BinaryError::UnexpectedEnd => "unexpected end of binary data",
+      BinaryError::WrongType(..) => "wrong type descriptor",
+      BinaryError::Overflow => "binary field too large",
+      BinaryError::Invalid(_) => "invalid binary ion",
+      BinaryError::UnknownSymbolId(_) => "undefined symbol ID",
+      BinaryError::InvalidSymbolTable(_) => "invalid symbol table",
+      BinaryError::Incomplete => "incomplete input",
+      BinaryError::Io(..) => "error reading input",
+      BinaryError::LimitExceeded(_) => "input limit exceeded",
+    }
+  }
+}
+
+/// Symbol table and limit errors, which are shared with the text reader
+impl From<ParseError> for BinaryError {
+  fn from(error: ParseError) -> Self
+  {
+    match error {
+      ParseError::UnknownSymbolId(sid) => BinaryError::UnknownSymbolId(sid),
+      ParseError::InvalidSymbolTable(reason) => BinaryError::InvalidSymbolTable(reason),
+      ParseError::LimitExceeded(limit) => BinaryError::LimitExceeded(limit),
+      other => BinaryError::Invalid(other.to_string()),
+    }
+  }
+}
+
+pub(crate) fn invalid(reason: &str) -> BinaryError
+{
+  BinaryError::Invalid(String::from(reason))
+}
+
+/// Fail with `limit` if `value` is over `max`
+pub(crate) fn check_limit(value: usize, max: usize, limit: Limit) -> Result<(), BinaryError>
+{
+  if value > max { Err(BinaryError::LimitExceeded(limit)) } else { Ok(()) }
+}
+
+/// Append the binary encoding of a decimal, or `null.decimal`
+pub fn encode_decimal(decimal: Option<&Decimal>, out: &mut Vec<u8>)
+{
+  let decimal = match decimal {
+    Some(decimal) => decimal,
+    None => return out.push(DECIMAL_TYPE << 4 | NULL_LENGTH),
+  };
+
+  // 0d0 has an empty representation
+  if decimal.is_zero() && !decimal.is_sign_negative() && decimal.exponent() == 0 {
+    return out.push(DECIMAL_TYPE << 4);
+  }
+
+  let mut representation = Vec::new();
+  write_var_int(decimal.exponent(), &mut representation);
+  if !decimal.is_zero() || decimal.is_sign_negative() {
+    write_int(decimal.coefficient(), decimal.is_sign_negative(), &mut representation);
+  }
+
+  write_descriptor(DECIMAL_TYPE, representation.len(), out);
+  out.extend(representation);
+}
+
+/// Decode a decimal value from the start of `data`, returning it and the
+/// number of bytes used
+pub fn decode_decimal(data: &[u8]) -> Result<(Option<Decimal>, usize), BinaryError>
+{
+  let (length, start) = match read_descriptor(DECIMAL_TYPE, data)? {
+    (None, used) => return Ok((None, used)),
+    (Some(length), used) => (length, used),
+  };
+
+  let representation = data.get(start..start + length).ok_or(BinaryError::UnexpectedEnd)?;
+  if representation.is_empty() {
+    return Ok((Some(Decimal::new(BigInt::from(0), 0)), start));
+  }
+
+  let (exponent, used) = read_var_int(representation)?;
+  let (coefficient, negative) = read_int(&representation[used..]);
+
+  let decimal = if negative && coefficient.sign() == Sign::NoSign {
+    Decimal::negative_zero(exponent)
+  } else {
+    Decimal::new(coefficient, exponent)
+  };
+  return Ok((Some(decimal), start + length));
+}
+
+fn write_descriptor(type_code: u8, length: usize, out: &mut Vec<u8>)
+{
+  if length < LENGTH_FOLLOWS as usize {
+    out.push(type_code << 4 | length as u8);
+  } else {
+    out.push(type_code << 4 | LENGTH_FOLLOWS);
+    write_var_uint(length as u64, out);
+  }
+}
+
+/// Read a type descriptor and length, checking the type; `None` for a
+/// typed null
+fn read_descriptor(type_code: u8, data: &[u8]) -> Result<(Option<usize>, usize), BinaryError>
+{
+  let descriptor = *data.first().ok_or(BinaryError::UnexpectedEnd)?;
+  if descriptor >> 4 != type_code {
+    return Err(BinaryError::WrongType(type_code, descriptor));
+  }
+  let header = read_header(data)?;
+  return Ok((header.length, header.header_length));
+}
+
+/// A value's type descriptor and where its representation lies
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Header {
+  pub(crate) type_code: u8,
+
+  /// Low nibble of the type descriptor
+  pub(crate) length_code: u8,
+
+  /// Length of the representation; `None` for nulls
+  pub(crate) length: Option<usize>,
+
+  /// Bytes before the representation
+  pub(crate) header_length: usize,
+}
+
+impl Header {
+  /// Bytes used by the whole value; an error if it's too large to index
+  pub(crate) fn end(&self) -> Result<usize, BinaryError>
+  {
+    self.header_length.checked_add(self.length.unwrap_or(0)).ok_or(BinaryError::Overflow)
+  }
+}
+
+pub(crate) fn read_header(data: &[u8]) -> Result<Header, BinaryError>
+{
+  let descriptor = *data.first().ok_or(BinaryError::UnexpectedEnd)?;
+  let (type_code, length_code) = (descriptor >> 4, descriptor & 0x0f);
+
+  let (length, header_length) = match (type_code, length_code) {
+    (15, _) => return Err(BinaryError::Invalid(format!("reserved type descriptor 0x{:02x}", descriptor))),
+    (_, NULL_LENGTH) => (None, 1),
+    // a bool's value is its length code
+    (BOOL_TYPE, _) => (Some(0), 1),
+    // a length code of 1 marks a struct with sorted fields
+    (STRUCT_TYPE, 1) | (_, LENGTH_FOLLOWS) => {
+      let (length, used) = read_var_uint(&data[1..])?;
+      (Some(usize::try_from(length).map_err(|_| BinaryError::Overflow)?), 1 + used)
+    },
+    (_, length) => (Some(length as usize), 1),
+  };
+
+  return Ok(Header {
+    type_code: type_code,
+    length_code: length_code,
+    length: length,
+    header_length: header_length,
+  });
+}
+
+/// Decode the value at the start of `data`, looking up symbol IDs in
+/// `symbols` and keeping to the limits of `options`; returns the value, or
+/// `None` for padding, and the bytes used
+pub(crate) fn decode_value(data: &[u8], symbols: &SymbolTable, options: &ReaderOptions)
+  -> Result<(Option<AnionValue>, usize), BinaryError>
+{
+  decode_nested(data, symbols, options, 0)
+}
+
+/// `decode_value` for a value inside `depth` containers
+pub(crate) fn decode_nested(data: &[u8], symbols: &SymbolTable, options: &ReaderOptions, depth: usize)
+  -> Result<(Option<AnionValue>, usize), BinaryError>
+{
+  let header = read_header(data)?;
+  let end = header.end()?;
+  let representation = data.get(header.header_length..end).ok_or(BinaryError::UnexpectedEnd)?;
+  let null = header.length.is_none();
+  check_value_limits(&header, representation, options, depth)?;
+
+  let value = match header.type_code {
+    NULL_TYPE if null => AnionValue::Null,
+    NULL_TYPE => return Ok((None, end)),
+    BOOL_TYPE => {
+      match header.length_code {
+        0 => AnionValue::Boolean(Some(false)),
+        1 => AnionValue::Boolean(Some(true)),
+        NULL_LENGTH => AnionValue::Boolean(None),
+        _ => return Err(invalid("bool with a length")),
+      }
+    },
+    POS_INT_TYPE | NEG_INT_TYPE if null => AnionValue::Integer(None),
+    POS_INT_TYPE => AnionValue::Integer(Some(read_uint_value(representation, false)?)),
+    NEG_INT_TYPE => AnionValue::Integer(Some(read_uint_value(representation, true)?)),
+    FLOAT_TYPE if null => AnionValue::Float(None),
+    FLOAT_TYPE => AnionValue::Float(Some(read_float(representation)?)),
+    DECIMAL_TYPE => AnionValue::Decimal(decode_decimal(&data[..end])?.0),
+    TIMESTAMP_TYPE if null => AnionValue::Timestamp(None),
+    TIMESTAMP_TYPE => {
+      let timestamp = read_timestamp(representation)?;
+      check_limit(timestamp.fraction.as_ref().map_or(0, String::len), options.max_fraction_digits, Limit::FractionDigits)?;
+      AnionValue::Timestamp(Some(timestamp))
+    },
+    SYMBOL_TYPE if null => AnionValue::Symbol(None),
+    SYMBOL_TYPE => AnionValue::Symbol(Some(symbol_text(read_uint(representation)?, symbols)?)),
+    STRING_TYPE if null => AnionValue::String(None),
+    STRING_TYPE => AnionValue::String(Some(String::from_utf8(representation.to_vec()).map_err(|_| invalid("string isn't UTF-8"))?)),
+    CLOB_TYPE => AnionValue::Clob(if null { None } else { Some(representation.to_vec()) }),
+    BLOB_TYPE => AnionValue::Blob(if null { None } else { Some(representation.to_vec()) }),
+    LIST_TYPE if null => AnionValue::List(None),
+    LIST_TYPE => AnionValue::List(Some(read_sequence(representation, symbols, options, depth + 1)?)),
+    SEXP_TYPE if null => AnionValue::SExp(None),
+    SEXP_TYPE => AnionValue::SExp(Some(read_sequence(representation, symbols, options, depth + 1)?)),
+    STRUCT_TYPE if null => AnionValue::Struct(None),
+    STRUCT_TYPE => AnionValue::Struct(Some(read_fields(representation, symbols, options, depth + 1)?)),
+    _ if null || header.length_code == 0 => return Err(invalid("annotation wrapper without a length")),
+    _ => read_annotated(representation, symbols, options, depth)?,
+  };
+  return Ok((Some(value), end));
+}
+
+/// Check the limits on the size of a non-null value, which can be done
+/// from its header and representation before decoding it
+pub(crate) fn check_value_limits(header: &Header, representation: &[u8], options: &ReaderOptions, depth: usize)
+  -> Result<(), BinaryError>
+{
+  if header.length.is_none() {
+    return Ok(());
+  }
+  match header.type_code {
+    POS_INT_TYPE | NEG_INT_TYPE => check_digits(representation, false, options.max_int_digits),
+    DECIMAL_TYPE => {
+      if representation.is_empty() {
+        // 0d0
+        return Ok(());
+      }
+      let (exponent, exponent_length) = read_var_int(representation)?;
+      let magnitude = usize::try_from((exponent as i128).abs()).unwrap_or(usize::max_value());
+      check_limit(magnitude, options.max_exponent, Limit::Exponent)?;
+      check_digits(&representation[exponent_length..], true, options.max_int_digits)
+    },
+    STRING_TYPE => check_limit(representation.len(), options.max_string_length, Limit::StringLength),
+    CLOB_TYPE | BLOB_TYPE => check_limit(representation.len(), options.max_lob_length, Limit::LobLength),
+    LIST_TYPE | SEXP_TYPE | STRUCT_TYPE => check_limit(depth + 1, options.max_depth, Limit::Depth),
+    _ => Ok(()),
+  }
+}
+
+/// Check the decimal digits of a big-endian magnitude against `max`;
+/// `signed` for an Int, whose first bit is its sign
+fn check_digits(magnitude: &[u8], signed: bool, max: usize) -> Result<(), BinaryError>
+{
+  let byte = |i: usize| if signed && i == 0 { magnitude[0] & 0x7f } else { magnitude[i] };
+  let first = match (0..magnitude.len()).find(|&i| byte(i) != 0) {
+    Some(first) => first,
+    None => return check_limit(1, max, Limit::IntDigits),
+  };
+  let bits = (magnitude.len() - first) * 8 - byte(first).leading_zeros() as usize;
+
+  // it's at least 2^(bits - 1), and log10(2) is a little over 0.30102, so
+  // this may be one digit short; only then, when it's short, is it
+  // converted to count exactly
+  let digits = (bits - 1) * 30102 / 100000 + 1;
+  if digits != max {
+    return check_limit(digits, max, Limit::IntDigits);
+  }
+  let mut bytes = magnitude.to_vec();
+  if signed {
+    bytes[0] &= 0x7f;
+  }
+  let digits = BigInt::from_bytes_be(Sign::Plus, &bytes).to_str_radix(10).len();
+  return check_limit(digits, max, Limit::IntDigits);
+}
+
+/// Text of a symbol ID; symbols with unknown text are `$0`
+fn symbol_text(sid: u64, symbols: &SymbolTable) -> Result<String, BinaryError>
+{
+  let sid = usize::try_from(sid).map_err(|_| BinaryError::Overflow)?;
+  let text = symbols.text(sid)?.unwrap_or(::UNKNOWN_SYMBOL_TEXT);
+  return Ok(String::from(text));
+}
+
+/// The values filling `data`, skipping padding
+fn read_sequence(data: &[u8], symbols: &SymbolTable, options: &ReaderOptions, depth: usize) -> Result<Vec<AnionValue>, BinaryError>
+{
+  let mut values = Vec::new();
+  let mut offset = 0;
+  while offset < data.len() {
+    let (value, used) = decode_nested(&data[offset..], symbols, options, depth)?;
+    if let Some(value) = value {
+      check_limit(values.len() + 1, options.max_container_length, Limit::ContainerLength)?;
+      values.push(value);
+    }
+    offset += used;
+  }
+  return Ok(values);
+}
+
+/// Struct fields filling `data`: each a VarUInt symbol ID then a value
+fn read_fields(data: &[u8], symbols: &SymbolTable, options: &ReaderOptions, depth: usize)
+  -> Result<Vec<(String, AnionValue)>, BinaryError>
+{
+  let mut fields = Vec::new();
+  let mut offset = 0;
+  while offset < data.len() {
+    let (sid, used) = read_var_uint(&data[offset..])?;
+    offset += used;
+    let (value, used) = decode_nested(&data[offset..], symbols, options, depth)?;
+    offset += used;
+    if let Some(value) = value {
+      check_limit(fields.len() + 1, options.max_container_length, Limit::ContainerLength)?;
+      fields.push((symbol_text(sid, symbols)?, value));
+    }
+  }
+  return Ok(fields);
+}
+
+fn read_annotated(data: &[u8], symbols: &SymbolTable, options: &ReaderOptions, depth: usize) -> Result<AnionValue, BinaryError>
+{
+  let (annotation_ids, wrapped) = split_annotations(data)?;
+  let annotations = annotation_texts(annotation_ids, symbols)?;
+  match decode_nested(wrapped, symbols, options, depth)? {
+    (Some(value), _) => Ok(AnionValue::Annotated(annotations, Box::new(value))),
+    (None, _) => Err(invalid("annotated padding")),
+  }
+}
+
+/// Split an annotation wrapper's representation into the symbol IDs of
+/// the annotations and the wrapped value. The representation is the length
+/// of the annotations, their IDs as VarUInts, then the value, which must
+/// fill the rest.
+pub(crate) fn split_annotations(data: &[u8]) -> Result<(&[u8], &[u8]), BinaryError>
+{
+  let (length, start) = read_var_uint(data)?;
+  let end = usize::try_from(length).ok().and_then(|length| length.checked_add(start)).ok_or(BinaryError::Overflow)?;
+  let annotation_ids = data.get(start..end).ok_or(BinaryError::UnexpectedEnd)?;
+  if annotation_ids.is_empty() {
+    return Err(invalid("annotation wrapper without annotations"));
+  }
+
+  let wrapped = &data[end..];
+  let header = read_header(wrapped)?;
+  if header.type_code == ANNOTATION_TYPE {
+    return Err(invalid("annotation wrapper inside another"));
+  }
+  if header.end()? != wrapped.len() {
+    return Err(invalid("annotation wrapper length doesn't match its value"));
+  }
+  return Ok((annotation_ids, wrapped));
+}
+
+/// Text of the annotations from `split_annotations`
+pub(crate) fn annotation_texts(annotation_ids: &[u8], symbols: &SymbolTable) -> Result<Vec<String>, BinaryError>
+{
+  let mut annotations = Vec::new();
+  let mut offset = 0;
+  while offset < annotation_ids.len() {
+    let (sid, used) = read_var_uint(&annotation_ids[offset..])?;
+    annotations.push(symbol_text(sid, symbols)?);
+    offset += used;
+  }
+  return Ok(annotations);
+}
+
+/// UInt: a big-endian unsigned value, at most 64 bits here
+fn read_uint(data: &[u8]) -> Result<u64, BinaryError>
+{
+  if data.len() > 8 {
+    return Err(BinaryError::Overflow);
+  }
+  return Ok(data.iter().fold(0, |value, &byte| value << 8 | byte as u64));
+}
+
+/// The value of an int's magnitude, negated for negative ints
+fn read_uint_value(data: &[u8], negative: bool) -> Result<Int, BinaryError>
+{
+  if negative && data.iter().all(|&byte| byte == 0) {
+    return Err(invalid("negative zero int"));
+  }
+  if data.len() <= 8 {
+    let magnitude = read_uint(data)?;
+    if !negative {
+      return Ok(Int::from(magnitude));
+    }
+    if magnitude <= 1 << 63 {
+      return Ok(Int::from((magnitude as i64).wrapping_neg()));
+    }
+  }
+  let sign = if negative { Sign::Minus } else { Sign::Plus };
+  return Ok(Int::from(BigInt::from_bytes_be(sign, data)));
+}
+
+fn read_float(data: &[u8]) -> Result<f64, BinaryError>
+{
+  let bits = data.iter().fold(0u64, |bits, &byte| bits << 8 | byte as u64);
+  match data.len() {
+    0 => Ok(0.0),
+    4 => Ok(f32::from_bits(bits as u32) as f64),
+    8 => Ok(f64::from_bits(bits)),
+    _ => Err(invalid("float which isn't 0, 4 or 8 bytes")),
+  }
+}
+
+/// A timestamp's representation: its offset as a VarInt (negative zero
+/// for unknown), year, month, day, hour, minute and second as VarUInts,
+/// then the fraction's exponent as a VarInt and coefficient as an Int.
+/// Fields after the year may be left out, but hour and minute go together.
+///
+/// The fields are UTC, so they're shifted by the offset to local time.
+fn read_timestamp(data: &[u8]) -> Result<Timestamp, BinaryError>
+{
+  let unknown_offset = data.first() == Some(&0xc0);
+  let (offset, mut used) = read_var_int(data)?;
+
+  let mut fields = Vec::new();
+  while fields.len() < 6 && used < data.len() {
+    let (field, length) = read_var_uint(&data[used..])?;
+    fields.push(field);
+    used += length;
+  }
+
+  let precision = match fields.len() {
+    1 => TimestampPrecision::Year,
+    2 => TimestampPrecision::Month,
+    3 => TimestampPrecision::Day,
+    5 => TimestampPrecision::Minute,
+    6 => TimestampPrecision::Second,
+    _ => return Err(invalid("timestamp without a year, or an hour without minutes")),
+  };
+
+  let fraction = if used < data.len() {
+    let (exponent, length) = read_var_int(&data[used..])?;
+    let (coefficient, negative) = read_int(&data[used + length..]);
+    fraction_digits(exponent, coefficient, negative)?
+  } else {
+    None
+  };
+
+  let field = |i: usize, default: u64, max: u64| {
+    let value = fields.get(i).cloned().unwrap_or(default);
+    if value > max { Err(invalid("timestamp field out of range")) } else { Ok(value) }
+  };
+  let (year, month) = (field(0, 1, 9999)?, field(1, 1, 12)?);
+  let timestamp = Timestamp {
+    precision: precision,
+    year: year as u16,
+    month: month as u8,
+    day: field(2, 1, days_in_month(year as i64, month as i64) as u64)? as u8,
+    hour: field(3, 0, 23)? as u8,
+    minute: field(4, 0, 59)? as u8,
+    second: field(5, 0, 59)? as u8,
+    fraction: fraction,
+    offset: None,
+  };
+  if year == 0 || month == 0 || timestamp.day == 0 {
+    return Err(invalid("timestamp field out of range"));
+  }
+
+  if unknown_offset || precision < TimestampPrecision::Minute {
+    return Ok(timestamp);
+  }
+  if offset.abs() >= 24 * 60 {
+    return Err(invalid("timestamp offset out of range"));
+  }
+  return shift_minutes(timestamp, offset).map(|timestamp| Timestamp { offset: Some(offset as i16), ..timestamp });
+}
+
+/// Most zeros a fraction of a second may have beyond its coefficient's
+/// digits; the exponent is input, so it can't be trusted to size a string
+const MAX_FRACTION_PADDING: usize = 100;
+
+/// Digits of a fraction of a second with this exponent and coefficient,
+/// keeping trailing zeros; `None` for no fraction
+fn fraction_digits(exponent: i64, coefficient: BigInt, negative: bool) -> Result<Option<String>, BinaryError>
+{
+  let zero = coefficient.sign() == Sign::NoSign;
+  if negative && !zero {
+    return Err(invalid("negative fraction of a second"));
+  }
+  if exponent >= 0 {
+    return if zero { Ok(None) } else { Err(invalid("fraction of a second greater than one")) };
+  }
+
+  let places = exponent.checked_neg().and_then(|places| usize::try_from(places).ok()).ok_or(BinaryError::Overflow)?;
+  let digits = coefficient.to_string();
+  if digits.len() > places {
+    return Err(invalid("fraction of a second greater than one"));
+  }
+  if places - digits.len() > MAX_FRACTION_PADDING {
+    return Err(invalid("fraction of a second too precise"));
+  }
+  return Ok(Some(format!("{}{}", "0".repeat(places - digits.len()), digits)));
+}
+
+/// Move a timestamp's date and time by `minutes`
+fn shift_minutes(timestamp: Timestamp, minutes: i64) -> Result<Timestamp, BinaryError>
+{
+  let days = days_from_civil(timestamp.year as i64, timestamp.month as i64, timestamp.day as i64);
+  let total = days * 1440 + timestamp.hour as i64 * 60 + timestamp.minute as i64 + minutes;
+  let (days, minute_of_day) = (floor_div(total, 1440), total - floor_div(total, 1440) * 1440);
+
+  let (year, month, day) = civil_from_days(days);
+  if year < 1 || year > 9999 {
+    return Err(invalid("timestamp out of range"));
+  }
+  return Ok(Timestamp {
+    year: year as u16,
+    month: month as u8,
+    day: day as u8,
+    hour: (minute_of_day / 60) as u8,
+    minute: (minute_of_day % 60) as u8,
+    ..timestamp
+  });
+}
+
+fn floor_div(a: i64, b: i64) -> i64
+{
+  if a % b < 0 { a / b - 1 } else { a / b }
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64
+{
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = floor_div(year, 400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  return era * 146097 + day_of_era - 719468;
+}
+
+/// The date `days` after 1970-01-01: (year, month, day)
+fn civil_from_days(days: i64) -> (i64, i64, i64)
+{
+  let days = days + 719468;
+  let era = floor_div(days, 146097);
+  let day_of_era = days - era * 146097;
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  return (year, month, day);
+}
+
+/// Reads binary ion values as data arrives.
+///
+/// Data is fed in chunks with `feed`; until `finish` is called,
+/// `next_value` returns `BinaryError::Incomplete` when the next value
+/// hasn't all arrived. Values are length-prefixed, so a value is only
+/// decoded once all of it is buffered, and never twice.
+///
+/// The stream must start with the version marker. Local symbol tables
+/// are applied and dropped, and padding is skipped.
+pub struct BinaryReader {
+  buffer: Vec<u8>,
+
+  /// Index of the next value in `buffer`
+  next: usize,
+  symbols: SymbolTable,
+  options: ReaderOptions,
+  started: bool,
+  finished: bool,
+  failed: bool,
+}
+
+impl BinaryReader {
+  pub fn new() -> BinaryReader
+  {
+    BinaryReader::with_options(ReaderOptions::default())
+  }
+
+  /// A reader keeping to the limits of `options`
+  pub fn with_options(options: ReaderOptions) -> BinaryReader
+  {
+    BinaryReader {
+      buffer: Vec::new(),
+      next: 0,
+      symbols: SymbolTable::system(),
+      options: options,
+      started: false,
+      finished: false,
+      failed: false,
+    }
+  }
+
+  /// Add the next chunk of input
+  pub fn feed(&mut self, data: &[u8])
+  {
+    self.buffer.drain(..self.next);
+    self.next = 0;
+    self.buffer.extend_from_slice(data);
+  }
+
+  /// Mark the end of input, after which values must be complete
+  pub fn finish(&mut self)
+  {
+    self.finished = true;
+  }
+
+  /// The next top level value, or `None` at the end of input
+  pub fn next_value(&mut self) -> Result<Option<AnionValue>, BinaryError>
+  {
+    loop {
+      let data = &self.buffer[self.next..];
+      if data.is_empty() {
+        return if self.finished { Ok(None) } else { Err(BinaryError::Incomplete) };
+      }
+
+      if !self.started || data[0] == VERSION_MARKER[0] {
+        let available = data.len().min(VERSION_MARKER.len());
+        if data[..available] != VERSION_MARKER[..available] {
+          let reason = if self.started { "unsupported version marker" } else { "missing version marker" };
+          return Err(invalid(reason));
+        }
+        if available < VERSION_MARKER.len() {
+          return Err(self.incomplete());
+        }
+        self.started = true;
+        self.symbols = SymbolTable::system();
+        self.next += VERSION_MARKER.len();
+        continue;
+      }
+
+      let end = match read_header(data) {
+        Ok(header) => header.end()?,
+        Err(BinaryError::UnexpectedEnd) => return Err(self.incomplete()),
+        Err(error) => return Err(error),
+      };
+      if data.len() < end {
+        return Err(self.incomplete());
+      }
+      let (value, used) = decode_value(&data[..end], &self.symbols, &self.options)?;
+      self.next += used;
+
+      let value = match value {
+        Some(value) => value,
+        None => continue,
+      };
+      if let Some(fields) = symbol_table_fields(&value) {
+        self.symbols = self.symbols.local(fields, self.options.max_symbols)?;
+        continue;
+      }
+      return Ok(Some(value));
+    }
+  }
+
+  /// The error for a value cut short
+  fn incomplete(&self) -> BinaryError
+  {
+    if self.finished { BinaryError::UnexpectedEnd } else { BinaryError::Incomplete }
+  }
+}
+
+impl Default for BinaryReader {
+  fn default() -> BinaryReader
+  {
+    BinaryReader::new()
+  }
+}
+
+/// As an iterator, reading stops after the first error; running out of
+/// data also ends the iteration, but it continues once more is fed
+impl Iterator for BinaryReader {
+  type Item = Result<AnionValue, BinaryError>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    if self.failed {
+      return None;
+    }
+    match self.next_value() {
+      Ok(value) => value.map(Ok),
+      Err(BinaryError::Incomplete) => None,
+      Err(error) => {
+        self.failed = true;
+        Some(Err(error))
+      },
+    }
+  }
+}
+
+/// VarUInt: big-endian groups of 7 bits, the last byte flagged by its
+/// high bit
+fn write_var_uint(mut value: u64, out: &mut Vec<u8>)
+{
+  let mut groups = vec![0x80 | (value & 0x7f) as u8];
+  value >>= 7;
+  while value != 0 {
+    groups.push((value & 0x7f) as u8);
+    value >>= 7;
+  }
+  out.extend(groups.into_iter().rev());
+}
+
+pub(crate) fn read_var_uint(data: &[u8]) -> Result<(u64, usize), BinaryError>
+{
+  let mut value: u64 = 0;
+  for (i, &byte) in data.iter().enumerate() {
+    if value >> 57 != 0 {
+      return Err(BinaryError::Overflow);
+    }
+    value = value << 7 | (byte & 0x7f) as u64;
+    if byte & 0x80 != 0 {
+      return Ok((value, i + 1));
+    }
+  }
+  return Err(BinaryError::UnexpectedEnd);
+}
+
+/// VarInt: a VarUInt of the magnitude, with the sign in the 0x40 bit of
+/// the first byte
+fn write_var_int(value: i64, out: &mut Vec<u8>)
+{
+  let mut magnitude = (value as i128).abs() as u64;
+  let mut groups = vec![(magnitude & 0x7f) as u8 | 0x80];
+  magnitude >>= 7;
+  while magnitude != 0 {
+    groups.push((magnitude & 0x7f) as u8);
+    magnitude >>= 7;
+  }
+
+  // make room for the sign bit
+  if groups.last().unwrap() & 0x40 != 0 {
+    groups.push(0);
+  }
+  if value < 0 {
+    *groups.last_mut().unwrap() |= 0x40;
+  }
+  out.extend(groups.into_iter().rev());
+}
+
+fn read_var_int(data: &[u8]) -> Result<(i64, usize), BinaryError>
+{
+  let first = *data.first().ok_or(BinaryError::UnexpectedEnd)?;
+  let negative = first & 0x40 != 0;
+
+  let mut magnitude = (first & 0x3f) as u64;
+  let mut used = 1;
+  let mut last = first;
+  while last & 0x80 == 0 {
+    last = *data.get(used).ok_or(BinaryError::UnexpectedEnd)?;
+    if magnitude >> 56 != 0 {
+      return Err(BinaryError::Overflow);
+    }
+    magnitude = magnitude << 7 | (last & 0x7f) as u64;
+    used += 1;
+  }
+
+  if magnitude > i64::max_value() as u64 {
+    return Err(BinaryError::Overflow);
+  }
+  let value = magnitude as i64;
+  return Ok((if negative { -value } else { value }, used));
+}
+
+/// Int: big-endian sign and magnitude, the sign in the high bit of the
+/// first byte; `negative` allows writing negative zero
+fn write_int(value: &BigInt, negative: bool, out: &mut Vec<u8>)
+{
+  let (_, mut magnitude) = value.to_bytes_be();
+  if magnitude == [0] {
+    magnitude.clear();
+  }
+  if magnitude.first().map_or(true, |&byte| byte & 0x80 != 0) {
+    magnitude.insert(0, 0);
+  }
+  if negative {
+    magnitude[0] |= 0x80;
+  }
+  out.extend(magnitude);
+}
+
+/// Read an Int filling all of `data`; returns the value and its sign,
+/// which may be negative for zero
+fn read_int(data: &[u8]) -> (BigInt, bool)
+{
+  let mut magnitude = data.to_vec();
+  let negative = magnitude.first().map_or(false, |&byte| byte & 0x80 != 0);
+  if negative {
+    magnitude[0] &= 0x7f;
+  }
+
+  let sign = if negative { Sign::Minus } else { Sign::Plus };
+  return (BigInt::from_bytes_be(sign, &magnitude), negative);
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use std::str::FromStr;
+
+  fn round_trip(text: &str) -> (Vec<u8>, Decimal)
+  {
+    let decimal = Decimal::from_str(text).unwrap();
+    let mut encoded = Vec::new();
+    encode_decimal(Some(&decimal), &mut encoded);
+
+    let (decoded, used) = decode_decimal(&encoded).unwrap();
+    assert_eq!(used, encoded.len());
+    return (encoded, decoded.unwrap());
+  }
+
+  #[test]
+  fn decimal_encoding()
+  {
+    assert_eq!(round_trip("0d0").0, vec![0x50]);
+    assert_eq!(round_trip("-0d0").0, vec![0x52, 0x80, 0x80]);
+    assert_eq!(round_trip("1.00").0, vec![0x52, 0xc2, 0x64]);
+    assert_eq!(round_trip("-1.5").0, vec![0x52, 0xc1, 0x8f]);
+    assert_eq!(round_trip("128d0").0, vec![0x53, 0x80, 0x00, 0x80]);
+
+    let mut null = Vec::new();
+    encode_decimal(None, &mut null);
+    assert_eq!(null, vec![0x5f]);
+    assert_eq!(decode_decimal(&null), Ok((None, 1)));
+  }
+
+  #[test]
+  fn decimals_round_trip_exactly()
+  {
+    let texts = ["1.00", "1.0", "-0d-2", "0d-2", "-0d5", "12345678901234567890.12345", "-7d300", "1d-200"];
+    for &text in texts.iter() {
+      let (_, decoded) = round_trip(text);
+      assert!(decoded.ion_eq(&Decimal::from_str(text).unwrap()), "{}", text);
+    }
+  }
+
+  #[test]
+  fn decode_errors()
+  {
+    assert_eq!(decode_decimal(&[]), Err(BinaryError::UnexpectedEnd));
+    assert_eq!(decode_decimal(&[0x53, 0xc2]), Err(BinaryError::UnexpectedEnd));
+    assert_eq!(decode_decimal(&[0x21, 0x01]), Err(BinaryError::WrongType(5, 0x21)));
+  }
+
+  fn decode(data: &[u8]) -> Result<AnionValue, BinaryError>
+  {
+    let (value, used) = decode_value(data, &SymbolTable::system(), &ReaderOptions::default())?;
+    assert_eq!(used, data.len());
+    return Ok(value.unwrap());
+  }
+
+  #[test]
+  fn scalars()
+  {
+    assert_eq!(decode(&[0x0f]), Ok(AnionValue::Null));
+    assert_eq!(decode(&[0x11]), Ok(AnionValue::from(true)));
+    assert_eq!(decode(&[0x1f]), Ok(AnionValue::Boolean(None)));
+    assert_eq!(decode(&[0x20]), Ok(AnionValue::from(0)));
+    assert_eq!(decode(&[0x22, 0x01, 0x00]), Ok(AnionValue::from(256)));
+    assert_eq!(decode(&[0x31, 0x05]), Ok(AnionValue::from(-5)));
+    assert_eq!(decode(&[0x38, 0x80, 0, 0, 0, 0, 0, 0, 0]), Ok(AnionValue::from(i64::min_value())));
+    assert_eq!(decode(&[0x29, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).map(|v| v.to_string()), Ok(String::from("18446744073709551616")));
+    assert_eq!(decode(&[0x3f]), Ok(AnionValue::Integer(None)));
+    assert_eq!(decode(&[0x40]), Ok(AnionValue::from(0.0)));
+    assert_eq!(decode(&[0x44, 0x3f, 0xc0, 0x00, 0x00]), Ok(AnionValue::from(1.5)));
+    assert_eq!(decode(&[0x48, 0xc0, 0x04, 0, 0, 0, 0, 0, 0]), Ok(AnionValue::from(-2.5)));
+    assert_eq!(decode(&[0x52, 0xc1, 0x0f]), Ok(AnionValue::from(Decimal::from_str("1.5").unwrap())));
+    assert_eq!(decode(&[0x71, 0x04]), Ok(AnionValue::Symbol(Some(String::from("name")))));
+    assert_eq!(decode(&[0x70]), Ok(AnionValue::Symbol(Some(String::from(::UNKNOWN_SYMBOL_TEXT)))));
+    assert_eq!(decode(&[0x83, b'a', b'b', b'c']), Ok(AnionValue::from("abc")));
+    assert_eq!(decode(&[0x92, b'h', b'i']), Ok(AnionValue::Clob(Some(b"hi".to_vec()))));
+    assert_eq!(decode(&[0xaf]), Ok(AnionValue::Blob(None)));
+
+    assert_eq!(decode(&[0x71, 0x0a]), Err(BinaryError::UnknownSymbolId(10)));
+    assert!(decode(&[0x30]).is_err());
+    assert!(decode(&[0x12]).is_err());
+    assert!(decode(&[0x42, 0, 0]).is_err());
+    assert!(decode(&[0x81, 0xff]).is_err());
+    assert!(decode(&[0xf0]).is_err());
+
+    // a length which can't be added to the header's
+    let too_long = [0x8e, 0x01, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0xff];
+    assert_eq!(decode(&too_long), Err(BinaryError::Overflow));
+    let mut reader = BinaryReader::new();
+    reader.feed(&VERSION_MARKER);
+    reader.feed(&too_long);
+    assert_eq!(reader.next_value(), Err(BinaryError::Overflow));
+  }
+
+  #[test]
+  fn containers_and_annotations()
+  {
+    assert_eq!(decode(&[0xb4, 0x21, 0x01, 0x00, 0x20]), Ok(ion!([1, 0])));
+    assert_eq!(decode(&[0xc3, 0x71, 0x04, 0x11]), Ok(ion!((name true))));
+    assert_eq!(decode(&[0xd5, 0x84, 0x21, 0x07, 0x80, 0x00]), Ok(ion!({ name: 7 })));
+    assert_eq!(decode(&[0xd1, 0x83, 0x84, 0x21, 0x07]), Ok(ion!({ name: 7 })));
+    assert_eq!(decode(&[0xe4, 0x81, 0x84, 0x21, 0x01]), Ok(ion!(name::1)));
+    assert_eq!(decode(&[0xee, 0x8c, 0x81, 0x84, 0x89, b'l', b'o', b'n', b'g', b' ', b't', b'e', b'x', b't']),
+               Ok(ion!(name::"long text")));
+
+    assert!(decode(&[0xe3, 0x80, 0x21, 0x01]).is_err());
+    assert!(decode(&[0xe5, 0x81, 0x84, 0x21, 0x01, 0x11]).is_err());
+    assert!(decode(&[0xe6, 0x81, 0x84, 0xe3, 0x81, 0x84, 0x11]).is_err());
+    assert_eq!(decode(&[0xb2, 0x21]), Err(BinaryError::UnexpectedEnd));
+  }
+
+  #[test]
+  fn timestamps()
+  {
+    let utc_midnight = [0x67, 0x80, 0x0f, 0xd0, 0x81, 0x81, 0x80, 0x80];
+    let minute = |year, month, day, hour, minute, offset| Timestamp {
+      precision: TimestampPrecision::Minute,
+      hour: hour,
+      minute: minute,
+      offset: offset,
+      ..Timestamp::day(year, month, day)
+    };
+
+    assert_eq!(decode(&utc_midnight), Ok(AnionValue::Timestamp(Some(minute(2000, 1, 1, 0, 0, Some(0))))));
+
+    // fields are UTC, so they're shifted to local time
+    let mut ahead = utc_midnight;
+    ahead[1] = 0xbc;
+    assert_eq!(decode(&ahead), Ok(AnionValue::Timestamp(Some(minute(2000, 1, 1, 1, 0, Some(60))))));
+    let mut behind = utc_midnight;
+    behind[1] = 0xfc;
+    assert_eq!(decode(&behind), Ok(AnionValue::Timestamp(Some(minute(1999, 12, 31, 23, 0, Some(-60))))));
+    let mut unknown = utc_midnight;
+    unknown[1] = 0xc0;
+    assert_eq!(decode(&unknown), Ok(AnionValue::Timestamp(Some(minute(2000, 1, 1, 0, 0, None)))));
+
+    assert_eq!(decode(&[0x63, 0xc0, 0x0f, 0xd0]), Ok(AnionValue::Timestamp(Some(Timestamp::year(2000)))));
+
+    let fraction = decode(&[0x6a, 0x80, 0x0f, 0xd0, 0x82, 0x9d, 0x8c, 0x9e, 0xad, 0xc3, 0x05]);
+    assert_eq!(fraction, Ok(AnionValue::Timestamp(Some(Timestamp {
+      precision: TimestampPrecision::Second,
+      second: 45,
+      fraction: Some(String::from("005")),
+      ..minute(2000, 2, 29, 12, 30, Some(0))
+    }))));
+
+    // hour without minute, February 30th, a fraction over one
+    assert!(decode(&[0x66, 0x80, 0x0f, 0xd0, 0x81, 0x81, 0x80]).is_err());
+    assert!(decode(&[0x65, 0xc0, 0x0f, 0xd0, 0x82, 0x9e]).is_err());
+    assert!(decode(&[0x6a, 0x80, 0x0f, 0xd0, 0x82, 0x9d, 0x8c, 0x9e, 0xad, 0xc1, 0x0a]).is_err());
+
+    // zero fractions with far too many places
+    for &exponent in &[-102, -(1 << 55), i64::min_value()] {
+      let mut representation = vec![0x80, 0x0f, 0xd0, 0x82, 0x9d, 0x8c, 0x9e, 0xad];
+      write_var_int(exponent, &mut representation);
+      let mut data = vec![0x6e];
+      write_var_uint(representation.len() as u64, &mut data);
+      data.extend_from_slice(&representation);
+      assert!(decode(&data).is_err());
+    }
+  }
+
+  fn stream() -> Vec<u8>
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    // $ion_symbol_table::{symbols: ["x"]}
+    data.extend(&[0xe7, 0x81, 0x83, 0xd4, 0x87, 0xb2, 0x81, b'x']);
+    data.extend(&[0x71, 0x0a, 0x01, 0xff]);
+    data.extend(&[0xe4, 0x81, 0x8a, 0x21, 0x01]);
+    data.extend(&VERSION_MARKER);
+    data.extend(&[0x71, 0x04]);
+    data
+  }
+
+  #[test]
+  fn reading_a_stream()
+  {
+    let mut reader = BinaryReader::new();
+    reader.feed(&stream());
+    reader.finish();
+    let values: Result<Vec<AnionValue>, _> = reader.collect();
+    assert_eq!(values, Ok(vec![ion!(x), ion!(x::1), ion!(name)]));
+
+    // the symbol table is reset by the version marker
+    let mut reset = stream();
+    reset.extend(&[0x71, 0x0a]);
+    let mut reader = BinaryReader::new();
+    reader.feed(&reset);
+    reader.finish();
+    assert_eq!(reader.last(), Some(Err(BinaryError::UnknownSymbolId(10))));
+
+    let mut reader = BinaryReader::new();
+    reader.feed(&[0x21, 0x01]);
+    assert_eq!(reader.next_value(), Err(BinaryError::Invalid(String::from("missing version marker"))));
+  }
+
+  #[test]
+  fn incremental_reading()
+  {
+    let data = stream();
+    for chunk_size in 1..6 {
+      let mut reader = BinaryReader::new();
+      let mut values = Vec::new();
+      for chunk in data.chunks(chunk_size) {
+        reader.feed(chunk);
+        values.extend(reader.by_ref().map(Result::unwrap));
+        assert_eq!(reader.next_value(), Err(BinaryError::Incomplete));
+      }
+      reader.finish();
+      assert_eq!(reader.next_value(), Ok(None));
+      assert_eq!(values, vec![ion!(x), ion!(x::1), ion!(name)], "chunks of {}", chunk_size);
+    }
+
+    let mut reader = BinaryReader::new();
+    reader.feed(&VERSION_MARKER);
+    reader.feed(&[0x83, b'a']);
+    assert_eq!(reader.next_value(), Err(BinaryError::Incomplete));
+    reader.finish();
+    assert_eq!(reader.next_value(), Err(BinaryError::UnexpectedEnd));
+  }
+
+  #[test]
+  fn limits()
+  {
+    let read = |data: &[u8], options: ReaderOptions| {
+      let mut reader = BinaryReader::with_options(options);
+      reader.feed(&VERSION_MARKER);
+      reader.feed(data);
+      reader.finish();
+      reader.next_value()
+    };
+    let defaults = ReaderOptions::default;
+
+    // [[1]]
+    let nested = [0xb3, 0xb2, 0x21, 0x01];
+    assert_eq!(read(&nested, ReaderOptions { max_depth: 2, ..defaults() }), Ok(Some(ion!([[1]]))));
+    assert_eq!(read(&nested, ReaderOptions { max_depth: 1, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::Depth)));
+    // and deep nesting fails by default
+    let mut deep = vec![0xb0];
+    for _ in 0..1000 {
+      let mut list = Vec::new();
+      write_descriptor(LIST_TYPE, deep.len(), &mut list);
+      list.extend(&deep);
+      deep = list;
+    }
+    assert_eq!(read(&deep, defaults()), Err(BinaryError::LimitExceeded(Limit::Depth)));
+
+    // [1, 2, 3] and {a: 1, a: 2}, with padding which doesn't count
+    let list = [0xb7, 0x21, 0x01, 0x21, 0x02, 0x00, 0x21, 0x03];
+    assert!(read(&list, ReaderOptions { max_container_length: 3, ..defaults() }).is_ok());
+    assert_eq!(read(&list, ReaderOptions { max_container_length: 2, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::ContainerLength)));
+    let fields = [0xd6, 0x84, 0x21, 0x01, 0x84, 0x21, 0x02];
+    assert_eq!(read(&fields, ReaderOptions { max_container_length: 1, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::ContainerLength)));
+
+    let string = [0x83, b'a', b'b', b'c'];
+    assert!(read(&string, ReaderOptions { max_string_length: 3, ..defaults() }).is_ok());
+    assert_eq!(read(&string, ReaderOptions { max_string_length: 2, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::StringLength)));
+    let blob = [0xa2, 0x01, 0x02];
+    assert_eq!(read(&blob, ReaderOptions { max_lob_length: 1, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::LobLength)));
+
+    // 1000 has 4 digits, and so does the coefficient of -1000d-1
+    let int = [0x22, 0x03, 0xe8];
+    assert!(read(&int, ReaderOptions { max_int_digits: 4, ..defaults() }).is_ok());
+    assert_eq!(read(&int, ReaderOptions { max_int_digits: 3, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::IntDigits)));
+    let decimal = [0x53, 0xc1, 0x83, 0xe8];
+    assert!(read(&decimal, ReaderOptions { max_int_digits: 4, ..defaults() }).is_ok());
+    assert_eq!(read(&decimal, ReaderOptions { max_int_digits: 3, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::IntDigits)));
+
+    assert_eq!(read(&decimal, ReaderOptions { max_exponent: 0, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::Exponent)));
+    // a fraction of a second of 005
+    let timestamp = [0x6a, 0x80, 0x0f, 0xd0, 0x82, 0x9d, 0x8c, 0x9e, 0xad, 0xc3, 0x05];
+    assert!(read(&timestamp, ReaderOptions { max_fraction_digits: 3, max_int_digits: 0, ..defaults() }).is_ok());
+    assert_eq!(read(&timestamp, ReaderOptions { max_fraction_digits: 2, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::FractionDigits)));
+
+    // 0d0 has no representation at all
+    let zero = AnionValue::Decimal(Some(Decimal::new(BigInt::from(0), 0)));
+    assert_eq!(decode_value(&[0x50], &SymbolTable::system(), &defaults()), Ok((Some(zero.clone()), 1)));
+    assert_eq!(read(&[0x50], defaults()), Ok(Some(zero)));
+
+    // $ion_symbol_table::{symbols: ["a", "b"]}
+    let table = [0xe9, 0x81, 0x83, 0xd6, 0x87, 0xb4, 0x81, b'a', 0x81, b'b'];
+    assert!(read(&table, ReaderOptions { max_symbols: 11, ..defaults() }).is_ok());
+    assert_eq!(read(&table, ReaderOptions { max_symbols: 10, ..defaults() }), Err(BinaryError::LimitExceeded(Limit::Symbols)));
+  }
+}
@@ -0,0 +1,451 @@
+//! Conversions from ion values back to rust types
+//!
+//! `TryFrom` is implemented for both `AnionValue` and `&AnionValue` into
+//! the rust types which convert into an `AnionValue`. A conversion fails
+//! if the value is of another ion type, doesn't fit in the target type,
+//! or is null; convert into an `Option` to accept nulls:
+//!
+//! ```ignore
+//! assert_eq!(u8::try_from(ion!(200)), Ok(200));
+//! assert_eq!(u8::try_from(ion!(300)), Err(ConversionError::OutOfRange));
+//! assert_eq!(u8::try_from(ion!(null.int)), Err(ConversionError::Null));
+//! assert_eq!(Option::<u8>::try_from(ion!(null.int)), Ok(None));
+//! ```
+//!
+//! Both `null` and the typed null of the target's ion type convert to
+//! `None`. Annotations are ignored. Strings convert from both ion strings
+//! and symbols, byte vectors from both blobs and clobs.
+//!
+//! `AnionValue` also converts to and from `NonNullAnionValue`, failing if
+//! the value is or contains a typed null.
+
+use super::{AnionValue, Decimal, Int, NonNullAnionValue};
+
+use num_bigint::BigInt;
+use num_bigdecimal::BigDecimal;
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// Error converting an `AnionValue` into a rust type
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConversionError {
+  /// The value has another ion type: (expected, found)
+  WrongType(&'static str, &'static str),
+
+  /// The value is null, and the target isn't an `Option`
+  Null,
+
+  /// The number doesn't fit in the target type
+  OutOfRange,
+}
+
+impl fmt::Display for ConversionError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    match *self {
+      ConversionError::WrongType(expected, found) => write!(f, "expected {} value, found {}", expected, found),
+      ConversionError::Null => write!(f, "value is null"),
+      ConversionError::OutOfRange => write!(f, "number out of range of target type"),
+    }
+  }
+}
+
+impl Error for ConversionError {
+  fn description(&self) -> &str
+  {
+    match *self {
+      ConversionError::WrongType(..) => "wrong ion type",
+      ConversionError::Null => "null value",
+      ConversionError::OutOfRange => "number out of range",
+    }
+  }
+}
+
+impl AnionValue {
+  /// Name of the value's ion type, as used in typed nulls (`"int"`,
+  /// `"struct"`, ...). Annotations are ignored.
+  pub fn type_name(&self) -> &'static str
+  {
+    use AnionValue::*;
+
+    match *self {
+      Null => "null",
+      Boolean(_) => "bool",
+      Integer(_) => "int",
+      Float(_) => "float",
+      Decimal(_) => "decimal",
+      Timestamp(_) => "timestamp",
+      String(_) => "string",
+      Symbol(_) => "symbol",
+      Blob(_) => "blob",
+      Clob(_) => "clob",
+      List(_) => "list",
+      SExp(_) => "sexp",
+      Struct(_) => "struct",
+      Annotated(_, ref value) => value.type_name(),
+    }
+  }
+}
+
+fn unannotated(mut value: &AnionValue) -> &AnionValue
+{
+  while let AnionValue::Annotated(_, ref inner) = *value {
+    value = inner;
+  }
+  return value;
+}
+
+fn into_unannotated(mut value: AnionValue) -> AnionValue
+{
+  while let AnionValue::Annotated(_, inner) = value {
+    value = *inner;
+  }
+  return value;
+}
+
+//
+// Content of each type of value; Ok(None) if it's null
+//
+
+fn boolean(value: &AnionValue) -> Result<Option<bool>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::Boolean(b) => Ok(b),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("bool", other.type_name())),
+  }
+}
+
+fn integer(value: &AnionValue) -> Result<Option<&Int>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::Integer(ref i) => Ok(i.as_ref()),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("int", other.type_name())),
+  }
+}
+
+fn float(value: &AnionValue) -> Result<Option<f64>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::Float(f) => Ok(f),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("float", other.type_name())),
+  }
+}
+
+fn decimal(value: &AnionValue) -> Result<Option<&Decimal>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::Decimal(ref d) => Ok(d.as_ref()),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("decimal", other.type_name())),
+  }
+}
+
+fn text(value: &AnionValue) -> Result<Option<&str>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::String(ref s) | AnionValue::Symbol(ref s) => Ok(s.as_ref().map(|s| s.as_str())),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("string", other.type_name())),
+  }
+}
+
+fn lob(value: &AnionValue) -> Result<Option<&[u8]>, ConversionError>
+{
+  match *unannotated(value) {
+    AnionValue::Blob(ref b) | AnionValue::Clob(ref b) => Ok(b.as_ref().map(|b| &b[..])),
+    AnionValue::Null => Ok(None),
+    ref other => Err(ConversionError::WrongType("blob", other.type_name())),
+  }
+}
+
+/// Range-checked conversion of an ion int to a primitive integer
+fn primitive_int<T: TryFrom<i64> + FromStr>(value: &AnionValue) -> Result<Option<T>, ConversionError>
+{
+  let converted = match integer(value)? {
+    Some(i) => match i.as_i64() {
+      Some(small) => T::try_from(small).ok(),
+      None => T::from_str(&i.to_string()).ok(),
+    },
+    None => return Ok(None),
+  };
+  return converted.map(Some).ok_or(ConversionError::OutOfRange);
+}
+
+fn primitive_f32(value: &AnionValue) -> Result<Option<f32>, ConversionError>
+{
+  match float(value)? {
+    Some(f) if f.is_finite() && f.abs() > ::std::f32::MAX as f64 => Err(ConversionError::OutOfRange),
+    f => Ok(f.map(|f| f as f32)),
+  }
+}
+
+/// Implement `TryFrom` for `$target` and `Option<$target>` from both
+/// `&AnionValue`, using `$convert`, and `AnionValue`, using `$into` (or
+/// `$convert` on a reference, if there's nothing to move out).
+macro_rules! impl_try_from {
+  ($target:ty, $convert:expr) => {
+    impl_try_from!($target, $convert, |value: AnionValue| $convert(&value));
+  };
+  ($target:ty, $convert:expr, $into:expr) => {
+    impl<'a> TryFrom<&'a AnionValue> for Option<$target> {
+      type Error = ConversionError;
+
+      fn try_from(value: &'a AnionValue) -> Result<Self, ConversionError>
+      {
+        $convert(value)
+      }
+    }
+
+    impl<'a> TryFrom<&'a AnionValue> for $target {
+      type Error = ConversionError;
+
+      fn try_from(value: &'a AnionValue) -> Result<Self, ConversionError>
+      {
+        $convert(value)?.ok_or(ConversionError::Null)
+      }
+    }
+
+    impl TryFrom<AnionValue> for Option<$target> {
+      type Error = ConversionError;
+
+      fn try_from(value: AnionValue) -> Result<Self, ConversionError>
+      {
+        $into(value)
+      }
+    }
+
+    impl TryFrom<AnionValue> for $target {
+      type Error = ConversionError;
+
+      fn try_from(value: AnionValue) -> Result<Self, ConversionError>
+      {
+        $into(value)?.ok_or(ConversionError::Null)
+      }
+    }
+  }
+}
+
+impl_try_from!(i8, primitive_int::<i8>);
+impl_try_from!(i16, primitive_int::<i16>);
+impl_try_from!(i32, primitive_int::<i32>);
+impl_try_from!(i64, primitive_int::<i64>);
+impl_try_from!(i128, primitive_int::<i128>);
+impl_try_from!(u8, primitive_int::<u8>);
+impl_try_from!(u16, primitive_int::<u16>);
+impl_try_from!(u32, primitive_int::<u32>);
+impl_try_from!(u64, primitive_int::<u64>);
+impl_try_from!(u128, primitive_int::<u128>);
+impl_try_from!(f32, primitive_f32);
+impl_try_from!(f64, float);
+impl_try_from!(bool, boolean);
+
+impl_try_from!(Int,
+  |value| integer(value).map(|i| i.cloned()),
+  |value| match into_unannotated(value) {
+    AnionValue::Integer(i) => Ok(i),
+    other => integer(&other).map(|_| None),
+  });
+
+impl_try_from!(BigInt,
+  |value| integer(value).map(|i| i.map(Int::to_big_int)),
+  |value| Option::<Int>::try_from(value).map(|i| i.map(BigInt::from)));
+
+impl_try_from!(Decimal,
+  |value| decimal(value).map(|d| d.cloned()),
+  |value| match into_unannotated(value) {
+    AnionValue::Decimal(d) => Ok(d),
+    other => decimal(&other).map(|_| None),
+  });
+
+impl_try_from!(BigDecimal,
+  |value| decimal(value)?.cloned().map(BigDecimal::try_from).transpose(),
+  |value| Option::<Decimal>::try_from(value)?.map(BigDecimal::try_from).transpose());
+
+impl_try_from!(String,
+  |value| text(value).map(|s| s.map(String::from)),
+  |value| match into_unannotated(value) {
+    AnionValue::String(s) | AnionValue::Symbol(s) => Ok(s),
+    other => text(&other).map(|_| None),
+  });
+
+impl_try_from!(Vec<u8>,
+  |value| lob(value).map(|b| b.map(|b| b.to_vec())),
+  |value| match into_unannotated(value) {
+    AnionValue::Blob(b) | AnionValue::Clob(b) => Ok(b),
+    other => lob(&other).map(|_| None),
+  });
+
+impl<'a> TryFrom<&'a AnionValue> for Option<&'a str> {
+  type Error = ConversionError;
+
+  fn try_from(value: &'a AnionValue) -> Result<Self, ConversionError>
+  {
+    text(value)
+  }
+}
+
+impl<'a> TryFrom<&'a AnionValue> for &'a str {
+  type Error = ConversionError;
+
+  fn try_from(value: &'a AnionValue) -> Result<Self, ConversionError>
+  {
+    text(value)?.ok_or(ConversionError::Null)
+  }
+}
+
+
+/// Fails with `ConversionError::Null` on the first typed null found
+impl TryFrom<AnionValue> for NonNullAnionValue {
+  type Error = ConversionError;
+
+  fn try_from(value: AnionValue) -> Result<Self, ConversionError>
+  {
+    use AnionValue::*;
+
+    let value = match value {
+      Null => NonNullAnionValue::Null,
+      Boolean(Some(b)) => NonNullAnionValue::Boolean(b),
+      Integer(Some(i)) => NonNullAnionValue::Integer(i),
+      Float(Some(f)) => NonNullAnionValue::Float(f),
+      Decimal(Some(d)) => NonNullAnionValue::Decimal(d),
+      Timestamp(Some(t)) => NonNullAnionValue::Timestamp(t),
+      String(Some(s)) => NonNullAnionValue::String(s),
+      Symbol(Some(s)) => NonNullAnionValue::Symbol(s),
+      Blob(Some(b)) => NonNullAnionValue::Blob(b),
+      Clob(Some(b)) => NonNullAnionValue::Clob(b),
+      List(Some(values)) => NonNullAnionValue::List(non_null_values(values)?),
+      SExp(Some(values)) => NonNullAnionValue::SExp(non_null_values(values)?),
+      Struct(Some(fields)) => {
+        let fields = fields.into_iter()
+          .map(|(name, value)| NonNullAnionValue::try_from(value).map(|value| (name, value)))
+          .collect::<Result<_, _>>()?;
+        NonNullAnionValue::Struct(fields)
+      },
+      Annotated(annotations, value) => NonNullAnionValue::Annotated(annotations, Box::new(NonNullAnionValue::try_from(*value)?)),
+      _ => return Err(ConversionError::Null),
+    };
+    return Ok(value);
+  }
+}
+
+impl<'a> TryFrom<&'a AnionValue> for NonNullAnionValue {
+  type Error = ConversionError;
+
+  fn try_from(value: &'a AnionValue) -> Result<Self, ConversionError>
+  {
+    NonNullAnionValue::try_from(value.clone())
+  }
+}
+
+fn non_null_values(values: Vec<AnionValue>) -> Result<Vec<NonNullAnionValue>, ConversionError>
+{
+  values.into_iter().map(NonNullAnionValue::try_from).collect()
+}
+
+impl From<NonNullAnionValue> for AnionValue {
+  fn from(value: NonNullAnionValue) -> Self
+  {
+    use NonNullAnionValue::*;
+
+    match value {
+      Null => AnionValue::Null,
+      Boolean(b) => AnionValue::Boolean(Some(b)),
+      Integer(i) => AnionValue::Integer(Some(i)),
+      Float(f) => AnionValue::Float(Some(f)),
+      Decimal(d) => AnionValue::Decimal(Some(d)),
+      Timestamp(t) => AnionValue::Timestamp(Some(t)),
+      String(s) => AnionValue::String(Some(s)),
+      Symbol(s) => AnionValue::Symbol(Some(s)),
+      Blob(b) => AnionValue::Blob(Some(b)),
+      Clob(b) => AnionValue::Clob(Some(b)),
+      List(values) => AnionValue::List(Some(values.into_iter().map(AnionValue::from).collect())),
+      SExp(values) => AnionValue::SExp(Some(values.into_iter().map(AnionValue::from).collect())),
+      Struct(fields) => AnionValue::Struct(Some(fields.into_iter().map(|(name, value)| (name, AnionValue::from(value))).collect())),
+      Annotated(annotations, value) => AnionValue::Annotated(annotations, Box::new(AnionValue::from(*value))),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use {AnionValue, Int, NonNullAnionValue};
+  use super::ConversionError;
+  use num_bigint::BigInt;
+  use std::convert::TryFrom;
+
+  #[test]
+  fn integers_are_range_checked()
+  {
+    assert_eq!(u8::try_from(ion!(255)), Ok(255));
+    assert_eq!(u8::try_from(ion!(256)), Err(ConversionError::OutOfRange));
+    assert_eq!(u64::try_from(ion!(-1)), Err(ConversionError::OutOfRange));
+    assert_eq!(i8::try_from(&ion!(-128)), Ok(-128));
+
+    let big = AnionValue::from(BigInt::from(i64::max_value()) * BigInt::from(4));
+    assert_eq!(i64::try_from(&big), Err(ConversionError::OutOfRange));
+    assert_eq!(i128::try_from(&big), Ok(i64::max_value() as i128 * 4));
+    assert_eq!(BigInt::try_from(&big), Ok(BigInt::from(i64::max_value()) * BigInt::from(4)));
+    assert_eq!(Int::try_from(big), Ok(Int::from(BigInt::from(i64::max_value()) * BigInt::from(4))));
+
+    assert_eq!(BigInt::try_from(ion!(-5)), Ok(BigInt::from(-5)));
+    assert_eq!(Int::try_from(&ion!(-5)).map(|i| i.as_i64()), Ok(Some(-5)));
+    assert_eq!(u64::try_from(ion!(#(u64::max_value()))), Ok(u64::max_value()));
+  }
+
+  #[test]
+  fn nulls_need_option()
+  {
+    assert_eq!(i32::try_from(ion!(null.int)), Err(ConversionError::Null));
+    assert_eq!(Option::<i32>::try_from(ion!(null.int)), Ok(None));
+    assert_eq!(Option::<i32>::try_from(ion!(null)), Ok(None));
+    assert_eq!(Option::<i32>::try_from(ion!(7)), Ok(Some(7)));
+    assert_eq!(Option::<i32>::try_from(ion!(null.float)), Err(ConversionError::WrongType("int", "float")));
+    assert_eq!(Option::<String>::try_from(ion!(null.string)), Ok(None));
+  }
+
+  #[test]
+  fn wrong_types()
+  {
+    assert_eq!(i32::try_from(ion!(1.5)), Err(ConversionError::WrongType("int", "float")));
+    assert_eq!(bool::try_from(ion!("true")), Err(ConversionError::WrongType("bool", "string")));
+    assert_eq!(String::try_from(ion!([])), Err(ConversionError::WrongType("string", "list")));
+  }
+
+  #[test]
+  fn other_types()
+  {
+    assert_eq!(bool::try_from(ion!(true)), Ok(true));
+    assert_eq!(f64::try_from(ion!(2.5)), Ok(2.5));
+    assert_eq!(f32::try_from(ion!(2.5)), Ok(2.5));
+    assert_eq!(f32::try_from(ion!(1e300)), Err(ConversionError::OutOfRange));
+    assert_eq!(String::try_from(ion!("abc")), Ok(String::from("abc")));
+    assert_eq!(String::try_from(ion!(a::b::abc)), Ok(String::from("abc")));
+    assert_eq!(<&str>::try_from(&ion!(sym)), Ok("sym"));
+    assert_eq!(Vec::<u8>::try_from(AnionValue::Blob(Some(vec![1, 2]))), Ok(vec![1, 2]));
+  }
+
+  #[test]
+  fn non_null_values()
+  {
+    let value = ion!({ a: [1, ann::x], b: null });
+    let non_null = NonNullAnionValue::try_from(&value).unwrap();
+    assert_eq!(non_null, NonNullAnionValue::Struct(vec![
+      (String::from("a"), NonNullAnionValue::List(vec![
+        NonNullAnionValue::Integer(Int::from(1)),
+        NonNullAnionValue::Annotated(vec![String::from("ann")], Box::new(NonNullAnionValue::Symbol(String::from("x")))),
+      ])),
+      (String::from("b"), NonNullAnionValue::Null),
+    ]));
+    assert_eq!(AnionValue::from(non_null), value);
+
+    assert_eq!(NonNullAnionValue::try_from(ion!(null.int)), Err(ConversionError::Null));
+    assert_eq!(NonNullAnionValue::try_from(ion!({ a: [1, null.list] })), Err(ConversionError::Null));
+  }
+}
@@ -0,0 +1,383 @@
+//! Ion decimal values
+//!
+//! Ion decimals are exact: `1.0`, `1.00` and `10d-1` are the same number
+//! but different values, and so are `0d0` and `-0d0`. `Decimal` keeps the
+//! coefficient, exponent and sign exactly as written.
+//!
+//! `PartialEq` (like `PartialEq` on `AnionValue`) compares numeric value,
+//! so `1.0 == 1.00` and `-0d0 == 0d0`; use `AnionValue::ion_eq` to also
+//! compare precision and sign.
+
+use num_bigint::{BigInt, Sign};
+use num_bigdecimal::BigDecimal;
+use convert::ConversionError;
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// An exact decimal number: `coefficient × 10^exponent`
+///
+#[derive(Debug, Clone)]
+pub struct Decimal {
+  coefficient: BigInt,
+  exponent: i64,
+
+  /// Zero coefficient with a minus sign, which BigInt can't represent
+  negative_zero: bool,
+}
+
+impl Decimal {
+  /// Create the decimal `coefficient × 10^exponent`
+  pub fn new(coefficient: BigInt, exponent: i64) -> Decimal
+  {
+    Decimal {
+      coefficient: coefficient,
+      exponent: exponent,
+      negative_zero: false,
+    }
+  }
+
+  /// Create the decimal `-0 × 10^exponent`
+  pub fn negative_zero(exponent: i64) -> Decimal
+  {
+    Decimal {
+      negative_zero: true,
+      ..Decimal::new(BigInt::from(0), exponent)
+    }
+  }
+
+  /// The digits of the decimal, signed; zero for both zeros
+  pub fn coefficient(&self) -> &BigInt
+  {
+    &self.coefficient
+  }
+
+  /// Power of ten the coefficient is multiplied by
+  pub fn exponent(&self) -> i64
+  {
+    self.exponent
+  }
+
+  pub fn is_zero(&self) -> bool
+  {
+    self.coefficient.sign() == Sign::NoSign
+  }
+
+  /// True for negative numbers and negative zero
+  pub fn is_sign_negative(&self) -> bool
+  {
+    self.negative_zero || self.coefficient.sign() == Sign::Minus
+  }
+
+  /// Compare numeric values, ignoring precision (so the zeros are equal)
+  pub fn value_cmp(&self, other: &Decimal) -> Ordering
+  {
+    let signum = |decimal: &Decimal| match decimal.coefficient.sign() {
+      Sign::Minus => -1,
+      Sign::NoSign => 0,
+      Sign::Plus => 1,
+    };
+    let sign = signum(self);
+    if sign != signum(other) || sign == 0 {
+      return sign.cmp(&signum(other));
+    }
+
+    // the magnitudes first differ in their number of digits left of the
+    // point, which can be compared without scaling
+    let (a_digits, b_digits) = (self.digits(), other.digits());
+    let (a_place, b_place) = (a_digits as i128 + self.exponent as i128, b_digits as i128 + other.exponent as i128);
+    if a_place != b_place {
+      let order = a_place.cmp(&b_place);
+      return if sign < 0 { order.reverse() } else { order };
+    }
+
+    // the exponents then differ by less than the number of digits
+    let exponent = if self.exponent < other.exponent { self.exponent } else { other.exponent };
+    let a = &self.coefficient * pow10((self.exponent as i128 - exponent as i128) as usize);
+    let b = &other.coefficient * pow10((other.exponent as i128 - exponent as i128) as usize);
+    return a.cmp(&b);
+  }
+
+  /// Number of digits in the coefficient
+  fn digits(&self) -> usize
+  {
+    let (_, magnitude) = self.coefficient.to_bytes_be();
+    BigInt::from_bytes_be(Sign::Plus, &magnitude).to_str_radix(10).len()
+  }
+
+  /// The same number with trailing zeros removed from the coefficient;
+  /// all zeros normalize to `0d0`
+  pub fn normalized(&self) -> Decimal
+  {
+    if self.is_zero() {
+      return Decimal::new(BigInt::from(0), 0);
+    }
+
+    let ten = BigInt::from(10);
+    let zero = BigInt::from(0);
+    let (mut coefficient, mut exponent) = (self.coefficient.clone(), self.exponent);
+
+    while &coefficient % &ten == zero {
+      coefficient = &coefficient / &ten;
+      exponent += 1;
+    }
+    return Decimal::new(coefficient, exponent);
+  }
+
+  /// Identical coefficient, exponent and sign - ion equivalence
+  pub fn ion_eq(&self, other: &Decimal) -> bool
+  {
+    self.coefficient == other.coefficient
+      && self.exponent == other.exponent
+      && self.negative_zero == other.negative_zero
+  }
+}
+
+fn pow10(exponent: usize) -> BigInt
+{
+  let mut result = BigInt::from(1);
+  for _ in 0..exponent {
+    result = result * BigInt::from(10);
+  }
+  return result;
+}
+
+impl PartialEq for Decimal {
+  fn eq(&self, other: &Decimal) -> bool
+  {
+    self.value_cmp(other) == Ordering::Equal
+  }
+}
+
+/// Consistent with `PartialEq`: equal values hash the same
+impl Hash for Decimal {
+  fn hash<H: Hasher>(&self, state: &mut H)
+  {
+    let normalized = self.normalized();
+    normalized.coefficient.hash(state);
+    normalized.exponent.hash(state);
+  }
+}
+
+impl From<BigInt> for Decimal {
+  fn from(int: BigInt) -> Self
+  {
+    Decimal::new(int, 0)
+  }
+}
+
+/// BigDecimal has no accessors, but its text form is exact; converting
+/// fails only if that isn't decimal text
+impl TryFrom<BigDecimal> for Decimal {
+  type Error = ParseDecimalError;
+
+  fn try_from(decimal: BigDecimal) -> Result<Decimal, ParseDecimalError>
+  {
+    Decimal::from_str(&decimal.to_string())
+  }
+}
+
+/// Negative zero becomes zero; BigDecimal's scale is the negated
+/// exponent, so converting fails only for the smallest `i64` exponent
+impl TryFrom<Decimal> for BigDecimal {
+  type Error = ConversionError;
+
+  fn try_from(decimal: Decimal) -> Result<BigDecimal, ConversionError>
+  {
+    let scale = decimal.exponent.checked_neg().ok_or(ConversionError::OutOfRange)?;
+    return Ok(BigDecimal::new(decimal.coefficient, scale));
+  }
+}
+
+/// Error parsing decimal text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseDecimalError {
+  text: String,
+}
+
+impl fmt::Display for ParseDecimalError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "invalid decimal '{}'", self.text)
+  }
+}
+
+impl Error for ParseDecimalError {
+  fn description(&self) -> &str
+  {
+    "invalid decimal"
+  }
+}
+
+/// Parse decimal text such as `-1.50`, `12d-3`, `1_000.0` or `-0d-2`.
+///
+/// The exponent marker may be `d`, `D`, `e` or `E`; underscores are
+/// ignored. The parser's grammar decides which forms are valid ion.
+impl FromStr for Decimal {
+  type Err = ParseDecimalError;
+
+  fn from_str(text: &str) -> Result<Decimal, ParseDecimalError>
+  {
+    let error = || ParseDecimalError { text: String::from(text) };
+    let cleaned = text.replace("_", "");
+
+    let (mantissa, exponent) = match cleaned.find(|c| c == 'd' || c == 'D' || c == 'e' || c == 'E') {
+      Some(i) => (&cleaned[..i], i64::from_str(cleaned[i + 1..].trim_start_matches('+')).map_err(|_| error())?),
+      None => (&cleaned[..], 0),
+    };
+
+    let (negative, mantissa) = match mantissa.chars().next() {
+      Some('-') => (true, &mantissa[1..]),
+      Some('+') => (false, &mantissa[1..]),
+      _ => (false, mantissa),
+    };
+
+    let (whole, fraction) = match mantissa.find('.') {
+      Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+      None => (mantissa, ""),
+    };
+
+    let digits = format!("{}{}", whole, fraction);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+      return Err(error());
+    }
+
+    let magnitude = BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(error)?;
+    let exponent = exponent.checked_sub(fraction.len() as i64).ok_or_else(error)?;
+
+    if negative && magnitude.sign() == Sign::NoSign {
+      return Ok(Decimal::negative_zero(exponent));
+    }
+
+    let coefficient = if negative { -magnitude } else { magnitude };
+    return Ok(Decimal::new(coefficient, exponent));
+  }
+}
+
+/// Most zeros written between a decimal point and the digits; smaller
+/// numbers are written with an exponent
+const MAX_LEADING_ZEROS: i64 = 6;
+
+/// Writes ion text which reads back as an identical decimal: a decimal
+/// point for negative exponents, `d` notation otherwise (`1.50`, `42d0`,
+/// `-0d0`), and for numbers which would need many zeros after the point
+/// (`1d-20`).
+impl fmt::Display for Decimal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    let (_, magnitude) = self.coefficient.to_bytes_be();
+    let sign = if self.is_sign_negative() { "-" } else { "" };
+    let digits = BigInt::from_bytes_be(Sign::Plus, &magnitude).to_string();
+
+    if self.exponent >= 0 {
+      return write!(f, "{}{}d{}", sign, digits, self.exponent);
+    }
+    // can't overflow with a negative exponent
+    let point = digits.len() as i64 + self.exponent;
+    if point < -MAX_LEADING_ZEROS {
+      return write!(f, "{}{}d{}", sign, digits, self.exponent);
+    }
+
+    if point > 0 {
+      let (whole, fraction) = digits.split_at(point as usize);
+      write!(f, "{}{}.{}", sign, whole, fraction)
+    } else {
+      let zeros: String = ::std::iter::repeat('0').take(-point as usize).collect();
+      write!(f, "{}0.{}{}", sign, zeros, digits)
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::Decimal;
+  use convert::ConversionError;
+  use num_bigint::BigInt;
+  use num_bigdecimal::BigDecimal;
+  use std::cmp::Ordering;
+  use std::convert::TryFrom;
+  use std::str::FromStr;
+
+  fn dec(s: &str) -> Decimal
+  {
+    Decimal::from_str(s).unwrap()
+  }
+
+  #[test]
+  fn keeps_precision()
+  {
+    assert_eq!(dec("1.00").coefficient(), &BigInt::from(100));
+    assert_eq!(dec("1.00").exponent(), -2);
+    assert_eq!(dec("1.0d0").exponent(), -1);
+    assert_eq!(dec("-1_2.5d3").coefficient(), &BigInt::from(-125));
+    assert_eq!(dec("-1_2.5d3").exponent(), 2);
+    assert!(Decimal::from_str("1.2.3").is_err());
+    assert!(Decimal::from_str("-").is_err());
+  }
+
+  #[test]
+  fn keeps_negative_zero()
+  {
+    assert!(dec("-0d-2").is_sign_negative());
+    assert_eq!(dec("-0d-2").exponent(), -2);
+    assert!(!dec("0d-2").is_sign_negative());
+    assert!(!dec("-0d-2").ion_eq(&dec("0d-2")));
+    assert!(dec("-0.00").ion_eq(&dec("-0d-2")));
+  }
+
+  #[test]
+  fn equality_is_numeric()
+  {
+    assert_eq!(dec("1.0"), dec("1.00"));
+    assert_eq!(dec("12d2"), dec("1200"));
+    assert_eq!(dec("-0.0"), dec("0d5"));
+    assert!(dec("1.1") != dec("1.10001"));
+    assert!(!dec("1.0").ion_eq(&dec("1.00")));
+  }
+
+  #[test]
+  fn display_round_trips()
+  {
+    for &text in ["1.00", "0.012", "-12.5", "42d0", "0d-0", "12d3", "-0d0", "-0.00", "-0.012"].iter() {
+      let expected = if text == "0d-0" { "0d0" } else { text };
+      assert_eq!(dec(text).to_string(), expected);
+      assert!(dec(expected).ion_eq(&dec(text)));
+    }
+  }
+
+  #[test]
+  fn extreme_exponents()
+  {
+    let (large, small) = (dec("1d1000000000"), dec("1d-1000000000"));
+    assert!(large != small);
+    assert_eq!(large.value_cmp(&small), Ordering::Greater);
+    assert_eq!(dec("-1d1000000000").value_cmp(&dec("-1d-1000000000")), Ordering::Less);
+    assert_eq!(dec("1d9223372036854775807").value_cmp(&dec("1d-9223372036854775808")), Ordering::Greater);
+    assert_eq!(dec("-5d-9223372036854775808").value_cmp(&dec("0d9223372036854775807")), Ordering::Less);
+    assert_eq!(dec("10d999999999"), dec("1d1000000000"));
+    assert_eq!(dec("-12.5").value_cmp(&dec("-1.25d1")), Ordering::Equal);
+    assert_eq!(dec("-12.6").value_cmp(&dec("-12.5")), Ordering::Less);
+
+    assert_eq!(dec("1d-1000000000000").to_string(), "1d-1000000000000");
+    assert_eq!(dec("-25d-9").to_string(), "-25d-9");
+    assert_eq!(dec("25d-8").to_string(), "0.00000025");
+    assert_eq!(dec("1d9223372036854775807").to_string(), "1d9223372036854775807");
+    assert_eq!(dec("-123d9223372036854775807").to_string(), "-123d9223372036854775807");
+    assert_eq!(dec("1d-9223372036854775808").to_string(), "1d-9223372036854775808");
+  }
+
+  #[test]
+  fn from_big_decimal()
+  {
+    let big = BigDecimal::new(BigInt::from(-125), 2);
+    assert!(Decimal::try_from(big).unwrap().ion_eq(&dec("-1.25")));
+
+    assert_eq!(BigDecimal::try_from(dec("-1.25")), Ok(BigDecimal::new(BigInt::from(-125), 2)));
+    assert_eq!(BigDecimal::try_from(dec("1d-9223372036854775808")), Err(ConversionError::OutOfRange));
+  }
+}
@@ -0,0 +1,189 @@
+//! Ion data-model equivalence
+//!
+//! Implements the equivalence relation from the ion specification, which
+//! is stricter than `PartialEq` about numbers and looser about structs:
+//!
+//!  * decimals must agree in precision and sign (`1.0` is not `1.00`,
+//!    `-0d0` is not `0d0`)
+//!  * floats compare bitwise, except every `nan` is equivalent to every
+//!    other `nan` (so `-0e0` is not `0e0`)
+//!  * struct fields are compared as a multiset of (name, value) pairs
+//!  * timestamps must agree in precision and local offset
+//!  * annotations must match, in order
+
+use super::{AnionValue, Decimal};
+
+impl AnionValue {
+  /// Test whether two values are equivalent under the Ion data model.
+  ///
+  /// This is the relation used by the `equivs`/`non-equivs` suites of the
+  /// ion test data. Typed nulls are only equivalent to nulls of the same
+  /// type.
+  ///
+  pub fn ion_eq(&self, other: &AnionValue) -> bool
+  {
+    use AnionValue::*;
+
+    match (self, other) {
+      (&Annotated(ref a, ref x), _) if a.is_empty() => x.ion_eq(other),
+      (_, &Annotated(ref b, ref y)) if b.is_empty() => self.ion_eq(y),
+      (&Annotated(ref a, ref x), &Annotated(ref b, ref y)) => a == b && x.ion_eq(y),
+
+      (&Null, &Null) => true,
+      (&Boolean(ref a), &Boolean(ref b)) => a == b,
+      (&Integer(ref a), &Integer(ref b)) => a == b,
+      (&Float(ref a), &Float(ref b)) => option_eq(a, b, float_eq),
+      (&Decimal(ref a), &Decimal(ref b)) => option_eq(a, b, decimal_eq),
+      (&Timestamp(ref a), &Timestamp(ref b)) => a == b,
+      (&String(ref a), &String(ref b)) => a == b,
+      (&Symbol(ref a), &Symbol(ref b)) => a == b,
+      (&Blob(ref a), &Blob(ref b)) => a == b,
+      (&Clob(ref a), &Clob(ref b)) => a == b,
+      (&List(ref a), &List(ref b)) => option_eq(a, b, sequence_eq),
+      (&SExp(ref a), &SExp(ref b)) => option_eq(a, b, sequence_eq),
+      (&Struct(ref a), &Struct(ref b)) => option_eq(a, b, fields_eq),
+
+      _ => false,
+    }
+  }
+}
+
+/// Two typed values are equal if both are null, or both non-null and
+/// equal according to `eq`.
+#[inline]
+fn option_eq<T, F>(a: &Option<T>, b: &Option<T>, eq: F) -> bool
+  where F: Fn(&T, &T) -> bool
+{
+  match (a, b) {
+    (&None, &None) => true,
+    (&Some(ref x), &Some(ref y)) => eq(x, y),
+    _ => false,
+  }
+}
+
+fn float_eq(a: &f64, b: &f64) -> bool
+{
+  if a.is_nan() || b.is_nan() {
+    return a.is_nan() && b.is_nan();
+  }
+  return a.to_bits() == b.to_bits();
+}
+
+fn decimal_eq(a: &Decimal, b: &Decimal) -> bool
+{
+  return a.ion_eq(b);
+}
+
+fn sequence_eq(a: &Vec<AnionValue>, b: &Vec<AnionValue>) -> bool
+{
+  return a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| x.ion_eq(y));
+}
+
+/// Compare struct fields as multisets; each field of `a` must be matched
+/// by a distinct, equivalent field of `b`.
+fn fields_eq(a: &Vec<(String, AnionValue)>, b: &Vec<(String, AnionValue)>) -> bool
+{
+  if a.len() != b.len() {
+    return false;
+  }
+
+  let mut matched = vec![false; b.len()];
+
+  for &(ref name, ref value) in a {
+    let found = (0..b.len()).position(|i| !matched[i] && b[i].0 == *name && b[i].1.ion_eq(value));
+
+    match found {
+      Some(i) => matched[i] = true,
+      None => return false,
+    }
+  }
+
+  return true;
+}
+
+
+#[cfg(test)]
+mod test {
+  use AnionValue;
+  use Timestamp;
+  use Decimal;
+  use std::str::FromStr;
+  use std::f64;
+
+  fn dec(s: &str) -> AnionValue
+  {
+    AnionValue::Decimal(Some(Decimal::from_str(s).unwrap()))
+  }
+
+  fn field(name: &str, value: AnionValue) -> (String, AnionValue)
+  {
+    (String::from(name), value)
+  }
+
+  #[test]
+  fn decimal_precision_matters()
+  {
+    assert_eq!(dec("1.0"), dec("1.00"));
+    assert!(!dec("1.0").ion_eq(&dec("1.00")));
+    assert!(dec("1.0").ion_eq(&dec("10e-1")));
+    assert!(dec("1.00").ion_eq(&dec("1.00")));
+    assert_eq!(dec("-0d0"), dec("0d0"));
+    assert!(!dec("-0d0").ion_eq(&dec("0d0")));
+  }
+
+  #[test]
+  fn float_nan_and_signed_zero()
+  {
+    assert!(AnionValue::from(f64::NAN).ion_eq(&AnionValue::from(f64::NAN)));
+    assert!(!AnionValue::from(0.0).ion_eq(&AnionValue::from(-0.0)));
+    assert!(AnionValue::from(1.5).ion_eq(&AnionValue::from(1.5)));
+  }
+
+  #[test]
+  fn typed_nulls()
+  {
+    assert!(AnionValue::Null.ion_eq(&AnionValue::Null));
+    assert!(AnionValue::Integer(None).ion_eq(&AnionValue::Integer(None)));
+    assert!(!AnionValue::Integer(None).ion_eq(&AnionValue::Null));
+    assert!(!AnionValue::Integer(None).ion_eq(&AnionValue::Float(None)));
+    assert!(!AnionValue::Integer(None).ion_eq(&AnionValue::from(0)));
+  }
+
+  #[test]
+  fn struct_fields_are_multisets()
+  {
+    let one = || AnionValue::from(1);
+    let a = AnionValue::Struct(Some(vec![field("a", one()), field("b", dec("2.0")), field("a", one())]));
+    let b = AnionValue::Struct(Some(vec![field("b", dec("2.0")), field("a", one()), field("a", one())]));
+    let c = AnionValue::Struct(Some(vec![field("b", dec("2.0")), field("a", one()), field("b", dec("2.0"))]));
+    let d = AnionValue::Struct(Some(vec![field("a", one()), field("b", dec("2.00")), field("a", one())]));
+    assert!(a.ion_eq(&b));
+    assert!(!a.ion_eq(&c));
+    assert!(!a.ion_eq(&d));
+  }
+
+  #[test]
+  fn timestamps_compare_precision_and_offset()
+  {
+    let day = Timestamp::day(2007, 1, 1);
+    let mut utc = day.clone();
+    utc.offset = Some(0);
+
+    let ts = |t: &Timestamp| AnionValue::Timestamp(Some(t.clone()));
+
+    assert!(!ts(&Timestamp::year(2007)).ion_eq(&ts(&day)));
+    assert!(!ts(&utc).ion_eq(&ts(&day)));
+    assert!(ts(&day).ion_eq(&ts(&day)));
+  }
+
+  #[test]
+  fn annotations_must_match()
+  {
+    let a = AnionValue::Annotated(vec![String::from("a")], Box::new(AnionValue::from(1)));
+    let b = AnionValue::Annotated(vec![String::from("b")], Box::new(AnionValue::from(1)));
+    assert!(a.ion_eq(&a.clone()));
+    assert!(!a.ion_eq(&b));
+    assert!(!a.ion_eq(&AnionValue::from(1)));
+    assert!(AnionValue::Annotated(vec![], Box::new(AnionValue::from(1))).ion_eq(&AnionValue::from(1)));
+  }
+}
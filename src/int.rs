@@ -0,0 +1,203 @@
+//! Ion int values
+//!
+//! Ion ints are unbounded, but almost all of them fit in an `i64`. `Int`
+//! stores those inline and only allocates a `BigInt` for larger values.
+//! Each value has exactly one representation, so equality and hashing
+//! don't depend on how an `Int` was made.
+
+use num_bigint::{BigInt, Sign};
+
+use std::cmp::Ordering;
+use std::fmt;
+
+/// An arbitrary precision integer
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Int(Repr);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Repr {
+  Small(i64),
+
+  /// Always outside the range of `i64`
+  Big(BigInt),
+}
+
+impl Int {
+  /// Parse digits in the given radix, with an optional sign, as
+  /// `BigInt::parse_bytes` does
+  pub fn parse_bytes(digits: &[u8], radix: u32) -> Option<Int>
+  {
+    let small = ::std::str::from_utf8(digits).ok().and_then(|digits| i64::from_str_radix(digits, radix).ok());
+    match small {
+      Some(i) => Some(Int(Repr::Small(i))),
+      None => BigInt::parse_bytes(digits, radix).map(Int::from),
+    }
+  }
+
+  /// The value, if it fits in an `i64`
+  pub fn as_i64(&self) -> Option<i64>
+  {
+    match self.0 {
+      Repr::Small(i) => Some(i),
+      Repr::Big(_) => None,
+    }
+  }
+
+  pub fn to_big_int(&self) -> BigInt
+  {
+    match self.0 {
+      Repr::Small(i) => BigInt::from(i),
+      Repr::Big(ref big) => big.clone(),
+    }
+  }
+
+  pub fn is_negative(&self) -> bool
+  {
+    match self.0 {
+      Repr::Small(i) => i < 0,
+      Repr::Big(ref big) => big.sign() == Sign::Minus,
+    }
+  }
+}
+
+macro_rules! impl_small_int_conversion {
+  ($int_type:ident) => {
+    impl From<$int_type> for Int {
+      #[inline]
+      fn from(int: $int_type) -> Self {
+        Int(Repr::Small(int as i64))
+      }
+    }
+  }
+}
+
+impl_small_int_conversion!(i8);
+impl_small_int_conversion!(i16);
+impl_small_int_conversion!(i32);
+impl_small_int_conversion!(i64);
+impl_small_int_conversion!(u8);
+impl_small_int_conversion!(u16);
+impl_small_int_conversion!(u32);
+
+impl From<u64> for Int {
+  fn from(int: u64) -> Self
+  {
+    if int <= i64::max_value() as u64 {
+      Int(Repr::Small(int as i64))
+    } else {
+      Int(Repr::Big(BigInt::from(int)))
+    }
+  }
+}
+
+impl From<BigInt> for Int {
+  fn from(big: BigInt) -> Self
+  {
+    let (sign, magnitude) = big.to_bytes_le();
+    if magnitude.len() <= 8 {
+      let magnitude = magnitude.iter().rev().fold(0u64, |m, &byte| m << 8 | byte as u64);
+      if sign == Sign::Minus && magnitude <= 1 << 63 {
+        return Int(Repr::Small((magnitude as i64).wrapping_neg()));
+      }
+      if sign != Sign::Minus && magnitude <= i64::max_value() as u64 {
+        return Int(Repr::Small(magnitude as i64));
+      }
+    }
+    Int(Repr::Big(big))
+  }
+}
+
+impl From<Int> for BigInt {
+  fn from(int: Int) -> Self
+  {
+    match int.0 {
+      Repr::Small(i) => BigInt::from(i),
+      Repr::Big(big) => big,
+    }
+  }
+}
+
+impl Ord for Int {
+  fn cmp(&self, other: &Int) -> Ordering
+  {
+    match (&self.0, &other.0) {
+      (&Repr::Small(a), &Repr::Small(b)) => a.cmp(&b),
+      (&Repr::Big(ref a), &Repr::Big(ref b)) => a.cmp(b),
+      // big values are beyond every small one
+      (&Repr::Big(_), &Repr::Small(_)) => if self.is_negative() { Ordering::Less } else { Ordering::Greater },
+      (&Repr::Small(_), &Repr::Big(_)) => if other.is_negative() { Ordering::Greater } else { Ordering::Less },
+    }
+  }
+}
+
+impl PartialOrd for Int {
+  fn partial_cmp(&self, other: &Int) -> Option<Ordering>
+  {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Int {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    match self.0 {
+      Repr::Small(i) => write!(f, "{}", i),
+      Repr::Big(ref big) => write!(f, "{}", big),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::Int;
+  use num_bigint::BigInt;
+
+  fn big(text: &str) -> BigInt
+  {
+    BigInt::parse_bytes(text.as_bytes(), 10).unwrap()
+  }
+
+  #[test]
+  fn small_values_are_inline()
+  {
+    assert_eq!(Int::from(42).as_i64(), Some(42));
+    assert_eq!(Int::from(big("-9223372036854775808")).as_i64(), Some(i64::min_value()));
+    assert_eq!(Int::from(big("9223372036854775807")).as_i64(), Some(i64::max_value()));
+    assert_eq!(Int::from(big("9223372036854775808")).as_i64(), None);
+    assert_eq!(Int::from(big("-9223372036854775809")).as_i64(), None);
+    assert_eq!(Int::from(u64::max_value()).as_i64(), None);
+    assert_eq!(Int::from(BigInt::from(0)).as_i64(), Some(0));
+  }
+
+  #[test]
+  fn one_representation_per_value()
+  {
+    assert_eq!(Int::from(BigInt::from(-7)), Int::from(-7));
+    assert_eq!(Int::from(u64::max_value()), Int::from(big("18446744073709551615")));
+    assert_eq!(Int::from(i64::max_value() as u64), Int::from(i64::max_value()));
+    assert_eq!(BigInt::from(Int::from(-7)), BigInt::from(-7));
+    assert_eq!(Int::from(big("123456789012345678901234567890")).to_big_int(), big("123456789012345678901234567890"));
+  }
+
+  #[test]
+  fn parsing()
+  {
+    assert_eq!(Int::parse_bytes(b"-8000000000000000", 16), Some(Int::from(i64::min_value())));
+    assert_eq!(Int::parse_bytes(b"10000000000000000", 16), Some(Int::from(big("18446744073709551616"))));
+    assert_eq!(Int::parse_bytes(b"-101", 2), Some(Int::from(-5)));
+    assert_eq!(Int::parse_bytes(b"12a", 10), None);
+  }
+
+  #[test]
+  fn ordering()
+  {
+    let mut ints = vec![
+      Int::from(big("99999999999999999999")), Int::from(3), Int::from(big("-99999999999999999999")),
+      Int::from(-4), Int::from(big("-99999999999999999998")), Int::from(big("99999999999999999998")),
+    ];
+    ints.sort();
+    let text: Vec<String> = ints.iter().map(|i| i.to_string()).collect();
+    assert_eq!(text, vec!["-99999999999999999999", "-99999999999999999998", "-4", "3", "99999999999999999998", "99999999999999999999"]);
+  }
+}
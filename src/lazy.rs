@@ -0,0 +1,687 @@
+//! Lazy reading of binary ion
+//!
+//! Binary values are length-prefixed, so a `LazyValue` only needs to know
+//! where its bytes are. Finding a struct field or list element steps over
+//! the values before it by their headers, and nothing is decoded until
+//! `decode` is called; reading two fields of a large record costs little
+//! more than finding them.
+//!
+//! Values borrow the buffer they're read from, which may be as large as
+//! the address space allows; with the `mmap` feature, `MappedFile` maps a
+//! file to read. `decode_ref` and the `as_str`/`as_bytes` accessors
+//! borrow the text of strings and the bytes of lobs rather than copying
+//! them.
+
+use super::{AnionValue, AnionValueRef};
+use binary::{BinaryError, Header, VERSION_MARKER, annotation_texts, check_limit, check_value_limits, decode_nested, invalid,
+             read_header, read_var_uint, split_annotations};
+use binary::{ANNOTATION_TYPE, BLOB_TYPE, CLOB_TYPE, LIST_TYPE, NULL_TYPE, SEXP_TYPE, STRING_TYPE, STRUCT_TYPE};
+use reader::{Limit, ReaderOptions};
+use symbols::{SymbolTable, symbol_table_fields};
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::rc::Rc;
+use std::str;
+
+#[cfg(feature = "mmap")]
+use memmap::Mmap;
+#[cfg(feature = "mmap")]
+use std::fs::File;
+#[cfg(feature = "mmap")]
+use std::io;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+/// Names of the ion types by type code, as in `AnionValue::type_name`
+const TYPE_NAMES: [&'static str; 14] = [
+  "null", "bool", "int", "int", "float", "decimal", "timestamp", "symbol", "string", "clob", "blob", "list", "sexp", "struct",
+];
+
+/// Reads the top level values of a complete binary ion buffer without
+/// decoding them.
+///
+/// The buffer must start with the version marker. Local symbol tables are
+/// applied and dropped, and padding is skipped; each value keeps the
+/// symbol table it was read under.
+pub struct LazyReader<'a> {
+  data: &'a [u8],
+
+  /// Index of the next value in `data`
+  next: usize,
+  symbols: Rc<SymbolTable>,
+  options: Rc<ReaderOptions>,
+  started: bool,
+  failed: bool,
+}
+
+impl<'a> LazyReader<'a> {
+  pub fn new(data: &'a [u8]) -> LazyReader<'a>
+  {
+    LazyReader::with_options(data, ReaderOptions::default())
+  }
+
+  /// A reader whose values keep to the limits of `options` when they're
+  /// decoded
+  pub fn with_options(data: &'a [u8], options: ReaderOptions) -> LazyReader<'a>
+  {
+    LazyReader {
+      data: data,
+      next: 0,
+      symbols: Rc::new(SymbolTable::system()),
+      options: Rc::new(options),
+      started: false,
+      failed: false,
+    }
+  }
+
+  /// The next top level value, or `None` at the end of the buffer
+  pub fn next_value(&mut self) -> Result<Option<LazyValue<'a>>, BinaryError>
+  {
+    let all = self.data;
+    loop {
+      let data = &all[self.next..];
+      if data.is_empty() {
+        return Ok(None);
+      }
+
+      if !self.started || data[0] == VERSION_MARKER[0] {
+        let available = data.len().min(VERSION_MARKER.len());
+        if data[..available] != VERSION_MARKER[..available] {
+          let reason = if self.started { "unsupported version marker" } else { "missing version marker" };
+          return Err(invalid(reason));
+        }
+        if available < VERSION_MARKER.len() {
+          return Err(BinaryError::UnexpectedEnd);
+        }
+        self.started = true;
+        self.symbols = Rc::new(SymbolTable::system());
+        self.next += VERSION_MARKER.len();
+        continue;
+      }
+
+      let (value, used) = LazyValue::read(data, &self.symbols, &self.options, 0)?;
+      self.next += used;
+      let value = match value {
+        Some(value) => value,
+        None => continue,
+      };
+
+      if value.header.type_code == STRUCT_TYPE && value.first_annotation()? == Some("$ion_symbol_table") {
+        let decoded = value.decode()?;
+        if let Some(fields) = symbol_table_fields(&decoded) {
+          self.symbols = Rc::new(self.symbols.local(fields, self.options.max_symbols)?);
+          continue;
+        }
+      }
+      return Ok(Some(value));
+    }
+  }
+}
+
+/// As an iterator, reading stops after the first error
+impl<'a> Iterator for LazyReader<'a> {
+  type Item = Result<LazyValue<'a>, BinaryError>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    if self.failed {
+      return None;
+    }
+    match self.next_value() {
+      Ok(value) => value.map(Ok),
+      Err(error) => {
+        self.failed = true;
+        Some(Err(error))
+      },
+    }
+  }
+}
+
+/// One binary ion value, decoded on request
+#[derive(Debug, Clone)]
+pub struct LazyValue<'a> {
+  /// The whole value, annotation wrapper included
+  data: &'a [u8],
+
+  /// Start of the unannotated value in `data`
+  value_start: usize,
+
+  /// Header of the unannotated value
+  header: Header,
+  symbols: Rc<SymbolTable>,
+  options: Rc<ReaderOptions>,
+
+  /// Number of containers the value is in
+  depth: usize,
+}
+
+impl<'a> LazyValue<'a> {
+  /// Frame the value at the start of `data`, checking only its headers;
+  /// returns the value, or `None` for padding, and the bytes used
+  fn read(data: &'a [u8], symbols: &Rc<SymbolTable>, options: &Rc<ReaderOptions>, depth: usize)
+    -> Result<(Option<LazyValue<'a>>, usize), BinaryError>
+  {
+    // the whole value must be in `data`, so the slices taken from it later
+    // are in range
+    let header = read_header(data)?;
+    let end = header.end()?;
+    let data = data.get(..end).ok_or(BinaryError::UnexpectedEnd)?;
+
+    let (value_start, header) = match header.type_code {
+      NULL_TYPE if header.length.is_some() => return Ok((None, end)),
+      ANNOTATION_TYPE if header.length.map_or(true, |length| length == 0) => {
+        return Err(invalid("annotation wrapper without a length"));
+      },
+      ANNOTATION_TYPE => {
+        let (_, wrapped) = split_annotations(&data[header.header_length..])?;
+        let wrapped_header = read_header(wrapped)?;
+        if wrapped_header.type_code == NULL_TYPE && wrapped_header.length.is_some() {
+          return Err(invalid("annotated padding"));
+        }
+        (end - wrapped.len(), wrapped_header)
+      },
+      _ => (0, header),
+    };
+
+    let value = LazyValue {
+      data: data,
+      value_start: value_start,
+      header: header,
+      symbols: symbols.clone(),
+      options: options.clone(),
+      depth: depth,
+    };
+    return Ok((Some(value), end));
+  }
+
+  /// Name of the value's ion type, as in `AnionValue::type_name`
+  pub fn type_name(&self) -> &'static str
+  {
+    TYPE_NAMES[self.header.type_code as usize]
+  }
+
+  pub fn is_null(&self) -> bool
+  {
+    self.header.length.is_none()
+  }
+
+  /// The encoded value, annotations included
+  pub fn encoded(&self) -> &'a [u8]
+  {
+    self.data
+  }
+
+  /// The value's annotations, in order
+  pub fn annotations(&self) -> Result<Vec<String>, BinaryError>
+  {
+    match self.annotation_ids()? {
+      Some(annotation_ids) => annotation_texts(annotation_ids, &self.symbols),
+      None => Ok(Vec::new()),
+    }
+  }
+
+  /// Symbol IDs of the annotations, if there are any
+  fn annotation_ids(&self) -> Result<Option<&'a [u8]>, BinaryError>
+  {
+    if self.value_start == 0 {
+      return Ok(None);
+    }
+    let wrapper = read_header(self.data)?;
+    let (annotation_ids, _) = split_annotations(&self.data[wrapper.header_length..])?;
+    return Ok(Some(annotation_ids));
+  }
+
+  /// Text of the first annotation, if there is one with known text; read
+  /// without decoding the value
+  fn first_annotation(&self) -> Result<Option<&str>, BinaryError>
+  {
+    let annotation_ids = match self.annotation_ids()? {
+      Some(annotation_ids) => annotation_ids,
+      None => return Ok(None),
+    };
+    let (sid, _) = read_var_uint(annotation_ids)?;
+    match usize::try_from(sid).map(|sid| self.symbols.text(sid)) {
+      Ok(Ok(text)) => Ok(text),
+      // undefined, and so not `$ion_symbol_table`; decoding reports it
+      _ => Ok(None),
+    }
+  }
+
+  /// Decode the whole value
+  pub fn decode(&self) -> Result<AnionValue, BinaryError>
+  {
+    let (value, _) = decode_nested(self.data, &self.symbols, &self.options, self.depth)?;
+    return Ok(value.expect("a lazy value is never padding"));
+  }
+
+  /// Decode the whole value, borrowing the text of strings and the bytes
+  /// of blobs and clobs. Symbols, field names and annotations are copied
+  /// from the symbol table.
+  pub fn decode_ref(&self) -> Result<AnionValueRef<'a>, BinaryError>
+  {
+    check_value_limits(&self.header, self.representation(), &self.options, self.depth)?;
+    let value = match self.header.type_code {
+      STRING_TYPE => AnionValueRef::String(self.as_str()?.map(Cow::Borrowed)),
+      BLOB_TYPE => AnionValueRef::Blob(self.as_bytes().map(Cow::Borrowed)),
+      CLOB_TYPE => AnionValueRef::Clob(self.as_bytes().map(Cow::Borrowed)),
+      LIST_TYPE | SEXP_TYPE | STRUCT_TYPE if self.is_null() => AnionValueRef::from(self.decode_unannotated()?),
+      LIST_TYPE => AnionValueRef::List(Some(self.decode_elements()?)),
+      SEXP_TYPE => AnionValueRef::SExp(Some(self.decode_elements()?)),
+      STRUCT_TYPE => {
+        let mut fields = Vec::new();
+        for field in self.fields() {
+          let (name, value) = field?;
+          check_limit(fields.len() + 1, self.options.max_container_length, Limit::ContainerLength)?;
+          fields.push((Cow::Owned(name), value.decode_ref()?));
+        }
+        AnionValueRef::Struct(Some(fields))
+      },
+      _ => AnionValueRef::from(self.decode_unannotated()?),
+    };
+
+    let annotations = self.annotations()?;
+    if annotations.is_empty() {
+      return Ok(value);
+    }
+    return Ok(AnionValueRef::Annotated(annotations.into_iter().map(Cow::Owned).collect(), Box::new(value)));
+  }
+
+  fn decode_elements(&self) -> Result<Vec<AnionValueRef<'a>>, BinaryError>
+  {
+    let mut elements = Vec::new();
+    for element in self.elements() {
+      check_limit(elements.len() + 1, self.options.max_container_length, Limit::ContainerLength)?;
+      elements.push(element?.decode_ref()?);
+    }
+    return Ok(elements);
+  }
+
+  fn decode_unannotated(&self) -> Result<AnionValue, BinaryError>
+  {
+    let (value, _) = decode_nested(&self.data[self.value_start..], &self.symbols, &self.options, self.depth)?;
+    return Ok(value.expect("a lazy value is never padding"));
+  }
+
+  /// The text of a non-null string, borrowed from the buffer; `None` for
+  /// other values
+  pub fn as_str(&self) -> Result<Option<&'a str>, BinaryError>
+  {
+    if self.header.type_code != STRING_TYPE || self.is_null() {
+      return Ok(None);
+    }
+    match str::from_utf8(self.representation()) {
+      Ok(text) => Ok(Some(text)),
+      Err(_) => Err(invalid("string isn't UTF-8")),
+    }
+  }
+
+  /// The bytes of a non-null blob or clob, borrowed from the buffer;
+  /// `None` for other values
+  pub fn as_bytes(&self) -> Option<&'a [u8]>
+  {
+    match self.header.type_code {
+      BLOB_TYPE | CLOB_TYPE if !self.is_null() => Some(self.representation()),
+      _ => None,
+    }
+  }
+
+  /// The unannotated value's representation, after its header
+  fn representation(&self) -> &'a [u8]
+  {
+    let start = self.value_start + self.header.header_length;
+    &self.data[start..start + self.header.length.unwrap_or(0)]
+  }
+
+  /// The first field of a struct with this name; `None` if there's no
+  /// such field or this isn't a non-null struct
+  pub fn get(&self, name: &str) -> Result<Option<LazyValue<'a>>, BinaryError>
+  {
+    if self.header.type_code != STRUCT_TYPE {
+      return Ok(None);
+    }
+    let mut children = self.children();
+    while let Some((sid, value)) = children.next_child()? {
+      let sid = usize::try_from(sid).map_err(|_| BinaryError::Overflow)?;
+      if self.symbols.text(sid)?.unwrap_or(::UNKNOWN_SYMBOL_TEXT) == name {
+        return Ok(Some(value));
+      }
+    }
+    return Ok(None);
+  }
+
+  /// Element `index` of a list or sexp; `None` if there are fewer
+  /// elements or this isn't a non-null list or sexp
+  pub fn index(&self, index: usize) -> Result<Option<LazyValue<'a>>, BinaryError>
+  {
+    match self.elements().nth(index) {
+      Some(Ok(value)) => Ok(Some(value)),
+      Some(Err(error)) => Err(error),
+      None => Ok(None),
+    }
+  }
+
+  /// The elements of a list or sexp; empty for other values
+  pub fn elements(&self) -> LazyElements<'a>
+  {
+    let mut children = self.children();
+    if self.header.type_code != LIST_TYPE && self.header.type_code != SEXP_TYPE {
+      children.data = &[];
+    }
+    LazyElements(children)
+  }
+
+  /// The fields of a struct; empty for other values
+  pub fn fields(&self) -> LazyFields<'a>
+  {
+    let mut children = self.children();
+    if self.header.type_code != STRUCT_TYPE {
+      children.data = &[];
+    }
+    LazyFields(children)
+  }
+
+  fn children(&self) -> Children<'a>
+  {
+    Children {
+      data: self.representation(),
+      offset: 0,
+      is_struct: self.header.type_code == STRUCT_TYPE,
+      symbols: self.symbols.clone(),
+      options: self.options.clone(),
+      depth: self.depth + 1,
+      failed: false,
+    }
+  }
+}
+
+/// A binary ion file mapped into memory, to read lazily
+#[cfg(feature = "mmap")]
+pub struct MappedFile {
+  /// `None` for an empty file, which can't be mapped
+  map: Option<Mmap>,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedFile {
+  /// Map a file into memory.
+  ///
+  /// # Safety
+  ///
+  /// Values borrow the mapped memory directly, so the file mustn't be
+  /// changed or truncated, by this process or any other, while it's open.
+  pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile>
+  {
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+      return Ok(MappedFile { map: None });
+    }
+    Ok(MappedFile { map: Some(Mmap::map(&file)?) })
+  }
+
+  /// The file's contents
+  pub fn bytes(&self) -> &[u8]
+  {
+    match self.map {
+      Some(ref map) => &map[..],
+      None => &[],
+    }
+  }
+
+  /// Read the file's top level values
+  pub fn values<'a>(&'a self) -> LazyReader<'a>
+  {
+    LazyReader::new(self.bytes())
+  }
+}
+
+/// Steps through the values of a container's representation
+struct Children<'a> {
+  data: &'a [u8],
+  offset: usize,
+  is_struct: bool,
+  symbols: Rc<SymbolTable>,
+  options: Rc<ReaderOptions>,
+
+  /// Number of containers the children are in
+  depth: usize,
+  failed: bool,
+}
+
+impl<'a> Children<'a> {
+  /// The next value, skipping padding, with its field's symbol ID in a
+  /// struct (0 otherwise)
+  fn next_child(&mut self) -> Result<Option<(u64, LazyValue<'a>)>, BinaryError>
+  {
+    while self.offset < self.data.len() {
+      let mut sid = 0;
+      if self.is_struct {
+        let (field, used) = read_var_uint(&self.data[self.offset..])?;
+        sid = field;
+        self.offset += used;
+      }
+      let (value, used) = LazyValue::read(&self.data[self.offset..], &self.symbols, &self.options, self.depth)?;
+      self.offset += used;
+      if let Some(value) = value {
+        return Ok(Some((sid, value)));
+      }
+    }
+    return Ok(None);
+  }
+
+  /// `next_child` for iterators, which stop after the first error
+  fn next_item(&mut self) -> Option<Result<(u64, LazyValue<'a>), BinaryError>>
+  {
+    if self.failed {
+      return None;
+    }
+    match self.next_child() {
+      Ok(child) => child.map(Ok),
+      Err(error) => {
+        self.failed = true;
+        Some(Err(error))
+      },
+    }
+  }
+}
+
+/// Iterator over the elements of a list or sexp; see `LazyValue::elements`
+pub struct LazyElements<'a>(Children<'a>);
+
+impl<'a> Iterator for LazyElements<'a> {
+  type Item = Result<LazyValue<'a>, BinaryError>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    self.0.next_item().map(|child| child.map(|(_, value)| value))
+  }
+}
+
+/// Iterator over the fields of a struct; see `LazyValue::fields`
+pub struct LazyFields<'a>(Children<'a>);
+
+impl<'a> Iterator for LazyFields<'a> {
+  type Item = Result<(String, LazyValue<'a>), BinaryError>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    let (sid, value) = match self.0.next_item()? {
+      Ok(child) => child,
+      Err(error) => return Some(Err(error)),
+    };
+    let name = usize::try_from(sid)
+      .map_err(|_| BinaryError::Overflow)
+      .and_then(|sid| Ok(self.0.symbols.text(sid)?.unwrap_or(::UNKNOWN_SYMBOL_TEXT)))
+      .map(String::from);
+    if name.is_err() {
+      self.0.failed = true;
+    }
+    Some(name.map(|name| (name, value)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  /// `{name: "abc", version: <invalid string>, symbols: [1, [2]], imports: name::7}`
+  /// then padding and `5`
+  fn records() -> Vec<u8>
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    data.extend(&[0xde, 0x96]);
+    data.extend(&[0x84, 0x83, b'a', b'b', b'c']);
+    data.extend(&[0x85, 0x82, 0xff, 0xfe]);
+    data.extend(&[0x87, 0xb5, 0x21, 0x01, 0xb2, 0x21, 0x02]);
+    data.extend(&[0x86, 0xe4, 0x81, 0x84, 0x21, 0x07]);
+    data.extend(&[0x00, 0x21, 0x05]);
+    data
+  }
+
+  #[test]
+  fn reading_top_level_values()
+  {
+    let data = records();
+    let values: Vec<LazyValue> = LazyReader::new(&data).map(Result::unwrap).collect();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[0].type_name(), "struct");
+    assert_eq!(values[0].encoded(), &data[4..28]);
+    assert_eq!(values[1].decode(), Ok(ion!(5)));
+
+    let mut reader = LazyReader::new(&data[..10]);
+    assert_eq!(reader.next().map(|value| value.map(|value| value.type_name())), Some(Err(BinaryError::UnexpectedEnd)));
+    assert!(reader.next().is_none());
+
+    assert!(LazyReader::new(&[0x21, 0x05]).next_value().is_err());
+
+    // lengths too large to index, alone, in a list and annotated
+    let too_long = [0x8e, 0x01, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0x7f, 0xff];
+    let mut data = VERSION_MARKER.to_vec();
+    data.extend(&too_long);
+    assert_eq!(LazyReader::new(&data).next_value().map(|value| value.is_some()), Err(BinaryError::Overflow));
+    let mut data = VERSION_MARKER.to_vec();
+    data.push(0xbb);
+    data.extend(&too_long);
+    let list = LazyReader::new(&data).next().unwrap().unwrap();
+    assert_eq!(list.index(0).map(|value| value.is_some()), Err(BinaryError::Overflow));
+    assert!(list.decode_ref().is_err());
+    let mut data = VERSION_MARKER.to_vec();
+    data.extend(&[0xed, 0x81, 0x84]);
+    data.extend(&too_long);
+    assert_eq!(LazyReader::new(&data).next_value().map(|value| value.is_some()), Err(BinaryError::Overflow));
+  }
+
+  #[test]
+  fn finding_fields_without_decoding_others()
+  {
+    let data = records();
+    let record = LazyReader::new(&data).next().unwrap().unwrap();
+
+    assert_eq!(record.get("name").unwrap().unwrap().decode(), Ok(ion!("abc")));
+    let element = record.get("symbols").unwrap().unwrap().index(1).unwrap().unwrap();
+    assert_eq!(element.index(0).unwrap().unwrap().decode(), Ok(ion!(2)));
+    assert!(record.get("symbols").unwrap().unwrap().index(2).unwrap().is_none());
+
+    let annotated = record.get("imports").unwrap().unwrap();
+    assert_eq!(annotated.type_name(), "int");
+    assert_eq!(annotated.annotations(), Ok(vec![String::from("name")]));
+    assert_eq!(annotated.decode(), Ok(ion!(name::7)));
+
+    assert!(record.get("max_id").unwrap().is_none());
+    assert!(record.index(0).unwrap().is_none());
+    assert!(record.get("version").unwrap().unwrap().decode().is_err());
+    assert!(record.decode().is_err());
+
+    let names: Result<Vec<String>, _> = record.fields().map(|field| field.map(|(name, _)| name)).collect();
+    assert_eq!(names, Ok(vec![String::from("name"), String::from("version"), String::from("symbols"), String::from("imports")]));
+    assert_eq!(record.elements().count(), 0);
+  }
+
+  #[test]
+  fn borrowing_strings_and_lobs()
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    // name::["abc", {{"hi"}}, {name: {{AQ==}}}, null.string, 5]
+    data.extend(&[0xee, 0x92, 0x81, 0x84, 0xbe, 0x8e, 0x83, b'a', b'b', b'c', 0x92, b'h', b'i']);
+    data.extend(&[0xd3, 0x84, 0xa1, 0x01, 0x8f, 0x21, 0x05]);
+
+    let value = LazyReader::new(&data).next().unwrap().unwrap();
+    assert_eq!(value.index(0).unwrap().unwrap().as_str(), Ok(Some("abc")));
+    assert_eq!(value.index(1).unwrap().unwrap().as_bytes(), Some(&b"hi"[..]));
+    assert_eq!(value.index(3).unwrap().unwrap().as_str(), Ok(None));
+    assert_eq!(value.index(4).unwrap().unwrap().as_bytes(), None);
+
+    let decoded = value.decode_ref().unwrap();
+    assert_eq!(decoded.to_owned(), value.decode().unwrap());
+    let elements = match decoded {
+      AnionValueRef::Annotated(_, ref list) => {
+        match **list {
+          AnionValueRef::List(Some(ref elements)) => elements.clone(),
+          _ => panic!("not a list: {:?}", list),
+        }
+      },
+      ref other => panic!("not annotated: {:?}", other),
+    };
+    assert!(match elements[0] { AnionValueRef::String(Some(Cow::Borrowed("abc"))) => true, _ => false });
+    assert!(match elements[1] { AnionValueRef::Clob(Some(Cow::Borrowed(b"hi"))) => true, _ => false });
+  }
+
+  #[cfg(feature = "mmap")]
+  #[test]
+  fn mapped_files()
+  {
+    use std::fs;
+
+    let path = ::std::env::temp_dir().join(format!("anion-mapped-{}.10n", ::std::process::id()));
+    fs::write(&path, records()).unwrap();
+    let file = unsafe { MappedFile::open(&path) }.unwrap();
+    assert_eq!(file.bytes(), &records()[..]);
+    assert_eq!(file.values().count(), 2);
+    drop(file);
+
+    fs::write(&path, b"").unwrap();
+    let empty = unsafe { MappedFile::open(&path) }.unwrap();
+    assert_eq!(empty.values().count(), 0);
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn local_symbol_tables()
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    // $ion_symbol_table::{symbols: ["x"]}
+    data.extend(&[0xe7, 0x81, 0x83, 0xd4, 0x87, 0xb2, 0x81, b'x']);
+    // {x: x}
+    data.extend(&[0xd3, 0x8a, 0x71, 0x0a]);
+
+    let values: Vec<LazyValue> = LazyReader::new(&data).map(Result::unwrap).collect();
+    assert_eq!(values.len(), 1);
+    assert_eq!(values[0].get("x").unwrap().unwrap().decode(), Ok(ion!(x)));
+
+    // other annotated structs aren't decoded to look for tables:
+    // name::{version: <invalid string>} and $99::{}
+    let mut data = VERSION_MARKER.to_vec();
+    data.extend(&[0xe7, 0x81, 0x84, 0xd4, 0x85, 0x82, 0xff, 0xfe]);
+    data.extend(&[0xe3, 0x81, 0xe3, 0xd0]);
+    let values: Vec<LazyValue> = LazyReader::new(&data).map(Result::unwrap).collect();
+    assert_eq!(values.len(), 2);
+    assert!(values[0].decode().is_err());
+    assert_eq!(values[1].decode(), Err(BinaryError::UnknownSymbolId(99)));
+  }
+
+  #[test]
+  fn limits_when_decoding()
+  {
+    let mut data = VERSION_MARKER.to_vec();
+    // [["abc"]]
+    data.extend(&[0xb6, 0xb5, 0x84, b'a', b'b', b'c', b'd']);
+
+    let options = ReaderOptions { max_depth: 1, max_string_length: 3, ..ReaderOptions::default() };
+    let value = LazyReader::with_options(&data, options).next().unwrap().unwrap();
+    let inner = value.index(0).unwrap().unwrap();
+    assert_eq!(value.decode(), Err(BinaryError::LimitExceeded(Limit::Depth)));
+    assert_eq!(inner.decode_ref().unwrap_err(), BinaryError::LimitExceeded(Limit::Depth));
+    assert_eq!(inner.index(0).unwrap().unwrap().decode(), Err(BinaryError::LimitExceeded(Limit::StringLength)));
+    assert_eq!(LazyReader::new(&data).next().unwrap().unwrap().decode(), Ok(ion!([["abcd"]])));
+  }
+}
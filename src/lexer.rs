@@ -0,0 +1,1695 @@
+//! Ion text tokenizer
+//!
+//! `Lexer` splits ion text into tokens one at a time, reading bytes from a
+//! `Source`: `StrSource` reads a string, and token text borrows from it;
+//! `ReadSource` reads any `io::Read` through a small buffer, so only the
+//! token being read is held in memory; `ChunkSource` is fed data as it
+//! arrives.
+//!
+//! Operator symbols such as `+` or `>=` are only tokens inside
+//! s-expressions, so the caller says whether it's reading one.
+
+use super::{Decimal, Int, Timestamp, TimestampPrecision};
+use reader::{Limit, ParseError, ReaderOptions, check_limit};
+use span::{Location, Span};
+use timestamp::days_in_month;
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::f64;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+use std::str::{Chars, FromStr};
+
+/// Bytes for the lexer, read one at a time with a few bytes of lookahead
+pub trait Source<'a> {
+  /// The byte `offset` places ahead, without consuming it; `None` at the
+  /// end of input
+  fn peek(&mut self, offset: usize) -> Result<Option<u8>, ParseError>;
+
+  /// Consume the next byte, which has been peeked
+  fn advance(&mut self);
+
+  /// Start a token's text at the next byte
+  fn mark(&mut self);
+
+  /// Text consumed since `mark`
+  fn marked(&mut self) -> Result<Cow<'a, str>, ParseError>;
+
+  /// Where the next byte is
+  fn location(&self) -> Location;
+
+  /// Remember the current position as the start of a top level value
+  fn checkpoint(&mut self)
+  {
+  }
+
+  /// Go back to the last checkpoint, after running out of data part way
+  /// through a value; only sources which can run out (`ChunkSource`) need
+  /// to
+  fn rewind(&mut self)
+  {
+  }
+
+  /// Whether there may be a whole top level value after the checkpoint;
+  /// if not, reading one would only run out of data again
+  fn ready(&mut self) -> bool
+  {
+    true
+  }
+}
+
+/// Reads a string; token text borrows from it
+pub struct StrSource<'a> {
+  text: &'a str,
+  location: Location,
+  mark: usize,
+}
+
+impl<'a> StrSource<'a> {
+  pub fn new(text: &'a str) -> StrSource<'a>
+  {
+    StrSource {
+      text: text,
+      location: Location::start(),
+      mark: 0,
+    }
+  }
+}
+
+impl<'a> Source<'a> for StrSource<'a> {
+  fn peek(&mut self, offset: usize) -> Result<Option<u8>, ParseError>
+  {
+    Ok(self.text.as_bytes().get(self.location.offset + offset).cloned())
+  }
+
+  fn advance(&mut self)
+  {
+    self.location.advance(self.text.as_bytes()[self.location.offset]);
+  }
+
+  fn mark(&mut self)
+  {
+    self.mark = self.location.offset;
+  }
+
+  fn marked(&mut self) -> Result<Cow<'a, str>, ParseError>
+  {
+    let text = self.text;
+    match text.get(self.mark..self.location.offset) {
+      Some(marked) => Ok(Cow::Borrowed(marked)),
+      None => Err(ParseError::Syntax(String::from("token splits a character"))),
+    }
+  }
+
+  fn location(&self) -> Location
+  {
+    self.location
+  }
+}
+
+const READ_SIZE: usize = 8 * 1024;
+
+/// Reads from an `io::Read` through a buffer; token text is copied out
+pub struct ReadSource<R> {
+  reader: R,
+  buffer: Vec<u8>,
+
+  /// Index of the next byte in `buffer`
+  next: usize,
+  location: Location,
+
+  /// Bytes of the token being read, once marked
+  token: Option<Vec<u8>>,
+}
+
+impl<R: Read> ReadSource<R> {
+  pub fn new(reader: R) -> ReadSource<R>
+  {
+    ReadSource {
+      reader: reader,
+      buffer: Vec::new(),
+      next: 0,
+      location: Location::start(),
+      token: None,
+    }
+  }
+
+  /// Read more input into the buffer, dropping consumed bytes; false at
+  /// the end of input
+  fn fill(&mut self) -> Result<bool, ParseError>
+  {
+    self.buffer.drain(..self.next);
+    self.next = 0;
+
+    let length = self.buffer.len();
+    self.buffer.resize(length + READ_SIZE, 0);
+    loop {
+      match self.reader.read(&mut self.buffer[length..]) {
+        Ok(read) => {
+          self.buffer.truncate(length + read);
+          return Ok(read != 0);
+        },
+        Err(ref error) if error.kind() == io::ErrorKind::Interrupted => continue,
+        Err(error) => {
+          self.buffer.truncate(length);
+          return Err(ParseError::Io(error.kind(), error.to_string()));
+        },
+      }
+    }
+  }
+}
+
+impl<'a, R: Read> Source<'a> for ReadSource<R> {
+  fn peek(&mut self, offset: usize) -> Result<Option<u8>, ParseError>
+  {
+    while self.next + offset >= self.buffer.len() {
+      if !self.fill()? {
+        return Ok(None);
+      }
+    }
+    Ok(Some(self.buffer[self.next + offset]))
+  }
+
+  fn advance(&mut self)
+  {
+    if let Some(ref mut token) = self.token {
+      token.push(self.buffer[self.next]);
+    }
+    self.location.advance(self.buffer[self.next]);
+    self.next += 1;
+  }
+
+  fn mark(&mut self)
+  {
+    self.token = Some(Vec::new());
+  }
+
+  fn marked(&mut self) -> Result<Cow<'a, str>, ParseError>
+  {
+    let bytes = self.token.take().unwrap_or_default();
+    String::from_utf8(bytes).map(Cow::Owned).map_err(|_| ParseError::Syntax(String::from("invalid UTF-8")))
+  }
+
+  fn location(&self) -> Location
+  {
+    self.location
+  }
+}
+
+/// Reads data fed to it in chunks, as it arrives.
+///
+/// Peeking past the data so far fails with `ParseError::Incomplete`
+/// until `finish` is called. Bytes before the last checkpoint are dropped
+/// as more data is fed, so only the value being read is kept.
+///
+/// Each chunk is scanned once for where top level values may end, and a
+/// value isn't read until one of those is in the data, so a value split
+/// between many chunks is still only lexed about once.
+pub struct ChunkSource {
+  buffer: Vec<u8>,
+
+  /// Index of the next byte in `buffer`
+  next: usize,
+  checkpoint: usize,
+  mark: usize,
+
+  /// Where the next byte and the checkpoint are in the whole input
+  location: Location,
+  checkpoint_location: Location,
+  finished: bool,
+  boundaries: Boundaries,
+
+  /// `boundaries.last` when reading last ran out of data
+  tried: usize,
+}
+
+impl ChunkSource {
+  pub fn new() -> ChunkSource
+  {
+    ChunkSource {
+      buffer: Vec::new(),
+      next: 0,
+      checkpoint: 0,
+      mark: 0,
+      location: Location::start(),
+      checkpoint_location: Location::start(),
+      finished: false,
+      boundaries: Boundaries::new(),
+      tried: 0,
+    }
+  }
+
+  /// Add the next chunk of input
+  pub fn feed(&mut self, data: &[u8])
+  {
+    // the scan may be waiting on a byte or two before the checkpoint
+    let dropped = self.checkpoint.min(self.boundaries.scanned);
+    self.buffer.drain(..dropped);
+    self.next -= dropped;
+    self.checkpoint -= dropped;
+    self.mark = self.mark.saturating_sub(dropped);
+    self.boundaries.drop_front(dropped);
+    self.tried = self.tried.saturating_sub(dropped);
+    self.buffer.extend_from_slice(data);
+  }
+
+  /// Mark the end of input
+  pub fn finish(&mut self)
+  {
+    self.finished = true;
+  }
+}
+
+impl Default for ChunkSource {
+  fn default() -> ChunkSource
+  {
+    ChunkSource::new()
+  }
+}
+
+impl<'a> Source<'a> for ChunkSource {
+  fn peek(&mut self, offset: usize) -> Result<Option<u8>, ParseError>
+  {
+    match self.buffer.get(self.next + offset) {
+      Some(&byte) => Ok(Some(byte)),
+      None if self.finished => Ok(None),
+      None => Err(ParseError::Incomplete),
+    }
+  }
+
+  fn advance(&mut self)
+  {
+    self.location.advance(self.buffer[self.next]);
+    self.next += 1;
+  }
+
+  fn mark(&mut self)
+  {
+    self.mark = self.next;
+  }
+
+  fn marked(&mut self) -> Result<Cow<'a, str>, ParseError>
+  {
+    let bytes = self.buffer[self.mark..self.next].to_vec();
+    String::from_utf8(bytes).map(Cow::Owned).map_err(|_| ParseError::Syntax(String::from("invalid UTF-8")))
+  }
+
+  fn location(&self) -> Location
+  {
+    self.location
+  }
+
+  fn checkpoint(&mut self)
+  {
+    self.checkpoint = self.next;
+    self.checkpoint_location = self.location;
+  }
+
+  fn rewind(&mut self)
+  {
+    self.next = self.checkpoint;
+    self.location = self.checkpoint_location;
+    self.tried = self.boundaries.last;
+  }
+
+  fn ready(&mut self) -> bool
+  {
+    if self.finished {
+      return true;
+    }
+    self.boundaries.scan(&self.buffer);
+    let last = self.boundaries.last;
+    return last > self.next && last > self.tried;
+  }
+}
+
+/// What the bytes `Boundaries` is scanning are in
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Scanning {
+  Text,
+  String,
+  LongString,
+  QuotedSymbol,
+  LineComment,
+  BlockComment,
+}
+
+/// Finds where top level values may end in a `ChunkSource`'s buffer:
+/// after whitespace, a closing bracket or a closing quote outside any
+/// container, string, comment or lob. Its state is kept between chunks so
+/// each byte is scanned once; it doesn't check the text is valid.
+struct Boundaries {
+  /// Index in the buffer of the next byte to scan
+  scanned: usize,
+  scanning: Scanning,
+
+  /// Whether the last byte was a backslash escaping the next
+  escaped: bool,
+  in_lob: bool,
+  depth: usize,
+
+  /// Index just past the last place a top level value may end
+  last: usize,
+}
+
+impl Boundaries {
+  fn new() -> Boundaries
+  {
+    Boundaries {
+      scanned: 0,
+      scanning: Scanning::Text,
+      escaped: false,
+      in_lob: false,
+      depth: 0,
+      last: 0,
+    }
+  }
+
+  /// The first `count` bytes have been dropped from the buffer
+  fn drop_front(&mut self, count: usize)
+  {
+    self.scanned -= count;
+    self.last = self.last.saturating_sub(count);
+  }
+
+  /// Scan as far into `buffer` as can be understood without more data
+  fn scan(&mut self, buffer: &[u8])
+  {
+    while self.scanned < buffer.len() {
+      let (length, may_end) = match self.step(&buffer[self.scanned..]) {
+        Some(step) => step,
+        None => return,
+      };
+      self.scanned += length;
+      if may_end && self.depth == 0 && !self.in_lob && self.scanning == Scanning::Text {
+        self.last = self.scanned;
+      }
+    }
+  }
+
+  /// Scan the start of `rest`: the number of bytes scanned, and whether a
+  /// value may end after them; `None` if that needs more data
+  fn step(&mut self, rest: &[u8]) -> Option<(usize, bool)>
+  {
+    let byte = rest[0];
+    if self.escaped {
+      self.escaped = false;
+      return Some((1, false));
+    }
+    let starts = |text: &[u8]| -> Option<bool> {
+      if rest.len() < text.len() { None } else { Some(rest.starts_with(text)) }
+    };
+
+    match self.scanning {
+      Scanning::String | Scanning::QuotedSymbol => {
+        let quote = if self.scanning == Scanning::String { b'"' } else { b'\'' };
+        if byte == b'\\' {
+          self.escaped = true;
+        } else if byte == quote {
+          self.scanning = Scanning::Text;
+          return Some((1, true));
+        }
+      },
+      Scanning::LongString => {
+        if byte == b'\\' {
+          self.escaped = true;
+        } else if byte == b'\'' && starts(b"'''")? {
+          self.scanning = Scanning::Text;
+          return Some((3, true));
+        }
+      },
+      Scanning::LineComment => {
+        if byte == b'\n' {
+          self.scanning = Scanning::Text;
+          return Some((1, true));
+        }
+      },
+      Scanning::BlockComment => {
+        if byte == b'*' && starts(b"*/")? {
+          self.scanning = Scanning::Text;
+          return Some((2, true));
+        }
+      },
+      Scanning::Text => match byte {
+        b'"' => self.scanning = Scanning::String,
+        b'\'' => {
+          if starts(b"'''")? {
+            self.scanning = Scanning::LongString;
+            return Some((3, false));
+          }
+          self.scanning = Scanning::QuotedSymbol;
+        },
+        // base64 can have slashes
+        b'/' if !self.in_lob => {
+          if starts(b"//")? {
+            self.scanning = Scanning::LineComment;
+            return Some((2, false));
+          }
+          if starts(b"/*")? {
+            self.scanning = Scanning::BlockComment;
+            return Some((2, false));
+          }
+        },
+        b'{' if starts(b"{{")? => {
+          self.in_lob = true;
+          return Some((2, false));
+        },
+        b'}' if self.in_lob => {
+          if starts(b"}}")? {
+            self.in_lob = false;
+            return Some((2, true));
+          }
+        },
+        b'[' | b'(' | b'{' => self.depth += 1,
+        b']' | b')' | b'}' => {
+          self.depth = self.depth.saturating_sub(1);
+          return Some((1, true));
+        },
+        b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => return Some((1, true)),
+        _ => (),
+      },
+    }
+    return Some((1, false));
+  }
+}
+
+/// A token of ion text
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token<'a> {
+  /// `null` or a typed null, by type name (`"null"` for `null`)
+  Null(&'static str),
+  Bool(bool),
+  Int(Int),
+  Float(f64),
+  Decimal(Decimal),
+  Timestamp(Timestamp),
+
+  /// A string's text, long strings already joined
+  String(Cow<'a, str>),
+
+  /// An unquoted symbol, which may be a symbol ID such as `$10`
+  Identifier(Cow<'a, str>),
+  QuotedSymbol(Cow<'a, str>),
+  Operator(Cow<'a, str>),
+  Blob(Vec<u8>),
+  Clob(Vec<u8>),
+  ListStart,
+  ListEnd,
+  SExpStart,
+  SExpEnd,
+  StructStart,
+  StructEnd,
+  Comma,
+  Colon,
+  DoubleColon,
+
+  /// End of input
+  End,
+}
+
+impl<'a> Token<'a> {
+  /// Ion text of the token, for error messages
+  pub fn describe(&self) -> String
+  {
+    match *self {
+      Token::Null("null") => String::from("null"),
+      Token::Null(type_name) => format!("null.{}", type_name),
+      Token::Bool(b) => b.to_string(),
+      Token::Int(ref i) => i.to_string(),
+      Token::Float(f) => f.to_string(),
+      Token::Decimal(ref d) => d.to_string(),
+      Token::Timestamp(ref t) => t.to_string(),
+      Token::String(ref s) => format!("{:?}", s),
+      Token::Identifier(ref s) | Token::Operator(ref s) => s.to_string(),
+      Token::QuotedSymbol(ref s) => format!("'{}'", s),
+      Token::Blob(_) | Token::Clob(_) => String::from("{{...}}"),
+      Token::ListStart => String::from("["),
+      Token::ListEnd => String::from("]"),
+      Token::SExpStart => String::from("("),
+      Token::SExpEnd => String::from(")"),
+      Token::StructStart => String::from("{"),
+      Token::StructEnd => String::from("}"),
+      Token::Comma => String::from(","),
+      Token::Colon => String::from(":"),
+      Token::DoubleColon => String::from("::"),
+      Token::End => String::from("end of input"),
+    }
+  }
+}
+
+const NULL_TYPES: [&'static str; 13] = [
+  "null", "bool", "int", "float", "decimal", "timestamp", "string", "symbol", "blob", "clob", "struct", "list", "sexp",
+];
+
+fn is_whitespace(byte: u8) -> bool
+{
+  match byte {
+    b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c => true,
+    _ => false,
+  }
+}
+
+/// Whether a byte may be written unescaped in a string, symbol or clob:
+/// not a control character other than whitespace, and in clobs only
+/// ascii
+fn is_text_byte(byte: u8, clob: bool) -> bool
+{
+  (byte >= 0x20 || is_whitespace(byte)) && !(clob && byte >= 0x80)
+}
+
+/// Bytes which end a token that isn't otherwise delimited
+fn is_delimiter(byte: u8) -> bool
+{
+  match byte {
+    b'{' | b'}' | b'[' | b']' | b'(' | b')' | b',' | b'"' | b'\'' => true,
+    _ => is_whitespace(byte),
+  }
+}
+
+fn is_identifier_start(byte: u8) -> bool
+{
+  byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$'
+}
+
+fn is_identifier_part(byte: u8) -> bool
+{
+  is_identifier_start(byte) || byte.is_ascii_digit()
+}
+
+fn is_operator(byte: u8) -> bool
+{
+  b"!#%&*+-./;<=>?@^`|~".contains(&byte)
+}
+
+/// Splits ion text into tokens
+pub struct Lexer<'a, S> {
+  source: S,
+
+  /// Limits from `ReaderOptions`
+  max_digits: usize,
+  max_exponent: usize,
+  max_fraction_digits: usize,
+  max_string_length: usize,
+  max_lob_length: usize,
+
+  /// Span of the last token read
+  span: Span,
+
+  /// Where the last token ended, if that's before the source's location:
+  /// long strings look past the whitespace after them for another part
+  token_end: Option<Location>,
+  text: PhantomData<Cow<'a, str>>,
+}
+
+impl<'a, S: Source<'a>> Lexer<'a, S> {
+  pub fn new(source: S) -> Lexer<'a, S>
+  {
+    Lexer {
+      source: source,
+      max_digits: usize::max_value(),
+      max_exponent: usize::max_value(),
+      max_fraction_digits: usize::max_value(),
+      max_string_length: usize::max_value(),
+      max_lob_length: usize::max_value(),
+      span: Span::default(),
+      token_end: None,
+      text: PhantomData,
+    }
+  }
+
+  /// Fail with `LimitExceeded` on numbers, strings and lobs beyond the
+  /// limits of `options`, before reading all of them in
+  pub fn limit(&mut self, options: &ReaderOptions)
+  {
+    self.max_digits = options.max_int_digits;
+    self.max_exponent = options.max_exponent;
+    self.max_fraction_digits = options.max_fraction_digits;
+    self.max_string_length = options.max_string_length;
+    self.max_lob_length = options.max_lob_length;
+  }
+
+  /// The most bytes `limit` allows
+  fn max_length(&self, limit: Limit) -> usize
+  {
+    if limit == Limit::LobLength { self.max_lob_length } else { self.max_string_length }
+  }
+
+  /// Number of bytes read
+  pub fn position(&self) -> usize
+  {
+    self.source.location().offset
+  }
+
+  /// Where the next byte is
+  pub fn location(&self) -> Location
+  {
+    self.source.location()
+  }
+
+  /// Span of the last token read, or of the text which failed to be one;
+  /// empty for `Token::End`
+  pub fn span(&self) -> Span
+  {
+    self.span
+  }
+
+  pub fn source_mut(&mut self) -> &mut S
+  {
+    &mut self.source
+  }
+
+  /// Read the next token; operators are only tokens `in_sexp`
+  pub fn next_token(&mut self, in_sexp: bool) -> Result<Token<'a>, ParseError>
+  {
+    self.skip_whitespace()?;
+    let start = self.source.location();
+    self.token_end = None;
+    let token = self.token(in_sexp);
+    let end = self.token_end.take().unwrap_or_else(|| self.source.location());
+    self.span = Span { start: start, end: end };
+    return token;
+  }
+
+  fn token(&mut self, in_sexp: bool) -> Result<Token<'a>, ParseError>
+  {
+    let byte = match self.peek(0)? {
+      Some(byte) => byte,
+      None => return Ok(Token::End),
+    };
+
+    match byte {
+      b'[' => self.punctuation(Token::ListStart),
+      b']' => self.punctuation(Token::ListEnd),
+      b'(' => self.punctuation(Token::SExpStart),
+      b')' => self.punctuation(Token::SExpEnd),
+      b'{' if self.peek(1)? == Some(b'{') => self.lob(),
+      b'{' => self.punctuation(Token::StructStart),
+      b'}' => self.punctuation(Token::StructEnd),
+      b',' => self.punctuation(Token::Comma),
+      b':' if self.peek(1)? == Some(b':') => {
+        self.source.advance();
+        self.punctuation(Token::DoubleColon)
+      },
+      b':' => self.punctuation(Token::Colon),
+      b'"' => self.quoted(b'"', Limit::StringLength).map(Token::String),
+      b'\'' if self.long_string_follows()? => self.long_strings().map(Token::String),
+      b'\'' => self.quoted(b'\'', Limit::StringLength).map(Token::QuotedSymbol),
+      b'0'..=b'9' if self.timestamp_follows()? => self.timestamp(),
+      b'0'..=b'9' => self.number(),
+      b'-' if self.peek(1)?.map_or(false, |next| next.is_ascii_digit()) => self.number(),
+      b'+' | b'-' if self.infinity_follows()? => {
+        self.advance_by(4);
+        Ok(Token::Float(if byte == b'+' { f64::INFINITY } else { f64::NEG_INFINITY }))
+      },
+      _ if is_identifier_start(byte) => self.word(),
+      _ if is_operator(byte) && in_sexp => self.operator(),
+      _ => Err(self.invalid("")),
+    }
+  }
+
+  /// Whether `::` comes next, skipping whitespace but not consuming it
+  pub fn at_double_colon(&mut self) -> Result<bool, ParseError>
+  {
+    self.skip_whitespace()?;
+    Ok(self.peek(0)? == Some(b':') && self.peek(1)? == Some(b':'))
+  }
+
+  fn peek(&mut self, offset: usize) -> Result<Option<u8>, ParseError>
+  {
+    self.source.peek(offset)
+  }
+
+  fn advance_by(&mut self, count: usize)
+  {
+    for _ in 0..count {
+      self.source.advance();
+    }
+  }
+
+  fn punctuation(&mut self, token: Token<'a>) -> Result<Token<'a>, ParseError>
+  {
+    self.source.advance();
+    Ok(token)
+  }
+
+  /// Skip whitespace and comments
+  fn skip_whitespace(&mut self) -> Result<(), ParseError>
+  {
+    loop {
+      let byte = self.peek(0)?;
+      let next = if byte == Some(b'/') { self.peek(1)? } else { None };
+      match (byte, next) {
+        (Some(byte), _) if is_whitespace(byte) => self.source.advance(),
+        (Some(b'/'), Some(b'/')) => {
+          while let Some(byte) = self.peek(0)? {
+            self.source.advance();
+            if byte == b'\n' {
+              break;
+            }
+          }
+        },
+        (Some(b'/'), Some(b'*')) => {
+          self.advance_by(2);
+          loop {
+            match self.peek(0)? {
+              None => return Err(ParseError::UnexpectedEnd),
+              Some(b'*') if self.peek(1)? == Some(b'/') => break,
+              _ => self.source.advance(),
+            }
+          }
+          self.advance_by(2);
+        },
+        _ => return Ok(()),
+      }
+    }
+  }
+
+  /// Whether the byte `offset` ahead may follow a number: whitespace, a
+  /// comment, a delimiter or the end of input
+  fn stop_at(&mut self, offset: usize) -> Result<bool, ParseError>
+  {
+    Ok(match self.peek(offset)? {
+      None => true,
+      Some(b'/') => {
+        let next = self.peek(offset + 1)?;
+        next == Some(b'/') || next == Some(b'*')
+      },
+      Some(byte) => is_delimiter(byte),
+    })
+  }
+
+  fn infinity_follows(&mut self) -> Result<bool, ParseError>
+  {
+    let inf = self.peek(1)? == Some(b'i') && self.peek(2)? == Some(b'n') && self.peek(3)? == Some(b'f');
+    Ok(inf && self.stop_at(4)?)
+  }
+
+  /// Whether a year and the `-` or `T` after it come next
+  fn timestamp_follows(&mut self) -> Result<bool, ParseError>
+  {
+    for offset in 0..4 {
+      if !self.peek(offset)?.map_or(false, |byte| byte.is_ascii_digit()) {
+        return Ok(false);
+      }
+    }
+    Ok(self.peek(4)? == Some(b'-') || self.peek(4)? == Some(b'T'))
+  }
+
+  fn long_string_follows(&mut self) -> Result<bool, ParseError>
+  {
+    Ok(self.peek(0)? == Some(b'\'') && self.peek(1)? == Some(b'\'') && self.peek(2)? == Some(b'\''))
+  }
+
+  /// Move past an escape in a string, returning the fewest bytes it can
+  /// unescape to: none for an escaped line break, at least one otherwise
+  fn escape(&mut self) -> Result<usize, ParseError>
+  {
+    self.source.advance();
+    let byte = self.peek(0)?.ok_or(ParseError::UnexpectedEnd)?;
+    self.source.advance();
+    if byte == b'\r' && self.peek(0)? == Some(b'\n') {
+      self.source.advance();
+    }
+
+    // a character code is one character, however many digits it has
+    let digits = match byte {
+      b'x' => 2,
+      b'u' => 4,
+      b'U' => 8,
+      _ => 0,
+    };
+    for _ in 0..digits {
+      if !self.peek(0)?.map_or(false, |byte| byte.is_ascii_hexdigit()) {
+        break;
+      }
+      self.source.advance();
+    }
+    Ok(if byte == b'\n' || byte == b'\r' { 0 } else { 1 })
+  }
+
+  /// The error for text which can't start a token: a syntax error quoting
+  /// `prefix` and the text up to the next delimiter
+  fn invalid(&mut self, prefix: &str) -> ParseError
+  {
+    match self.rest() {
+      Ok(rest) => ParseError::Syntax(format!("{}{}", prefix, rest)),
+      Err(error) => error,
+    }
+  }
+
+  /// Text up to the next whitespace or delimiter, at least one byte
+  fn rest(&mut self) -> Result<Cow<'a, str>, ParseError>
+  {
+    self.source.mark();
+    let mut first = true;
+    while let Some(byte) = self.peek(0)? {
+      if is_delimiter(byte) && !first {
+        break;
+      }
+      self.source.advance();
+      first = false;
+    }
+    self.source.marked()
+  }
+
+  /// An int, decimal or float, which must be followed by a stop character
+  fn number(&mut self) -> Result<Token<'a>, ParseError>
+  {
+    self.source.mark();
+    while let Some(byte) = self.peek(0)? {
+      if !(byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'.' || byte == b'+' || byte == b'-') {
+        break;
+      }
+      self.source.advance();
+    }
+    let run = self.source.marked()?;
+
+    let (kind, length) = scan_number(run.as_bytes());
+    if length == run.len() && self.stop_at(0)? {
+      if kind != NumberKind::Float {
+        check_limit(count_digits(&run), self.max_digits, Limit::IntDigits)?;
+      }
+      if kind != NumberKind::Int {
+        check_limit(exponent_magnitude(&run), self.max_exponent, Limit::Exponent)?;
+      }
+      return match kind {
+        NumberKind::Int => int_from_token(&run).map(Token::Int),
+        NumberKind::Decimal => {
+          let decimal = Decimal::from_str(&run).map_err(|_| ParseError::Syntax(run.to_string()))?;
+          let exponent = usize::try_from((decimal.exponent() as i128).abs()).unwrap_or(usize::max_value());
+          check_limit(exponent, self.max_exponent, Limit::Exponent)?;
+          Ok(Token::Decimal(decimal))
+        },
+        NumberKind::Float => Ok(Token::Float(float_from_token(&run))),
+      };
+    }
+
+    // the rest of the run, and anything after it up to a stop character
+    let rest = if length == run.len() { self.rest()?.to_string() } else { String::new() };
+    if length == 0 {
+      return Err(ParseError::Syntax(format!("{}{}", run, rest)));
+    }
+    Err(ParseError::NumberNotStopped(String::from(&run[..length]), format!("{}{}", &run[length..], rest)))
+  }
+
+  /// A timestamp, which must be followed by a stop character
+  fn timestamp(&mut self) -> Result<Token<'a>, ParseError>
+  {
+    self.source.mark();
+    let mut fraction = None;
+    while let Some(byte) = self.peek(0)? {
+      if !(byte.is_ascii_digit() || b"-+:.TZ".contains(&byte)) {
+        break;
+      }
+      fraction = match (fraction, byte) {
+        (None, b'.') => Some(0),
+        (Some(digits), b'0'..=b'9') => Some(digits + 1),
+        (fraction, _) => fraction,
+      };
+      check_limit(fraction.unwrap_or(0), self.max_fraction_digits, Limit::FractionDigits)?;
+      self.source.advance();
+    }
+    let text = self.source.marked()?;
+
+    if !self.stop_at(0)? {
+      return Err(self.invalid(&text));
+    }
+    timestamp_from_token(&text).map(Token::Timestamp)
+  }
+
+  /// Identifiers and the keywords among them
+  fn word(&mut self) -> Result<Token<'a>, ParseError>
+  {
+    self.source.mark();
+    while self.peek(0)?.map_or(false, is_identifier_part) {
+      self.source.advance();
+    }
+    let word = self.source.marked()?;
+
+    match &*word {
+      "true" => Ok(Token::Bool(true)),
+      "false" => Ok(Token::Bool(false)),
+      "nan" if self.stop_at(0)? => Ok(Token::Float(f64::NAN)),
+      "nan" => Err(self.invalid("nan")),
+      "null" if self.peek(0)? == Some(b'.') => {
+        self.source.advance();
+        self.source.mark();
+        while self.peek(0)?.map_or(false, is_identifier_part) {
+          self.source.advance();
+        }
+        let type_name = self.source.marked()?;
+        match NULL_TYPES.iter().find(|&&name| name == type_name) {
+          Some(&name) => Ok(Token::Null(name)),
+          None => Err(ParseError::Syntax(format!("null.{}", type_name))),
+        }
+      },
+      "null" => Ok(Token::Null("null")),
+      _ => Ok(Token::Identifier(word)),
+    }
+  }
+
+  /// A run of operator characters, stopping before any comment
+  fn operator(&mut self) -> Result<Token<'a>, ParseError>
+  {
+    self.source.mark();
+    while let Some(byte) = self.peek(0)? {
+      if !is_operator(byte) || byte == b'/' && self.stop_at(0)? {
+        break;
+      }
+      self.source.advance();
+    }
+    self.source.marked().map(Token::Operator)
+  }
+
+  /// Text between `quote`s on one line, unescaped; borrowed if there are
+  /// no escapes. It may be no longer than `limit` allows, and is a clob's
+  /// if that's the lob length.
+  fn quoted(&mut self, quote: u8, limit: Limit) -> Result<Cow<'a, str>, ParseError>
+  {
+    let max = self.max_length(limit);
+    let clob = limit == Limit::LobLength;
+    self.source.advance();
+    self.source.mark();
+
+    let mut escaped = false;
+    let mut length = 0;
+    loop {
+      match self.peek(0)? {
+        None => return Err(ParseError::UnexpectedEnd),
+        Some(byte) if byte == quote => break,
+        Some(b'\n') | Some(b'\r') => return Err(self.invalid("")),
+        Some(b'\\') => {
+          escaped = true;
+          length += self.escape()?;
+        },
+        Some(byte) if !is_text_byte(byte, clob) => return Err(self.invalid("")),
+        Some(_) => {
+          length += 1;
+          self.source.advance();
+        },
+      }
+      check_limit(length, max, limit)?;
+    }
+
+    let text = self.source.marked()?;
+    self.source.advance();
+    if escaped {
+      unescape(&text).map(Cow::Owned)
+    } else {
+      Ok(text)
+    }
+  }
+
+  /// One or more `'''` strings separated by whitespace, joined
+  fn long_strings(&mut self) -> Result<Cow<'a, str>, ParseError>
+  {
+    let mut parts = Vec::new();
+    let mut length = 0;
+    while self.long_string_follows()? {
+      self.advance_by(3);
+      self.source.mark();
+
+      let mut escaped = false;
+      loop {
+        match self.peek(0)? {
+          None => return Err(ParseError::UnexpectedEnd),
+          Some(b'\'') if self.long_string_follows()? => break,
+          Some(b'\\') => {
+            escaped = true;
+            length += self.escape()?;
+          },
+          Some(byte) if !is_text_byte(byte, false) => return Err(self.invalid("")),
+          Some(_) => {
+            length += 1;
+            self.source.advance();
+          },
+        }
+        check_limit(length, self.max_string_length, Limit::StringLength)?;
+      }
+
+      let text = self.source.marked()?;
+      self.advance_by(3);
+      parts.push(if escaped { Cow::Owned(unescape(&text)?) } else { text });
+      self.token_end = Some(self.source.location());
+      self.skip_whitespace()?;
+    }
+
+    if parts.len() == 1 {
+      return Ok(parts.pop().unwrap());
+    }
+    Ok(Cow::Owned(parts.concat()))
+  }
+
+  /// A blob of base64, or a clob string, between `{{` and `}}`
+  fn lob(&mut self) -> Result<Token<'a>, ParseError>
+  {
+    self.advance_by(2);
+    self.skip_lob_whitespace()?;
+
+    let token = match self.peek(0)? {
+      Some(b'"') => {
+        let text = self.quoted(b'"', Limit::LobLength)?;
+        Token::Clob(clob_bytes(&text)?)
+      },
+      Some(b'\'') if self.long_string_follows()? => {
+        let mut bytes = Vec::new();
+        while self.long_string_follows()? {
+          // long strings in clobs may only be separated by whitespace
+          let text = self.long_string_in_lob(bytes.len())?;
+          bytes.extend(clob_bytes(&text)?);
+        }
+        Token::Clob(bytes)
+      },
+      _ => {
+        self.source.mark();
+        let mut characters: usize = 0;
+        while let Some(byte) = self.peek(0)? {
+          if byte == b'}' {
+            break;
+          }
+          if !is_whitespace(byte) {
+            // every 4 characters of base64 are 3 bytes, less up to 2 of
+            // padding at the end
+            characters += 1;
+            check_limit((characters / 4 * 3).saturating_sub(2), self.max_lob_length, Limit::LobLength)?;
+          }
+          self.source.advance();
+        }
+        let text = self.source.marked()?;
+        match base64_decode(&text) {
+          Some(bytes) => Token::Blob(bytes),
+          None => return Err(ParseError::Syntax(format!("{{{{{}}}}}", text.trim()))),
+        }
+      },
+    };
+
+    self.skip_lob_whitespace()?;
+    match (self.peek(0)?, self.peek(1)?) {
+      (Some(b'}'), Some(b'}')) => {
+        self.advance_by(2);
+        Ok(token)
+      },
+      (None, _) | (Some(b'}'), None) => Err(ParseError::UnexpectedEnd),
+      _ => Err(self.invalid("")),
+    }
+  }
+
+  /// A single `'''` string inside a clob, and the whitespace after it,
+  /// after `length` bytes of the clob
+  fn long_string_in_lob(&mut self, length: usize) -> Result<String, ParseError>
+  {
+    let mut length = length;
+    self.advance_by(3);
+    self.source.mark();
+    loop {
+      match self.peek(0)? {
+        None => return Err(ParseError::UnexpectedEnd),
+        Some(b'\'') if self.long_string_follows()? => break,
+        Some(b'\\') => length += self.escape()?,
+        Some(byte) if !is_text_byte(byte, true) => return Err(self.invalid("")),
+        Some(_) => {
+          length += 1;
+          self.source.advance();
+        },
+      }
+      check_limit(length, self.max_lob_length, Limit::LobLength)?;
+    }
+    let text = unescape(&self.source.marked()?)?;
+    self.advance_by(3);
+    self.skip_lob_whitespace()?;
+    Ok(text)
+  }
+
+  /// Whitespace, but not comments, which aren't allowed in lobs
+  fn skip_lob_whitespace(&mut self) -> Result<(), ParseError>
+  {
+    while self.peek(0)?.map_or(false, is_whitespace) {
+      self.source.advance();
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum NumberKind {
+  Int,
+  Decimal,
+  Float,
+}
+
+/// The longest number at the start of `text`, and its length; the
+/// length is zero if there isn't one.
+///
+/// Ints are `0`, or digits not starting with zero, or `0x` hex or `0b`
+/// binary digits, optionally after a `-`; digits may be split by single
+/// underscores. A `.` and more digits, or a `d` exponent, makes a decimal;
+/// an `e` exponent makes a float.
+fn scan_number(text: &[u8]) -> (NumberKind, usize)
+{
+  let is_digit = |byte: u8| byte.is_ascii_digit();
+  let is_hex = |byte: u8| byte.is_ascii_hexdigit();
+  let is_bin = |byte: u8| byte == b'0' || byte == b'1';
+
+  let sign = if text.first() == Some(&b'-') { 1 } else { 0 };
+  let unsigned = &text[sign..];
+
+  if unsigned.first() == Some(&b'0') {
+    let radix_digits = match unsigned.get(1) {
+      Some(&b'x') | Some(&b'X') => digit_groups(&unsigned[2..], &is_hex),
+      Some(&b'b') | Some(&b'B') => digit_groups(&unsigned[2..], &is_bin),
+      _ => 0,
+    };
+    if radix_digits != 0 {
+      return (NumberKind::Int, sign + 2 + radix_digits);
+    }
+  }
+
+  let whole = match unsigned.first() {
+    Some(&b'0') => 1,
+    Some(_) => digit_groups(unsigned, &is_digit),
+    None => 0,
+  };
+  if whole == 0 {
+    return (NumberKind::Int, 0);
+  }
+
+  let mut kind = NumberKind::Int;
+  let mut length = sign + whole;
+  if text.get(length) == Some(&b'.') {
+    kind = NumberKind::Decimal;
+    length += 1;
+    length += digit_groups(&text[length..], &is_digit);
+  }
+
+  let exponent_kind = match text.get(length) {
+    Some(&b'd') | Some(&b'D') => NumberKind::Decimal,
+    Some(&b'e') | Some(&b'E') => NumberKind::Float,
+    _ => return (kind, length),
+  };
+  let mut exponent = length + 1;
+  if text.get(exponent) == Some(&b'+') || text.get(exponent) == Some(&b'-') {
+    exponent += 1;
+  }
+  let digits = text[exponent..].iter().take_while(|&&byte| is_digit(byte)).count();
+  if digits == 0 {
+    return (kind, length);
+  }
+  return (exponent_kind, exponent + digits);
+}
+
+/// Length of the digits at the start of `text`, which may be split by
+/// single underscores
+fn digit_groups(text: &[u8], is_digit: &dyn Fn(u8) -> bool) -> usize
+{
+  let mut end = 0;
+  let mut i = 0;
+  loop {
+    let start = i;
+    while i < text.len() && is_digit(text[i]) {
+      i += 1;
+    }
+    if i == start {
+      return end;
+    }
+    end = i;
+    if text.get(i) != Some(&b'_') {
+      return end;
+    }
+    i += 1;
+  }
+}
+
+/// Digits of an int, or of a decimal's coefficient
+fn count_digits(number: &str) -> usize
+{
+  let number = number.trim_start_matches('-');
+  let digits = if number.starts_with("0x") || number.starts_with("0X") || number.starts_with("0b") || number.starts_with("0B") {
+    &number[2..]
+  } else {
+    number.split(|c| c == 'd' || c == 'D').next().unwrap_or("")
+  };
+  return digits.bytes().filter(u8::is_ascii_hexdigit).count();
+}
+
+/// Size of the exponent written in a decimal or float token, or
+/// `usize::max_value()` if it doesn't fit
+fn exponent_magnitude(number: &str) -> usize
+{
+  let exponent = match number.find(|c| c == 'd' || c == 'D' || c == 'e' || c == 'E') {
+    Some(i) => number[i + 1..].trim_start_matches(|c| c == '+' || c == '-'),
+    None => return 0,
+  };
+  usize::from_str(exponent).unwrap_or(usize::max_value())
+}
+
+/// Value of an int token: an optional minus, optional `0x` or `0b` radix
+/// prefix, then digits which may be split by underscores
+pub(crate) fn int_from_token(token: &str) -> Result<Int, ParseError>
+{
+  let error = || ParseError::Syntax(String::from(token));
+
+  let (negative, unsigned) = if token.starts_with('-') { (true, &token[1..]) } else { (false, token) };
+  let (radix, digits) = match unsigned.get(..2) {
+    Some("0x") | Some("0X") => (16, &unsigned[2..]),
+    Some("0b") | Some("0B") => (2, &unsigned[2..]),
+    _ => (10, unsigned),
+  };
+
+  let digits = digits.replace("_", "");
+  if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+    return Err(error());
+  }
+
+  let signed = if negative { format!("-{}", digits) } else { digits };
+  return Int::parse_bytes(signed.as_bytes(), radix).ok_or_else(error);
+}
+
+pub(crate) fn float_from_token(token: &str) -> f64
+{
+  match token {
+    "nan" => f64::NAN,
+    "+inf" => f64::INFINITY,
+    "-inf" => f64::NEG_INFINITY,
+    _ => token.replace("_", "").parse().unwrap(),
+  }
+}
+
+/// Value of a timestamp token such as `2007T`, `2007-02-23` or
+/// `2007-02-23T12:14:33.079-08:00`, checking its fields are in range
+pub(crate) fn timestamp_from_token(token: &str) -> Result<Timestamp, ParseError>
+{
+  let error = || ParseError::Syntax(String::from(token));
+  let bytes = token.as_bytes();
+  let digits = |at: usize, width: usize, min: u16, max: u16| {
+    let field = bytes.get(at..at + width).ok_or_else(error)?;
+    if !field.iter().all(u8::is_ascii_digit) {
+      return Err(error());
+    }
+    let value = field.iter().fold(0, |value, &digit| value * 10 + (digit - b'0') as u16);
+    if value < min || value > max { Err(error()) } else { Ok(value) }
+  };
+  let at = |i: usize| bytes.get(i).cloned();
+
+  let year = digits(0, 4, 1, 9999)?;
+  if at(4) == Some(b'T') && bytes.len() == 5 {
+    return Ok(Timestamp::year(year));
+  }
+  let month = digits(5, 2, 1, 12)? as u8;
+  if at(7) == Some(b'T') && bytes.len() == 8 {
+    return Ok(Timestamp { precision: TimestampPrecision::Month, month: month, ..Timestamp::year(year) });
+  }
+  if at(4) != Some(b'-') || at(7) != Some(b'-') {
+    return Err(error());
+  }
+  let day = digits(8, 2, 1, days_in_month(year as i64, month as i64) as u16)? as u8;
+  if bytes.len() == 10 || at(10) == Some(b'T') && bytes.len() == 11 {
+    return Ok(Timestamp::day(year, month, day));
+  }
+
+  if at(10) != Some(b'T') || at(13) != Some(b':') {
+    return Err(error());
+  }
+  let mut timestamp = Timestamp {
+    precision: TimestampPrecision::Minute,
+    hour: digits(11, 2, 0, 23)? as u8,
+    minute: digits(14, 2, 0, 59)? as u8,
+    ..Timestamp::day(year, month, day)
+  };
+  let mut next = 16;
+  if at(next) == Some(b':') {
+    timestamp.precision = TimestampPrecision::Second;
+    timestamp.second = digits(17, 2, 0, 59)? as u8;
+    next = 19;
+    if at(next) == Some(b'.') {
+      let fraction = bytes[next + 1..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+      if fraction == 0 {
+        return Err(error());
+      }
+      timestamp.fraction = Some(String::from(&token[next + 1..next + 1 + fraction]));
+      next += 1 + fraction;
+    }
+  }
+
+  // the offset, with -00:00 for an unknown one
+  timestamp.offset = match at(next) {
+    Some(b'Z') if bytes.len() == next + 1 => Some(0),
+    Some(sign) if (sign == b'+' || sign == b'-') && at(next + 3) == Some(b':') && bytes.len() == next + 6 => {
+      let minutes = digits(next + 1, 2, 0, 23)? as i16 * 60 + digits(next + 4, 2, 0, 59)? as i16;
+      match sign {
+        b'-' if minutes == 0 => None,
+        b'-' => Some(-minutes),
+        _ => Some(minutes),
+      }
+    },
+    _ => return Err(error()),
+  };
+  return Ok(timestamp);
+}
+
+/// Replace the escapes in string or symbol text
+fn unescape(text: &str) -> Result<String, ParseError>
+{
+  let error = || ParseError::Syntax(format!("invalid escape in '{}'", text));
+
+  let mut result = String::with_capacity(text.len());
+  let mut chars = text.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+
+    let unescaped = match chars.next().ok_or_else(error)? {
+      'a' => '\x07',
+      'b' => '\x08',
+      't' => '\t',
+      'n' => '\n',
+      'f' => '\x0c',
+      'r' => '\r',
+      'v' => '\x0b',
+      '0' => '\0',
+      c @ '?' | c @ '\'' | c @ '"' | c @ '/' | c @ '\\' => c,
+      'x' => hex_escape(&mut chars, 2).ok_or_else(error)?,
+      'u' => hex_escape(&mut chars, 4).ok_or_else(error)?,
+      'U' => hex_escape(&mut chars, 8).ok_or_else(error)?,
+      // an escaped line break continues the line
+      '\n' => continue,
+      '\r' => {
+        if chars.clone().next() == Some('\n') {
+          chars.next();
+        }
+        continue;
+      },
+      _ => return Err(error()),
+    };
+    result.push(unescaped);
+  }
+  return Ok(result);
+}
+
+/// The character of a `\x`, `\u` or `\U` escape; `\u` escapes may be a
+/// surrogate pair
+fn hex_escape(chars: &mut Chars, digits: usize) -> Option<char>
+{
+  let read_hex = |chars: &mut Chars, digits: usize| {
+    let mut value = 0;
+    for _ in 0..digits {
+      value = value * 16 + chars.next()?.to_digit(16)?;
+    }
+    Some(value)
+  };
+
+  let value = read_hex(chars, digits)?;
+  if (0xd800..0xdc00).contains(&value) {
+    if chars.next()? != '\\' || chars.next()? != 'u' {
+      return None;
+    }
+    let low = read_hex(chars, 4)?;
+    if !(0xdc00..0xe000).contains(&low) {
+      return None;
+    }
+    return ::std::char::from_u32(0x10000 + ((value - 0xd800) << 10) + (low - 0xdc00));
+  }
+  ::std::char::from_u32(value)
+}
+
+/// Bytes of clob text, which must be ascii apart from escapes below 0x100
+fn clob_bytes(text: &str) -> Result<Vec<u8>, ParseError>
+{
+  text.chars()
+      .map(|c| if (c as u32) < 0x100 { Ok(c as u8) } else { Err(ParseError::Syntax(format!("{{{{\"{}\"}}}}", text))) })
+      .collect()
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>>
+{
+  let mut bytes = Vec::new();
+  let (mut group, mut count, mut padding) = (0u32, 0, 0);
+
+  for byte in text.bytes().filter(|&byte| !is_whitespace(byte)) {
+    let value = match byte {
+      b'A'..=b'Z' => byte - b'A',
+      b'a'..=b'z' => byte - b'a' + 26,
+      b'0'..=b'9' => byte - b'0' + 52,
+      b'+' => 62,
+      b'/' => 63,
+      b'=' if count >= 2 => {
+        padding += 1;
+        0
+      },
+      _ => return None,
+    };
+    if padding > 0 && byte != b'=' {
+      return None;
+    }
+
+    group = group << 6 | value as u32;
+    count += 1;
+    if count == 4 {
+      let decoded = [(group >> 16) as u8, (group >> 8) as u8, group as u8];
+      bytes.extend_from_slice(&decoded[..3 - padding]);
+      group = 0;
+      count = 0;
+    }
+  }
+
+  if count != 0 {
+    return None;
+  }
+  Some(bytes)
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn tokens<'a>(text: &'a str, in_sexp: bool) -> Result<Vec<Token<'a>>, ParseError>
+  {
+    let mut lexer = Lexer::new(StrSource::new(text));
+    let mut tokens = Vec::new();
+    loop {
+      match lexer.next_token(in_sexp)? {
+        Token::End => return Ok(tokens),
+        token => tokens.push(token),
+      }
+    }
+  }
+
+  fn first_token<'a>(text: &'a str) -> Result<Token<'a>, ParseError>
+  {
+    tokens(text, false).map(|mut tokens| tokens.remove(0))
+  }
+
+  fn identifier<'a>(text: &'a str) -> Token<'a>
+  {
+    Token::Identifier(Cow::Borrowed(text))
+  }
+
+  #[test]
+  fn punctuation_and_words()
+  {
+    assert_eq!(tokens("{a: [b, c::d]}", false), Ok(vec![
+      Token::StructStart, identifier("a"), Token::Colon, Token::ListStart, identifier("b"), Token::Comma,
+      identifier("c"), Token::DoubleColon, identifier("d"), Token::ListEnd, Token::StructEnd,
+    ]));
+    assert_eq!(tokens("true false null null.int trueish $10", false), Ok(vec![
+      Token::Bool(true), Token::Bool(false), Token::Null("null"), Token::Null("int"),
+      identifier("trueish"), identifier("$10"),
+    ]));
+    assert!(tokens("null.integer", false).is_err());
+  }
+
+  #[test]
+  fn whitespace_and_comments()
+  {
+    assert_eq!(tokens(" a // comment\n\tb /* multi\nline */ c\r\n", false), Ok(vec![identifier("a"), identifier("b"), identifier("c")]));
+    assert_eq!(tokens("1// comment", false), Ok(vec![Token::Int(Int::from(1))]));
+    assert_eq!(tokens("a /* unterminated", false), Err(ParseError::UnexpectedEnd));
+  }
+
+  #[test]
+  fn numbers()
+  {
+    assert_eq!(first_token("-0x1_F"), Ok(Token::Int(Int::from(-31))));
+    assert_eq!(first_token("0b101"), Ok(Token::Int(Int::from(5))));
+    assert_eq!(first_token("1_000"), Ok(Token::Int(Int::from(1000))));
+    assert_eq!(first_token("1.50"), Ok(Token::Decimal(Decimal::from_str("1.50").unwrap())));
+    assert_eq!(first_token("1d-2"), Ok(Token::Decimal(Decimal::from_str("0.01").unwrap())));
+    assert_eq!(first_token("-1.5e+2"), Ok(Token::Float(-150.0)));
+    assert_eq!(first_token("+inf"), Ok(Token::Float(f64::INFINITY)));
+    assert_eq!(first_token("-inf"), Ok(Token::Float(f64::NEG_INFINITY)));
+
+    let not_stopped = |number: &str, rest: &str| Err(ParseError::NumberNotStopped(String::from(number), String::from(rest)));
+    assert_eq!(first_token("123abc"), not_stopped("123", "abc"));
+    assert_eq!(first_token("00"), not_stopped("0", "0"));
+    assert_eq!(first_token("1__0"), not_stopped("1", "__0"));
+    assert_eq!(first_token("0x_1"), not_stopped("0", "x_1"));
+    assert_eq!(first_token("1.0e"), not_stopped("1.0", "e"));
+    assert_eq!(first_token("12:30"), not_stopped("12", ":30"));
+    assert_eq!(first_token("1/2"), not_stopped("1", "/2"));
+    assert_eq!(first_token("+1"), Err(ParseError::Syntax(String::from("+1"))));
+  }
+
+  #[test]
+  fn timestamps()
+  {
+    let timestamp = |text: &str| match first_token(text) {
+      Ok(Token::Timestamp(t)) => Ok(t),
+      other => Err(format!("{:?}", other)),
+    };
+
+    assert_eq!(timestamp("2007T"), Ok(Timestamp::year(2007)));
+    assert_eq!(timestamp("2007-02T").map(|t| (t.precision, t.month)), Ok((TimestampPrecision::Month, 2)));
+    assert_eq!(timestamp("2008-02-29"), Ok(Timestamp::day(2008, 2, 29)));
+    assert_eq!(timestamp("2008-02-29T"), Ok(Timestamp::day(2008, 2, 29)));
+    assert_eq!(timestamp("2007-02-23T12:14Z"), Ok(Timestamp {
+      precision: TimestampPrecision::Minute,
+      hour: 12,
+      minute: 14,
+      offset: Some(0),
+      ..Timestamp::day(2007, 2, 23)
+    }));
+    assert_eq!(timestamp("2007-02-23T12:14:33.0790-08:00"), Ok(Timestamp {
+      precision: TimestampPrecision::Second,
+      hour: 12,
+      minute: 14,
+      second: 33,
+      fraction: Some(String::from("0790")),
+      offset: Some(-480),
+      ..Timestamp::day(2007, 2, 23)
+    }));
+    assert_eq!(timestamp("2007-02-23T00:00:00-00:00").map(|t| t.offset), Ok(None));
+    assert_eq!(tokens("[2007T,2007-01-01]", false).map(|tokens| tokens.len()), Ok(5));
+
+    for text in &["0000T", "2007", "2007-02", "2007-13T", "2007-02-29", "2007-02-23T12:14", "2007-02-23T24:00Z",
+                  "2007-02-23T12:14:33.Z", "2007-02-23T12:14+24:00", "2007-02-23T12:14Z1", "2007-02-23a", "2007T-"] {
+      assert!(timestamp(text).is_err(), "{}", text);
+    }
+  }
+
+  #[test]
+  fn scanning_numbers()
+  {
+    let scan = |text: &str| scan_number(text.as_bytes());
+
+    assert_eq!(scan("0"), (NumberKind::Int, 1));
+    assert_eq!(scan("-12_3"), (NumberKind::Int, 5));
+    assert_eq!(scan("12_"), (NumberKind::Int, 2));
+    assert_eq!(scan("0X1f"), (NumberKind::Int, 4));
+    assert_eq!(scan("0b"), (NumberKind::Int, 1));
+    assert_eq!(scan("42."), (NumberKind::Decimal, 3));
+    assert_eq!(scan("1._0"), (NumberKind::Decimal, 2));
+    assert_eq!(scan("1.0_1d+3"), (NumberKind::Decimal, 8));
+    assert_eq!(scan("1e1_0"), (NumberKind::Float, 3));
+    assert_eq!(scan("-"), (NumberKind::Int, 0));
+    assert_eq!(scan("_1"), (NumberKind::Int, 0));
+  }
+
+  #[test]
+  fn int_tokens()
+  {
+    assert_eq!(int_from_token("-0x1f"), Ok(Int::from(-31)));
+    assert_eq!(int_from_token("-0x8000_0000_0000_0000").map(|i| i.as_i64()), Ok(Some(i64::min_value())));
+    assert_eq!(int_from_token("0x8000_0000_0000_0000").map(|i| i.as_i64()), Ok(None));
+    assert_eq!(int_from_token("0x-1f"), Err(ParseError::Syntax(String::from("0x-1f"))));
+    assert_eq!(int_from_token("0o17"), Err(ParseError::Syntax(String::from("0o17"))));
+    assert!(int_from_token("-").is_err());
+  }
+
+  #[test]
+  fn operators_only_in_sexps()
+  {
+    let operator = |text: &'static str| Token::Operator(Cow::Borrowed(text));
+
+    assert_eq!(tokens("a+b >= -1 -x -inf", true), Ok(vec![
+      identifier("a"), operator("+"), identifier("b"), operator(">="), Token::Int(Int::from(-1)),
+      operator("-"), identifier("x"), Token::Float(f64::NEG_INFINITY),
+    ]));
+    assert_eq!(tokens("+//c\n-/*c*/", true), Ok(vec![operator("+"), operator("-")]));
+    assert_eq!(tokens("a+b", false), Err(ParseError::Syntax(String::from("+b"))));
+  }
+
+  #[test]
+  fn strings_and_symbols()
+  {
+    assert_eq!(first_token("\"plain\""), Ok(Token::String(Cow::Borrowed("plain"))));
+    assert_eq!(first_token(r#""a\"b\n\x41\u00e9\U0001F600\ud83d\ude00""#), Ok(Token::String(Cow::Owned(String::from("a\"b\nAé😀😀")))));
+    assert_eq!(first_token("\"a\\\nb\""), Ok(Token::String(Cow::Owned(String::from("ab")))));
+    assert_eq!(first_token("'it\\'s'"), Ok(Token::QuotedSymbol(Cow::Owned(String::from("it's")))));
+    assert_eq!(first_token("''"), Ok(Token::QuotedSymbol(Cow::Borrowed(""))));
+    assert_eq!(first_token("'''a''' /* c */ '''b\\n'''"), Ok(Token::String(Cow::Owned(String::from("ab\n")))));
+    assert_eq!(first_token("'''a\nb'''"), Ok(Token::String(Cow::Borrowed("a\nb"))));
+    assert_eq!(first_token("\"open"), Err(ParseError::UnexpectedEnd));
+    assert!(first_token("\"a\nb\"").is_err());
+    assert!(first_token("\"\\q\"").is_err());
+    assert!(first_token("\"\\ud83d\"").is_err());
+
+    // control characters other than whitespace must be escaped
+    assert_eq!(first_token("\"a\tb\x7f\""), Ok(Token::String(Cow::Borrowed("a\tb\x7f"))));
+    for text in &["\"a\x01\"", "'a\x1f'", "'''a\x00'''", "'''a''' '''\x08'''"] {
+      assert!(first_token(text).is_err(), "{:?}", text);
+    }
+  }
+
+  #[test]
+  fn lobs()
+  {
+    assert_eq!(first_token("{{aGVsbG8=}}"), Ok(Token::Blob(b"hello".to_vec())));
+    assert_eq!(first_token("{{ aGVs bG8h }}"), Ok(Token::Blob(b"hello!".to_vec())));
+    assert_eq!(first_token("{{}}"), Ok(Token::Blob(vec![])));
+    assert_eq!(first_token("{{\"a\\\"\\xff\"}}"), Ok(Token::Clob(b"a\"\xff".to_vec())));
+    assert_eq!(first_token("{{'''a''' '''b'''}}"), Ok(Token::Clob(b"ab".to_vec())));
+    assert!(first_token("{{aGVsbG8}}").is_err());
+    assert!(first_token("{{a=GV}}").is_err());
+    assert!(first_token("{{\"\\u0100\"}}").is_err());
+    // clobs are ascii apart from escapes
+    assert_eq!(first_token("{{\"\\xe9\x7f\"}}"), Ok(Token::Clob(b"\xe9\x7f".to_vec())));
+    for text in &["{{\"\u{e9}\"}}", "{{\"\u{80}\"}}", "{{'''a\u{ff}'''}}", "{{\"\x01\"}}", "{{'''\x00'''}}"] {
+      assert!(first_token(text).is_err(), "{:?}", text);
+    }
+    assert!(first_token("{{\"a\"} }").is_err());
+  }
+
+  #[test]
+  fn reading_through_a_buffer()
+  {
+    let text = format!("[{}] \"{}\"", vec!["12345"; 4000].join(", "), "é".repeat(5000));
+    let mut lexer = Lexer::new(ReadSource::new(text.as_bytes()));
+
+    assert_eq!(lexer.next_token(false), Ok(Token::ListStart));
+    for i in 0..4000 {
+      assert_eq!(lexer.next_token(false), Ok(Token::Int(Int::from(12345))));
+      assert_eq!(lexer.next_token(false), Ok(if i == 3999 { Token::ListEnd } else { Token::Comma }));
+    }
+    assert_eq!(lexer.next_token(false), Ok(Token::String(Cow::Owned("é".repeat(5000)))));
+    assert_eq!(lexer.next_token(false), Ok(Token::End));
+    assert_eq!(lexer.position(), text.len());
+  }
+
+  #[test]
+  fn chunk_boundaries()
+  {
+    let mut source = ChunkSource::new();
+    let mut fed = |data: &[u8]| {
+      source.feed(data);
+      source.ready()
+    };
+
+    // brackets in strings, comments and lobs don't count
+    assert!(!fed(b"[1, \"]\" // ]\n"));
+    assert!(!fed(b"''']]''' /* ] */ {{ ab//+/ }} '"));
+    assert!(!fed(b"]' ( ]"));
+    assert!(fed(b")"));
+
+    // a value which ran out of data isn't read again until another
+    // boundary arrives
+    source.rewind();
+    assert!(!source.ready());
+    source.feed(b"x");
+    assert!(!source.ready());
+    source.feed(b" ");
+    assert!(source.ready());
+  }
+}
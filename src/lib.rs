@@ -1,23 +1,45 @@
 //! Rust implementation of Amazon's Ion data format
 //!
 //! There you go
+//!
+//! Builds on stable Rust 1.35 or later.
 
-#![recursion_limit = "197"]
-#![feature(custom_attribute)]
 #![allow(unreachable_code)]
 
-#[macro_use]
-extern crate pest;
-
+extern crate crossbeam_utils;
 extern crate num_bigint;
 extern crate num_bigdecimal;
 extern crate num_rational;
+#[cfg(feature = "mmap")]
+extern crate memmap;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "async")]
+extern crate futures_core;
 
 use num_bigint::BigInt;
-use num_bigdecimal::BigDecimal;
 
 
+#[macro_use]
+pub mod macros;
 pub mod parser;
+pub mod lexer;
+pub mod reader;
+pub mod span;
+pub mod int;
+pub mod decimal;
+pub mod binary;
+pub mod lazy;
+pub mod parallel;
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod timestamp;
+mod equivalence;
+mod symbols;
+mod writer;
+pub mod ordering;
+pub mod convert;
+pub mod value_ref;
 
 /// Enum of all possible types of elements in an ion document.
 ///
@@ -25,31 +47,63 @@ pub mod parser;
 /// All values are Options, with a None value corresponding to the
 /// equivalent 'null' value of the ion document.
 ///
+/// The derived `PartialEq` compares numbers by value; use
+/// [`ion_eq`](#method.ion_eq) for Ion's stricter notion of equivalence.
+///
 #[derive(Debug, PartialEq, Clone)]
 pub enum AnionValue {
-  /// Pure null type
-  // Null,
+  /// Pure null type (null, null.null)
+  Null,
+
   /// true, false, null.bool
   Boolean(Option<bool>),
 
-  /// Bigint values (unlimited)
-  Integer(Option<BigInt>),
+  /// Integer values (unlimited)
+  Integer(Option<Int>),
 
   /// 64 bit floating point value
   Float(Option<f64>),
 
   /// Exact precision real number value
-  Decimal(Option<BigDecimal>),
+  Decimal(Option<Decimal>),
+
+  /// Point in time with precision and offset
+  Timestamp(Option<Timestamp>),
 
   /// string.
   String(Option<String>),
+
+  /// Symbolic atom (identifier); the text `$0` stands for a symbol with
+  /// unknown text
+  Symbol(Option<String>),
+
+  /// Binary data
+  Blob(Option<Vec<u8>>),
+
+  /// Character data of unspecified encoding
+  Clob(Option<Vec<u8>>),
+
+  /// Ordered collection of values
+  List(Option<Vec<AnionValue>>),
+
+  /// Ordered collection of values with application-defined semantics
+  SExp(Option<Vec<AnionValue>>),
+
+  /// Unordered collection of named values; field names may repeat.
+  Struct(Option<Vec<(String, AnionValue)>>),
+
+  /// A value preceded by one or more annotations (`a::b::value`)
+  Annotated(Vec<String>, Box<AnionValue>),
 }
 
-/// Variant of AnionValue enum that does not permit null values.
+/// Variant of AnionValue enum that does not permit typed null values.
 /// This includes the 'pure NULL' null value, though this may
 /// be removed due to naming sillyness. This is much closer in type
 /// to true JSON values.
 ///
+/// Converts losslessly to and from `AnionValue`; converting a value
+/// containing a typed null (`null.int`, ...) fails.
+///
 #[derive(Debug, PartialEq, Clone)]
 pub enum NonNullAnionValue {
   /// The NULL value - in the NonNullValue!
@@ -58,27 +112,65 @@ pub enum NonNullAnionValue {
   /// true, false
   Boolean(bool),
 
-  /// Bigint values (unlimited)
-  Integer(i32),
+  /// Integer values (unlimited)
+  Integer(Int),
 
   /// 64 bit floating point value
   Float(f64),
 
   /// Exact precision real number value
-  Decimal(BigDecimal),
+  Decimal(Decimal),
+
+  /// Point in time with precision and offset
+  Timestamp(Timestamp),
 
   /// String of utf8 characters
   String(String),
+
+  /// Symbolic atom (identifier)
+  Symbol(String),
+
+  /// Binary data
+  Blob(Vec<u8>),
+
+  /// Character data of unspecified encoding
+  Clob(Vec<u8>),
+
+  /// Ordered collection of values
+  List(Vec<NonNullAnionValue>),
+
+  /// Ordered collection of values with application-defined semantics
+  SExp(Vec<NonNullAnionValue>),
+
+  /// Unordered collection of named values; field names may repeat.
+  Struct(Vec<(String, NonNullAnionValue)>),
+
+  /// A value preceded by one or more annotations (`a::b::value`)
+  Annotated(Vec<String>, Box<NonNullAnionValue>),
 }
 
-pub use parser::Rdp;
+pub use reader::{Diagnostic, Limit, ParseError, ReaderOptions, TextReader};
+pub use span::{Location, Span, Spans};
+pub use binary::{BinaryError, BinaryReader};
+pub use lazy::{LazyReader, LazyValue};
+#[cfg(feature = "mmap")]
+pub use lazy::MappedFile;
+pub use int::Int;
+pub use decimal::Decimal;
+pub use timestamp::{Timestamp, TimestampPrecision};
+pub use ordering::IonKey;
+pub use convert::ConversionError;
+pub use value_ref::AnionValueRef;
+
+/// Symbol text standing for the symbol with unknown text, written `$0`
+pub const UNKNOWN_SYMBOL_TEXT: &'static str = "$0";
 
 macro_rules! impl_int_conversion {
   ($int_type:ident) => {
     impl From<$int_type> for AnionValue {
       #[inline]
       fn from(int: $int_type) -> Self {
-        AnionValue::Integer(Some(BigInt::from(int)))
+        AnionValue::Integer(Some(Int::from(int)))
       }
     }
   }
@@ -93,6 +185,7 @@ impl_int_conversion!(u16);
 impl_int_conversion!(u32);
 impl_int_conversion!(u64);
 impl_int_conversion!(BigInt);
+impl_int_conversion!(Int);
 
 
 macro_rules! impl_float_conversion {
@@ -116,6 +209,27 @@ impl From<bool> for AnionValue {
   }
 }
 
+impl From<Decimal> for AnionValue {
+  fn from(decimal: Decimal) -> Self
+  {
+    AnionValue::Decimal(Some(decimal))
+  }
+}
+
+impl<'a> From<&'a str> for AnionValue {
+  fn from(string: &'a str) -> Self
+  {
+    AnionValue::String(Some(String::from(string)))
+  }
+}
+
+impl From<String> for AnionValue {
+  fn from(string: String) -> Self
+  {
+    AnionValue::String(Some(string))
+  }
+}
+
 // impl From<i32> for AnionValue {
 //   fn from(int: i32) -> AnionValue
 //   {
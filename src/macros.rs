@@ -0,0 +1,293 @@
+//! The `ion!` macro
+//!
+//! Builds an `AnionValue` from ion-like syntax, which is much easier to
+//! read than nested enum constructors when writing fixtures:
+//!
+//! ```ignore
+//! let value = ion!({
+//!   name: "widget",
+//!   sizes: [1, 2.5, null.int],
+//!   rule: (gte size 10),
+//!   tagged: units::meters::7,
+//!   count: #count,
+//!   total: #(count * 2),
+//! });
+//! ```
+//!
+//!  * `null`, `null.int`, `null.struct` etc. are (typed) nulls
+//!  * rust literals convert with `From`: `42` is an int, `2.5` a float,
+//!    `"text"` a string
+//!  * bare identifiers are symbols
+//!  * `[a, b]` is a list, `(a b)` an s-expression, `{ name: v }` a struct
+//!    (field names are identifiers or string literals)
+//!  * inside s-expressions, punctuation such as `+` or `>=` is an operator
+//!    symbol
+//!  * `a::b::value` annotates a value
+//!  * `#name` and `#(expression)` interpolate any rust value which
+//!    converts into an `AnionValue`
+
+/// Construct an `AnionValue` from ion-like syntax.
+///
+/// See the [module documentation](macros/index.html) for the syntax.
+///
+#[macro_export]
+macro_rules! ion {
+
+  //
+  // list elements, separated by commas
+  //
+  (@list [$($done:expr,)*] []) => {
+    vec![$($done),*]
+  };
+  (@list [$($done:expr,)*] [$($value:tt)+]) => {
+    vec![$($done,)* $crate::ion!($($value)+)]
+  };
+  (@list [$($done:expr,)*] [$($value:tt)+] , $($rest:tt)*) => {
+    $crate::ion!(@list [$($done,)* $crate::ion!($($value)+),] [] $($rest)*)
+  };
+  (@list [$($done:expr,)*] [] , $($rest:tt)*) => {
+    compile_error!("ion!: expected a list element before ','")
+  };
+  (@list [$($done:expr,)*] [$($value:tt)*] $next:tt $($rest:tt)*) => {
+    $crate::ion!(@list [$($done,)*] [$($value)* $next] $($rest)*)
+  };
+
+  //
+  // s-expression elements, separated by whitespace
+  //
+  (@sexp [$($done:expr,)*] []) => {
+    vec![$($done),*]
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)+]) => {
+    compile_error!("ion!: expected a value after annotation in s-expression")
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] $a:ident :: $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)*] [$($ann)* $a] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] null . $t:ident $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* null . $t),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] # $var:ident $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* # $var),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] # ($e:expr) $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* # ($e)),] [] $($rest)*)
+  };
+  // `-` before anything but a number is an operator
+  (@sexp [$($done:expr,)*] [] - $value:ident $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator -),] [] $value $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [] - [$($value:tt)*] $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator -),] [] [$($value)*] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [] - ($($value:tt)*) $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator -),] [] ($($value)*) $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [] - {$($value:tt)*} $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator -),] [] {$($value)*} $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [] -) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator -),] [])
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] - $lit:literal $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* - $lit),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] $lit:literal $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* $lit),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] $value:ident $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* $value),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] [$($value:tt)*] $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* [$($value)*]),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] ($($value:tt)*) $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* ($($value)*)),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)*] {$($value:tt)*} $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!($($ann ::)* {$($value)*}),] [] $($rest)*)
+  };
+  // anything else is punctuation: an operator symbol
+  (@sexp [$($done:expr,)*] [] $op:tt $($rest:tt)*) => {
+    $crate::ion!(@sexp [$($done,)* $crate::ion!(@operator $op),] [] $($rest)*)
+  };
+  (@sexp [$($done:expr,)*] [$($ann:ident)+] $op:tt $($rest:tt)*) => {
+    compile_error!(concat!("ion!: operator `", stringify!($op), "` can't be annotated"))
+  };
+  (@operator $op:tt) => {
+    $crate::AnionValue::Symbol(Some(::std::string::String::from(stringify!($op))))
+  };
+
+  //
+  // struct fields, `name: value` separated by commas
+  //
+  (@fields [$($done:expr,)*]) => {
+    vec![$($done),*]
+  };
+  (@fields [$($done:expr,)*] $name:ident : $($rest:tt)*) => {
+    $crate::ion!(@field [$($done,)*] (stringify!($name)) [] $($rest)*)
+  };
+  (@fields [$($done:expr,)*] $name:literal : $($rest:tt)*) => {
+    $crate::ion!(@field [$($done,)*] ($name) [] $($rest)*)
+  };
+  (@fields [$($done:expr,)*] $($rest:tt)+) => {
+    compile_error!(concat!("ion!: expected `name: value` in struct, found `", stringify!($($rest)+), "`"))
+  };
+
+  (@field [$($done:expr,)*] ($name:expr) []) => {
+    compile_error!(concat!("ion!: missing value for struct field ", stringify!($name)))
+  };
+  (@field [$($done:expr,)*] ($name:expr) [] , $($rest:tt)*) => {
+    compile_error!(concat!("ion!: missing value for struct field ", stringify!($name)))
+  };
+  (@field [$($done:expr,)*] ($name:expr) [$($value:tt)+]) => {
+    $crate::ion!(@fields [$($done,)* (::std::string::String::from($name), $crate::ion!($($value)+)),])
+  };
+  (@field [$($done:expr,)*] ($name:expr) [$($value:tt)+] , $($rest:tt)*) => {
+    $crate::ion!(@fields [$($done,)* (::std::string::String::from($name), $crate::ion!($($value)+)),] $($rest)*)
+  };
+  (@field [$($done:expr,)*] ($name:expr) [$($value:tt)*] $next:tt $($rest:tt)*) => {
+    $crate::ion!(@field [$($done,)*] ($name) [$($value)* $next] $($rest)*)
+  };
+
+  //
+  // annotations, `a::b::value`
+  //
+  (@annotated [$($ann:ident)+] $a:ident :: $($rest:tt)+) => {
+    $crate::ion!(@annotated [$($ann)+ $a] $($rest)+)
+  };
+  (@annotated [$($ann:ident)+] $($value:tt)+) => {
+    $crate::AnionValue::Annotated(
+      vec![$(::std::string::String::from(stringify!($ann))),+],
+      Box::new($crate::ion!($($value)+)),
+    )
+  };
+
+  //
+  // values
+  //
+  (null) => { $crate::AnionValue::Null };
+  (null . null) => { $crate::AnionValue::Null };
+  (null . bool) => { $crate::AnionValue::Boolean(None) };
+  (null . int) => { $crate::AnionValue::Integer(None) };
+  (null . float) => { $crate::AnionValue::Float(None) };
+  (null . decimal) => { $crate::AnionValue::Decimal(None) };
+  (null . timestamp) => { $crate::AnionValue::Timestamp(None) };
+  (null . string) => { $crate::AnionValue::String(None) };
+  (null . symbol) => { $crate::AnionValue::Symbol(None) };
+  (null . blob) => { $crate::AnionValue::Blob(None) };
+  (null . clob) => { $crate::AnionValue::Clob(None) };
+  (null . list) => { $crate::AnionValue::List(None) };
+  (null . sexp) => { $crate::AnionValue::SExp(None) };
+  (null . struct) => { $crate::AnionValue::Struct(None) };
+  (null . $other:ident) => {
+    compile_error!(concat!("ion!: unknown null type `null.", stringify!($other), "`"))
+  };
+
+  (true) => { $crate::AnionValue::Boolean(Some(true)) };
+  (false) => { $crate::AnionValue::Boolean(Some(false)) };
+
+  ([ $($elements:tt)* ]) => {
+    $crate::AnionValue::List(Some($crate::ion!(@list [] [] $($elements)*)))
+  };
+  (( $($elements:tt)* )) => {
+    $crate::AnionValue::SExp(Some($crate::ion!(@sexp [] [] $($elements)*)))
+  };
+  ({ $($fields:tt)* }) => {
+    $crate::AnionValue::Struct(Some($crate::ion!(@fields [] $($fields)*)))
+  };
+
+  ($a:ident :: $($rest:tt)+) => {
+    $crate::ion!(@annotated [$a] $($rest)+)
+  };
+
+  (# $var:ident) => { $crate::AnionValue::from($var) };
+  (# ($e:expr)) => { $crate::AnionValue::from($e) };
+
+  ($lit:literal) => { $crate::AnionValue::from($lit) };
+  (- $lit:literal) => { $crate::AnionValue::from(-$lit) };
+
+  ($symbol:ident) => {
+    $crate::AnionValue::Symbol(Some(::std::string::String::from(stringify!($symbol))))
+  };
+
+  () => {
+    compile_error!("ion!: expected a value")
+  };
+  ($($other:tt)+) => {
+    compile_error!(concat!("ion!: not an ion value: `", stringify!($($other)+), "`"))
+  };
+}
+
+
+#[cfg(test)]
+mod test {
+  use {AnionValue, Int};
+
+  fn sym(s: &str) -> AnionValue
+  {
+    AnionValue::Symbol(Some(String::from(s)))
+  }
+
+  #[test]
+  fn scalars()
+  {
+    assert_eq!(ion!(null), AnionValue::Null);
+    assert_eq!(ion!(null.int), AnionValue::Integer(None));
+    assert_eq!(ion!(null.struct), AnionValue::Struct(None));
+    assert_eq!(ion!(true), AnionValue::Boolean(Some(true)));
+    assert_eq!(ion!(42), AnionValue::Integer(Some(Int::from(42))));
+    assert_eq!(ion!(-42), AnionValue::from(-42));
+    assert_eq!(ion!(2.5), AnionValue::Float(Some(2.5)));
+    assert_eq!(ion!("text"), AnionValue::String(Some(String::from("text"))));
+    assert_eq!(ion!(abc), sym("abc"));
+  }
+
+  #[test]
+  fn containers()
+  {
+    assert_eq!(ion!([]), AnionValue::List(Some(vec![])));
+    assert_eq!(ion!([1, -2, null.float,]),
+               AnionValue::List(Some(vec![AnionValue::from(1), AnionValue::from(-2), AnionValue::Float(None)])));
+    assert_eq!(ion!((gte size -10 null.int)),
+               AnionValue::SExp(Some(vec![sym("gte"), sym("size"), AnionValue::from(-10), AnionValue::Integer(None)])));
+    assert_eq!(ion!({ a: 1, "b c": [x], a: {} }),
+               AnionValue::Struct(Some(vec![
+                 (String::from("a"), AnionValue::from(1)),
+                 (String::from("b c"), AnionValue::List(Some(vec![sym("x")]))),
+                 (String::from("a"), AnionValue::Struct(Some(vec![]))),
+               ])));
+  }
+
+  #[test]
+  fn operators_in_sexps()
+  {
+    assert_eq!(ion!((+ a (* b 2))),
+               AnionValue::SExp(Some(vec![sym("+"), sym("a"), AnionValue::SExp(Some(vec![sym("*"), sym("b"), AnionValue::from(2)]))])));
+    assert_eq!(ion!((>= x -10)), AnionValue::SExp(Some(vec![sym(">="), sym("x"), AnionValue::from(-10)])));
+    assert_eq!(ion!((- x 1)), AnionValue::SExp(Some(vec![sym("-"), sym("x"), AnionValue::from(1)])));
+    assert_eq!(ion!((a -)), AnionValue::SExp(Some(vec![sym("a"), sym("-")])));
+    assert_eq!(ion!((- (f) 1)), AnionValue::SExp(Some(vec![sym("-"), AnionValue::SExp(Some(vec![sym("f")])), AnionValue::from(1)])));
+  }
+
+  #[test]
+  fn annotations()
+  {
+    let expected = AnionValue::Annotated(vec![String::from("a"), String::from("b")], Box::new(AnionValue::from(1)));
+    assert_eq!(ion!(a::b::1), expected);
+    assert_eq!(ion!([a::b::1]), AnionValue::List(Some(vec![expected.clone()])));
+    assert_eq!(ion!((a::b::1)), AnionValue::SExp(Some(vec![expected.clone()])));
+    assert_eq!(ion!({ f: a::b::1 }), AnionValue::Struct(Some(vec![(String::from("f"), expected)])));
+  }
+
+  #[test]
+  fn interpolation()
+  {
+    let count = 3u8;
+    let name = String::from("widget");
+    assert_eq!(ion!(#count), AnionValue::from(3));
+    assert_eq!(ion!([#(count * 2), #(name.clone())]),
+               AnionValue::List(Some(vec![AnionValue::from(6), AnionValue::String(Some(name.clone()))])));
+    assert_eq!(ion!((f #count)), AnionValue::SExp(Some(vec![sym("f"), AnionValue::from(3)])));
+  }
+}
@@ -0,0 +1,383 @@
+//! Total ordering and hashing of ion values
+//!
+//! `AnionValue` can't be `Ord` or `Eq` as its `PartialEq` follows IEEE
+//! float semantics. Instead [`ion_cmp`](../enum.AnionValue.html#method.ion_cmp)
+//! gives a canonical total order which agrees with `ion_eq`, and
+//! [`IonKey`](struct.IonKey.html) wraps a value so it may be used as the
+//! key of a `BTreeMap` or `HashSet`.
+//!
+//! Values are ordered first by type, in the order the ion specification
+//! lists them, then by annotations, and then by content:
+//!
+//!  * typed nulls sort before any other value of their type
+//!  * floats: `-inf < -0e0 < 0e0 < +inf < nan`
+//!  * decimals by value, then `-0` before `0`, then fewer digits of precision first
+//!  * timestamps by instant, then precision and offset
+//!  * strings, symbols and lobs by bytes (code point order)
+//!  * lists and sexps lexicographically
+//!  * structs by their fields, sorted by name then value
+
+use super::{AnionValue, Decimal};
+
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+impl AnionValue {
+  /// Compare two values under the canonical total ordering.
+  ///
+  /// Returns `Ordering::Equal` exactly when `self.ion_eq(other)`.
+  ///
+  pub fn ion_cmp(&self, other: &AnionValue) -> Ordering
+  {
+    let (a_annotations, a) = split_annotations(self);
+    let (b_annotations, b) = split_annotations(other);
+
+    return type_rank(a)
+      .cmp(&type_rank(b))
+      .then_with(|| a_annotations.cmp(b_annotations))
+      .then_with(|| content_cmp(a, b));
+  }
+}
+
+/// Wrapper giving an `AnionValue` the `Eq`, `Ord` and `Hash` of the ion
+/// data model, for use as a map key or with `sort`.
+///
+#[derive(Debug, Clone)]
+pub struct IonKey(pub AnionValue);
+
+impl PartialEq for IonKey {
+  fn eq(&self, other: &IonKey) -> bool
+  {
+    self.0.ion_eq(&other.0)
+  }
+}
+
+impl Eq for IonKey {
+}
+
+impl Ord for IonKey {
+  fn cmp(&self, other: &IonKey) -> Ordering
+  {
+    self.0.ion_cmp(&other.0)
+  }
+}
+
+impl PartialOrd for IonKey {
+  fn partial_cmp(&self, other: &IonKey) -> Option<Ordering>
+  {
+    Some(self.cmp(other))
+  }
+}
+
+impl Hash for IonKey {
+  fn hash<H: Hasher>(&self, state: &mut H)
+  {
+    self.0.hash(state)
+  }
+}
+
+/// Hashing is consistent with both `ion_eq` and `PartialEq`: values equal
+/// under either relation hash the same.
+impl Hash for AnionValue {
+  fn hash<H: Hasher>(&self, state: &mut H)
+  {
+    use AnionValue::*;
+
+    let (annotations, mut value) = split_annotations(self);
+    type_rank(value).hash(state);
+    annotations.hash(state);
+    // annotations inside annotations
+    while let Annotated(..) = *value {
+      let (annotations, inner) = split_annotations(value);
+      if !annotations.is_empty() {
+        annotations.hash(state);
+      }
+      value = inner;
+    }
+
+    match *value {
+      Null | Annotated(..) => (),
+      Boolean(ref b) => b.hash(state),
+      Integer(ref i) => i.hash(state),
+      Float(ref f) => f.map(float_hash_bits).hash(state),
+      Decimal(ref d) => d.hash(state),
+      Timestamp(ref t) => t.hash(state),
+      String(ref s) | Symbol(ref s) => s.hash(state),
+      Blob(ref b) | Clob(ref b) => b.hash(state),
+      List(ref l) | SExp(ref l) => l.hash(state),
+      Struct(ref fields) => {
+        // fields are unordered - combine their hashes order-independently
+        fields.as_ref().map(|fields| {
+          let mut hashes: Vec<u64> = fields.iter()
+            .map(|field| {
+              let mut hasher = DefaultHasher::new();
+              field.hash(&mut hasher);
+              hasher.finish()
+            })
+            .collect();
+          hashes.sort();
+          hashes
+        }).hash(state)
+      },
+    }
+  }
+}
+
+/// The annotations and the value they're on, past any layers without
+/// annotations, which `ion_eq` ignores
+fn split_annotations(value: &AnionValue) -> (&[String], &AnionValue)
+{
+  let mut value = value;
+  while let AnionValue::Annotated(ref annotations, ref inner) = *value {
+    if !annotations.is_empty() {
+      return (&annotations[..], inner);
+    }
+    value = inner;
+  }
+  return (&[], value);
+}
+
+/// Position of the value's type in the ion type ordering
+fn type_rank(value: &AnionValue) -> u8
+{
+  use AnionValue::*;
+
+  match *value {
+    Null => 0,
+    Boolean(_) => 1,
+    Integer(_) => 2,
+    Float(_) => 3,
+    Decimal(_) => 4,
+    Timestamp(_) => 5,
+    Symbol(_) => 6,
+    String(_) => 7,
+    Clob(_) => 8,
+    Blob(_) => 9,
+    List(_) => 10,
+    SExp(_) => 11,
+    Struct(_) => 12,
+    Annotated(_, ref inner) => type_rank(inner),
+  }
+}
+
+/// Compare values of the same type
+fn content_cmp(a: &AnionValue, b: &AnionValue) -> Ordering
+{
+  use AnionValue::*;
+
+  match (a, b) {
+    (&Boolean(ref x), &Boolean(ref y)) => x.cmp(y),
+    (&Integer(ref x), &Integer(ref y)) => x.cmp(y),
+    (&Float(ref x), &Float(ref y)) => option_cmp(x, y, float_cmp),
+    (&Decimal(ref x), &Decimal(ref y)) => option_cmp(x, y, decimal_cmp),
+    (&Timestamp(ref x), &Timestamp(ref y)) => x.cmp(y),
+    (&String(ref x), &String(ref y)) | (&Symbol(ref x), &Symbol(ref y)) => x.cmp(y),
+    (&Blob(ref x), &Blob(ref y)) | (&Clob(ref x), &Clob(ref y)) => x.cmp(y),
+    (&List(ref x), &List(ref y)) | (&SExp(ref x), &SExp(ref y)) => option_cmp(x, y, |x, y| sequence_cmp(x, y)),
+    (&Struct(ref x), &Struct(ref y)) => option_cmp(x, y, fields_cmp),
+    // annotations inside annotations
+    (&Annotated(..), _) | (_, &Annotated(..)) => a.ion_cmp(b),
+    _ => Ordering::Equal,
+  }
+}
+
+/// Nulls sort first
+fn option_cmp<T, F>(a: &Option<T>, b: &Option<T>, cmp: F) -> Ordering
+  where F: Fn(&T, &T) -> Ordering
+{
+  match (a, b) {
+    (&None, &None) => Ordering::Equal,
+    (&None, &Some(_)) => Ordering::Less,
+    (&Some(_), &None) => Ordering::Greater,
+    (&Some(ref x), &Some(ref y)) => cmp(x, y),
+  }
+}
+
+fn float_cmp(a: &f64, b: &f64) -> Ordering
+{
+  match (a.is_nan(), b.is_nan()) {
+    (true, true) => Ordering::Equal,
+    (true, false) => Ordering::Greater,
+    (false, true) => Ordering::Less,
+    // only ±0 compare equal by value; the negative one is smaller
+    (false, false) => a.partial_cmp(b).unwrap().then(b.is_sign_negative().cmp(&a.is_sign_negative())),
+  }
+}
+
+/// All nans hash alike, and so do both zeros
+fn float_hash_bits(f: f64) -> u64
+{
+  if f.is_nan() {
+    return ::std::f64::NAN.to_bits();
+  }
+  if f == 0.0 {
+    return 0;
+  }
+  return f.to_bits();
+}
+
+/// By value, negative zero before zero, then by precision
+fn decimal_cmp(a: &Decimal, b: &Decimal) -> Ordering
+{
+  return a.value_cmp(b)
+    .then(b.is_sign_negative().cmp(&a.is_sign_negative()))
+    .then(b.exponent().cmp(&a.exponent()));
+}
+
+fn sequence_cmp(a: &[AnionValue], b: &[AnionValue]) -> Ordering
+{
+  for (x, y) in a.iter().zip(b.iter()) {
+    match x.ion_cmp(y) {
+      Ordering::Equal => continue,
+      order => return order,
+    }
+  }
+  return a.len().cmp(&b.len());
+}
+
+fn sorted_fields(fields: &Vec<(String, AnionValue)>) -> Vec<&(String, AnionValue)>
+{
+  let mut fields: Vec<&(String, AnionValue)> = fields.iter().collect();
+  fields.sort_by(|x, y| x.0.cmp(&y.0).then_with(|| x.1.ion_cmp(&y.1)));
+  return fields;
+}
+
+fn fields_cmp(a: &Vec<(String, AnionValue)>, b: &Vec<(String, AnionValue)>) -> Ordering
+{
+  let (a, b) = (sorted_fields(a), sorted_fields(b));
+
+  for (x, y) in a.iter().zip(b.iter()) {
+    match x.0.cmp(&y.0).then_with(|| x.1.ion_cmp(&y.1)) {
+      Ordering::Equal => continue,
+      order => return order,
+    }
+  }
+  return a.len().cmp(&b.len());
+}
+
+
+#[cfg(test)]
+mod test {
+  use AnionValue;
+  use super::IonKey;
+  use Decimal;
+  use std::cmp::Ordering;
+  use std::collections::{BTreeSet, HashSet};
+  use std::collections::hash_map::DefaultHasher;
+  use std::hash::{Hash, Hasher};
+  use std::str::FromStr;
+  use std::f64;
+
+  fn dec(s: &str) -> AnionValue
+  {
+    AnionValue::Decimal(Some(Decimal::from_str(s).unwrap()))
+  }
+
+  #[test]
+  fn types_are_ordered()
+  {
+    let mut values = vec![
+      AnionValue::String(Some(String::from("a"))),
+      dec("1.0"),
+      AnionValue::from(2.0),
+      AnionValue::Null,
+      AnionValue::from(3),
+      AnionValue::from(true),
+    ];
+    values.sort_by(AnionValue::ion_cmp);
+    assert_eq!(values, vec![
+      AnionValue::Null,
+      AnionValue::from(true),
+      AnionValue::from(3),
+      AnionValue::from(2.0),
+      dec("1.0"),
+      AnionValue::String(Some(String::from("a"))),
+    ]);
+  }
+
+  #[test]
+  fn floats_are_totally_ordered()
+  {
+    let mut values: Vec<f64> = vec![f64::NAN, 1.0, -0.0, f64::INFINITY, 0.0, f64::NEG_INFINITY];
+    values.sort_by(|a, b| AnionValue::from(*a).ion_cmp(&AnionValue::from(*b)));
+    assert_eq!(format!("{:?}", values), "[-inf, -0.0, 0.0, 1.0, inf, NaN]");
+    assert_eq!(AnionValue::from(f64::NAN).ion_cmp(&AnionValue::from(f64::NAN)), Ordering::Equal);
+    assert_eq!(AnionValue::Float(None).ion_cmp(&AnionValue::from(f64::NEG_INFINITY)), Ordering::Less);
+  }
+
+  #[test]
+  fn decimals_order_by_value_then_precision()
+  {
+    assert_eq!(dec("1.0").ion_cmp(&dec("1.00")), Ordering::Less);
+    assert_eq!(dec("1.0").ion_cmp(&dec("10e-1")), Ordering::Equal);
+    assert_eq!(dec("-2").ion_cmp(&dec("1.5")), Ordering::Less);
+    assert_eq!(dec("0.012").ion_cmp(&dec("0.0115")), Ordering::Greater);
+    assert_eq!(dec("-0.0").ion_cmp(&dec("0d0")), Ordering::Less);
+  }
+
+  fn hash(value: &AnionValue) -> u64
+  {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+  }
+
+  #[test]
+  fn ordering_agrees_with_equivalence()
+  {
+    let field = |name: &str, value| (String::from(name), value);
+    let a = AnionValue::Struct(Some(vec![field("x", AnionValue::from(1)), field("y", dec("2.0"))]));
+    let b = AnionValue::Struct(Some(vec![field("y", dec("2.0")), field("x", AnionValue::from(1))]));
+    let c = AnionValue::Struct(Some(vec![field("y", dec("2.00")), field("x", AnionValue::from(1))]));
+
+    assert_eq!(a.ion_cmp(&b), Ordering::Equal);
+    assert!(a.ion_cmp(&c) != Ordering::Equal);
+    assert_eq!(a.ion_cmp(&c), c.ion_cmp(&a).reverse());
+
+    // layers without annotations are ignored, at any depth
+    let annotated = |annotations: &[&str], value| {
+      AnionValue::Annotated(annotations.iter().map(|&a| String::from(a)).collect(), Box::new(value))
+    };
+    let one = AnionValue::from(1);
+    let a = annotated(&["a"], one.clone());
+    let values = [
+      annotated(&[], annotated(&[], a.clone())),
+      annotated(&["a"], annotated(&[], one.clone())),
+      annotated(&["a"], annotated(&["b"], one.clone())),
+      annotated(&["a"], annotated(&["c"], one.clone())),
+      annotated(&["a", "b"], one.clone()),
+      annotated(&[], one.clone()),
+    ];
+    for x in values.iter().chain(Some(&a)).chain(Some(&one)) {
+      for y in values.iter().chain(Some(&a)).chain(Some(&one)) {
+        assert_eq!(x.ion_cmp(y) == Ordering::Equal, x.ion_eq(y), "{:?} {:?}", x, y);
+        assert_eq!(x.ion_cmp(y), y.ion_cmp(x).reverse(), "{:?} {:?}", x, y);
+        if x.ion_eq(y) {
+          assert_eq!(hash(x), hash(y), "{:?} {:?}", x, y);
+        }
+      }
+    }
+    assert_eq!(values[0].ion_cmp(&a), Ordering::Equal);
+    assert_eq!(values[1].ion_cmp(&a), Ordering::Equal);
+    assert!(values[2].ion_cmp(&values[3]) != Ordering::Equal);
+  }
+
+  #[test]
+  fn keys_in_collections()
+  {
+    let mut hashed = HashSet::new();
+    hashed.insert(IonKey(AnionValue::from(f64::NAN)));
+    hashed.insert(IonKey(AnionValue::from(f64::NAN)));
+    hashed.insert(IonKey(AnionValue::from(0.0)));
+    hashed.insert(IonKey(AnionValue::from(-0.0)));
+    hashed.insert(IonKey(dec("1.0")));
+    hashed.insert(IonKey(dec("1.00")));
+    hashed.insert(IonKey(dec("10e-1")));
+    assert_eq!(hashed.len(), 5);
+
+    let sorted: BTreeSet<IonKey> = hashed.into_iter().collect();
+    assert_eq!(sorted.len(), 5);
+    assert!(sorted.iter().next().unwrap().0.ion_eq(&AnionValue::from(-0.0)));
+  }
+}
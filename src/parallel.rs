@@ -0,0 +1,315 @@
+//! Reading large ion text on several threads
+//!
+//! Log files and the like hold a top level value per line. The text is
+//! split at line breaks between top level values into a chunk per
+//! thread, the chunks are read at once, and the values are returned in
+//! their original order.
+//!
+//! What a symbol ID means depends on the version markers and symbol tables
+//! before it. Chunks which might hold either - any mentioning `$ion`, a
+//! symbol ID whose text starts with it, or a quoted symbol with escapes -
+//! are read first, in order, to find the table each later chunk starts
+//! with; the rest are read in parallel.
+
+use super::{AnionValue, AnionValueRef};
+use lexer::StrSource;
+use reader::{ParseError, ReaderOptions, TextReader};
+use symbols::SymbolTable;
+
+use crossbeam_utils::thread;
+use std::panic;
+
+/// Parse the top level user values of ion text on up to `threads`
+/// threads; the result is the same as `parse_values`
+pub fn parse_values_parallel(text: &str, threads: usize) -> Result<Vec<AnionValue>, ParseError>
+{
+  parse_values_parallel_with(text, threads, &ReaderOptions::default())
+}
+
+/// Parse the top level values of ion text on up to `threads` threads; the
+/// result is the same as `parse_values_with`
+pub fn parse_values_parallel_with(text: &str, threads: usize, options: &ReaderOptions) -> Result<Vec<AnionValue>, ParseError>
+{
+  let starts = chunk_starts(text.as_bytes(), threads.max(1));
+  let ends = starts.iter().skip(1).cloned().chain(Some(text.len()));
+  let ranges: Vec<(usize, usize)> = starts.iter().cloned().zip(ends).collect();
+
+  // chunks which may change the symbol table are read first; after an
+  // error the tables of later chunks are unknown, and they're not read
+  let mut results = Vec::new();
+  let mut tables = Vec::new();
+  let mut symbols = SymbolTable::system();
+  for &(start, end) in &ranges {
+    let chunk = &text[start..end];
+    tables.push(symbols.clone());
+    if !may_change_symbols(chunk.as_bytes(), &symbols) {
+      results.push(None);
+      continue;
+    }
+    let mut reader = TextReader::new(StrSource::new(chunk), options.clone()).with_symbols(symbols);
+    let values: Result<Vec<AnionValue>, ParseError> = reader.by_ref().map(|value| value.map(AnionValueRef::into_owned)).collect();
+    symbols = reader.symbols().clone();
+    let failed = values.is_err();
+    results.push(Some(values));
+    if failed {
+      break;
+    }
+  }
+
+  // the other chunks are read on threads borrowing the text
+  let read = thread::scope(|scope| {
+    let mut threads = Vec::new();
+    for ((result, &(start, end)), symbols) in results.iter().zip(&ranges).zip(tables) {
+      threads.push(match *result {
+        Some(_) => None,
+        None => {
+          let chunk = &text[start..end];
+          Some(scope.spawn(move |_| read_chunk(chunk, symbols, options)))
+        },
+      });
+    }
+
+    let mut values = Vec::new();
+    for (result, thread) in results.into_iter().zip(threads) {
+      let chunk_values = match (result, thread) {
+        (Some(result), _) => result,
+        (None, Some(thread)) => thread.join().unwrap_or_else(|payload| panic::resume_unwind(payload)),
+        (None, None) => unreachable!(),
+      };
+      values.extend(chunk_values?);
+    }
+    return Ok(values);
+  });
+  return read.unwrap_or_else(|payload| panic::resume_unwind(payload));
+}
+
+fn read_chunk(text: &str, symbols: SymbolTable, options: &ReaderOptions) -> Result<Vec<AnionValue>, ParseError>
+{
+  TextReader::new(StrSource::new(text), options.clone())
+    .with_symbols(symbols)
+    .map(|value| value.map(AnionValueRef::into_owned))
+    .collect()
+}
+
+/// Where each of at most `count` chunks of about equal size starts:
+/// after line breaks between top level values
+fn chunk_starts(text: &[u8], count: usize) -> Vec<usize>
+{
+  let size = text.len() / count + 1;
+  let mut starts = vec![0];
+  let mut depth = 0i64;
+  let mut i = 0;
+  while i < text.len() {
+    let rest = &text[i..];
+    i = match text[i] {
+      b'\'' if rest.starts_with(b"'''") => skip_past(text, i + 3, b"'''", true),
+      b'\'' => skip_past(text, i + 1, b"'", true),
+      b'"' => skip_past(text, i + 1, b"\"", true),
+      // the line break ending a comment is left to be a boundary
+      b'/' if rest.starts_with(b"//") => skip_past(text, i + 2, b"\n", false) - 1,
+      b'/' if rest.starts_with(b"/*") => skip_past(text, i + 2, b"*/", false),
+      b'[' | b'(' | b'{' => {
+        depth += 1;
+        i + 1
+      },
+      b']' | b')' | b'}' => {
+        depth -= 1;
+        i + 1
+      },
+      b'\n' => {
+        if depth == 0 && i + 1 >= starts[starts.len() - 1] + size && can_split(text, i) {
+          starts.push(i + 1);
+        }
+        i + 1
+      },
+      _ => i + 1,
+    };
+  }
+  return starts;
+}
+
+/// Whether a chunk might hold a version marker or symbol table, read while
+/// `symbols` is current: if it mentions `$ion`, has a symbol ID whose text
+/// starts with `$ion`, like `$3`, or a quoted symbol with escapes, which
+/// could spell either
+fn may_change_symbols(text: &[u8], symbols: &SymbolTable) -> bool
+{
+  if text.windows(4).any(|window| window == b"$ion") {
+    return true;
+  }
+  let is_identifier = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$';
+  let mut i = 0;
+  while i < text.len() {
+    let rest = &text[i..];
+    i = match text[i] {
+      b'\'' if rest.starts_with(b"'''") => skip_past(text, i + 3, b"'''", true),
+      b'\'' => {
+        let end = skip_past(text, i + 1, b"'", true);
+        if text[i + 1..end].contains(&b'\\') {
+          return true;
+        }
+        end
+      },
+      b'"' => skip_past(text, i + 1, b"\"", true),
+      b'/' if rest.starts_with(b"//") => skip_past(text, i + 2, b"\n", false),
+      b'/' if rest.starts_with(b"/*") => skip_past(text, i + 2, b"*/", false),
+      b'$' if i == 0 || !is_identifier(text[i - 1]) => {
+        let end = i + 1 + rest[1..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+        let sid = match ::std::str::from_utf8(&text[i + 1..end]).ok().and_then(|digits| digits.parse().ok()) {
+          Some(sid) if end == text.len() || !is_identifier(text[end]) => Some(sid),
+          _ => None,
+        };
+        if let Some(Ok(Some(symbol))) = sid.map(|sid| symbols.text(sid)) {
+          if symbol.starts_with("$ion") {
+            return true;
+          }
+        }
+        end
+      },
+      _ => i + 1,
+    };
+  }
+  return false;
+}
+
+/// Index after the next `end` from `from`, or the end of the text;
+/// with `escapes`, a backslash escapes the byte after it
+fn skip_past(text: &[u8], from: usize, end: &[u8], escapes: bool) -> usize
+{
+  let mut i = from;
+  while i < text.len() {
+    if escapes && text[i] == b'\\' {
+      i += 2;
+    } else if text[i..].starts_with(end) {
+      return i + end.len();
+    } else {
+      i += 1;
+    }
+  }
+  return text.len();
+}
+
+/// Whether the top level line break at `newline` is between values: not
+/// between an annotation and its value, or long strings which join, and
+/// not the last thing in the text
+fn can_split(text: &[u8], newline: usize) -> bool
+{
+  let is_space = |byte: &u8| b" \t\r\n\x0b\x0c".contains(byte);
+  let before = text[..newline].iter().rev().find(|byte| !is_space(byte));
+  let after = match text[newline..].iter().position(|byte| !is_space(byte)) {
+    Some(offset) => &text[newline + offset..],
+    None => return false,
+  };
+  return before != Some(&b':') && !after.starts_with(b":") && !after.starts_with(b"/") && !after.starts_with(b"'''");
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use reader::parse_values;
+
+  #[test]
+  fn splitting_between_top_level_values()
+  {
+    let text = b"1\n2\n3\n4\n";
+    assert_eq!(chunk_starts(text, 1), vec![0]);
+    assert_eq!(chunk_starts(text, 2), vec![0, 6]);
+    assert_eq!(chunk_starts(text, 4), vec![0, 4]);
+
+    // only line breaks outside of values, not before comments, which might
+    // hide an annotation, and not the last one
+    let text = b"[1,\n2]\n\"a\n\\\"\nb\"\n'''c\n'''\n{{\n}}\n/* \n */ d\n// e\nf\n";
+    let starts = chunk_starts(text, text.len());
+    let breaks: Vec<&str> = starts[1..].iter().map(|&i| ::std::str::from_utf8(&text[i - 2..i]).unwrap()).collect();
+    assert_eq!(breaks, vec!["]\n", "'\n", "e\n"]);
+
+    // annotations and joined long strings stay together
+    let text = b"a::\nb\nc\n::d\ne\n'''f'''\n'''g'''\n";
+    assert_eq!(chunk_starts(text, text.len()), vec![0, 6, 12]);
+  }
+
+  #[test]
+  fn same_values_as_reading_in_order()
+  {
+    let mut text = String::new();
+    for i in 0..50 {
+      text.push_str(&format!("{{id: {}, tags: [a, 'b c'], note: \"line\\nbreak\"}}\n", i));
+      if i == 20 {
+        text.push_str("$ion_symbol_table::{symbols: [\"first\", \"second\"]}\n");
+      }
+      if i > 20 {
+        text.push_str("$10::$11\n");
+      }
+      if i == 35 {
+        text.push_str("$ion_1_0\n$ion_symbol_table::{symbols: [\"third\", \"fourth\"]}\n");
+      }
+      text.push_str("'''one''' // joined\n'''two'''\n");
+    }
+
+    let expected = parse_values(&text).unwrap();
+    for threads in 1..9 {
+      assert_eq!(parse_values_parallel(&text, threads), Ok(expected.clone()), "{} threads", threads);
+    }
+
+    let options = ReaderOptions { system_values: true, ..ReaderOptions::default() };
+    let with_system_values = parse_values_parallel_with(&text, 4, &options).unwrap();
+    assert_eq!(with_system_values.len(), expected.len() + 3);
+  }
+
+  #[test]
+  fn chunks_which_may_change_symbols()
+  {
+    let system = SymbolTable::system();
+    assert!(may_change_symbols(b"a\n$ion_1_0\n", &system));
+    assert!(may_change_symbols(b"$3::{symbols: [\"a\"]}", &system));
+    assert!(may_change_symbols(b"$2\n", &system));
+    assert!(may_change_symbols(b"'\\x24ion_symbol_table'::{}", &system));
+    assert!(!may_change_symbols(b"$4::$5 a$3 $3a \"$3\" '$3' /* $3 */ $99999999999999999999\n", &system));
+
+    let local = system.local(&[(String::from("symbols"), AnionValue::List(Some(vec![AnionValue::String(Some(String::from("$ion_symbol_table")))])))], 100).unwrap();
+    assert!(!may_change_symbols(b"$10::{}", &system));
+    assert!(may_change_symbols(b"$10::{}", &local));
+  }
+
+  #[test]
+  fn symbol_tables_by_symbol_id()
+  {
+    let mut text = String::new();
+    for i in 0..40 {
+      text.push_str(&format!("{}\n", i));
+      if i == 10 {
+        text.push_str("$3::{symbols: [\"first\"]}\n");
+      }
+      if i == 20 {
+        text.push_str("'\\x24ion_symbol_table'::{symbols: [\"second\"]}\n");
+      }
+      if i > 10 {
+        text.push_str("$10\n");
+      }
+    }
+
+    let expected = parse_values(&text).unwrap();
+    assert!(expected.contains(&AnionValue::Symbol(Some(String::from("first")))));
+    assert!(expected.contains(&AnionValue::Symbol(Some(String::from("second")))));
+    for threads in 1..9 {
+      assert_eq!(parse_values_parallel(&text, threads), Ok(expected.clone()), "{} threads", threads);
+    }
+  }
+
+  #[test]
+  fn first_error_in_order()
+  {
+    let mut text = String::new();
+    for i in 0..40 {
+      text.push_str(&format!("{}\n", i));
+      if i == 10 {
+        text.push_str("1a\n");
+      }
+      if i == 30 {
+        text.push_str("$99\n");
+      }
+    }
+    assert_eq!(parse_values_parallel(&text, 4), parse_values(&text));
+    assert!(parse_values_parallel(&text, 4).is_err());
+  }
+}
@@ -0,0 +1,1490 @@
+//! Reading ion text
+//!
+//! `TextReader` reads top level values one at a time from a `Lexer`,
+//! tracking version markers and local symbol tables as it goes. Read a
+//! string with `TextReader::from_text`, and values borrow from it; read
+//! any `io::Read` with `TextReader::from_reader`, which holds only the
+//! value being read in memory. `TextReader::incremental` is fed data as it
+//! arrives, say from a socket. `next_value_with_spans` also says where
+//! in the text each part of a value was, and a `tolerant` reader carries
+//! on after errors, collecting them as diagnostics.
+//!
+//! ```
+//! use anion::{AnionValue, TextReader};
+//!
+//! let input: &[u8] = b"1 two [3]";
+//! let values: Result<Vec<AnionValue>, _> = TextReader::from_reader(input).map(|value| value.map(AnionValue::from)).collect();
+//! assert_eq!(values.unwrap().len(), 3);
+//! ```
+
+use super::{AnionValue, AnionValueRef};
+use lexer::{ChunkSource, Lexer, ReadSource, Source, StrSource, Token};
+use span::{Location, Span, Spans};
+use symbols::{SymbolTable, symbol_table_fields};
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+use std::str::FromStr;
+
+/// Error reading ion text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseError {
+  /// The text isn't a valid ion value
+  Syntax(String),
+
+  /// A symbol ID with no symbol defined for it, such as `$10` without a
+  /// symbol table
+  UnknownSymbolId(usize),
+
+  /// A version marker other than `$ion_1_0`
+  UnsupportedVersion(String),
+
+  /// A local symbol table which can't be used: (the reason)
+  InvalidSymbolTable(String),
+
+  /// A number is directly followed by something other than a stop
+  /// character, as in `123abc`: (the number, the text following it)
+  NumberNotStopped(String, String),
+
+  /// The input ends inside a value
+  UnexpectedEnd,
+
+  /// More data is needed to finish the next value; not a failure while
+  /// data is still arriving (see `TextReader::incremental`)
+  Incomplete,
+
+  /// Reading the input failed: (the error's kind, its message)
+  Io(io::ErrorKind, String),
+
+  /// The input goes beyond one of the limits in `ReaderOptions`
+  LimitExceeded(Limit),
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    match *self {
+      ParseError::Syntax(ref text) => write!(f, "invalid ion value '{}'", text),
+      ParseError::UnknownSymbolId(sid) => write!(f, "no symbol defined for symbol ID ${}", sid),
+      ParseError::UnsupportedVersion(ref marker) => write!(f, "unsupported ion version marker '{}'", marker),
+      ParseError::InvalidSymbolTable(ref reason) => write!(f, "invalid symbol table: {}", reason),
+      ParseError::NumberNotStopped(ref number, ref rest) => {
+        write!(f, "number '{}' must be followed by whitespace, a comment or a delimiter, not '{}'", number, rest)
+      },
+      ParseError::UnexpectedEnd => write!(f, "unexpected end of input"),
+      ParseError::Incomplete => write!(f, "more data is needed to finish the value"),
+      ParseError::Io(_, ref message) => write!(f, "error reading input: {}", message),
+      ParseError::LimitExceeded(limit) => write!(f, "input exceeds the limit on {}", limit),
+    }
+  }
+}
+
+impl Error for ParseError {
+  fn description(&self) -> &str
+  {
+    match *self {
+      ParseError::Syntax(_) => "invalid ion value",
+      ParseError::UnknownSymbolId(_) => "undefined symbol ID",
+      ParseError::UnsupportedVersion(_) => "unsupported ion version",
+      ParseError::InvalidSymbolTable(_) => "invalid symbol table",
+      ParseError::NumberNotStopped(..) => "number not followed by a stop character",
+      ParseError::UnexpectedEnd => "unexpected end of input",
+      ParseError::Incomplete => "incomplete input",
+      ParseError::Io(..) => "error reading input",
+      ParseError::LimitExceeded(_) => "input limit exceeded",
+    }
+  }
+}
+
+/// An error found by a tolerant reader, and where it was found
+#[derive(Debug, PartialEq, Clone)]
+pub struct Diagnostic {
+  pub error: ParseError,
+
+  /// The token the error was found at
+  pub span: Span,
+}
+
+/// `line:column: error`
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "{}: {}", self.span.start, self.error)
+  }
+}
+
+/// The limits on input in `ReaderOptions`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Limit {
+  /// `max_depth`
+  Depth,
+
+  /// `max_container_length`
+  ContainerLength,
+
+  /// `max_string_length`
+  StringLength,
+
+  /// `max_lob_length`
+  LobLength,
+
+  /// `max_int_digits`
+  IntDigits,
+
+  /// `max_exponent`
+  Exponent,
+
+  /// `max_fraction_digits`
+  FractionDigits,
+
+  /// `max_symbols`
+  Symbols,
+}
+
+impl fmt::Display for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    let limit = match *self {
+      Limit::Depth => "nesting depth",
+      Limit::ContainerLength => "container length",
+      Limit::StringLength => "string length",
+      Limit::LobLength => "lob length",
+      Limit::IntDigits => "digits in a number",
+      Limit::Exponent => "size of an exponent",
+      Limit::FractionDigits => "digits in a fraction of a second",
+      Limit::Symbols => "symbol table size",
+    };
+    write!(f, "{}", limit)
+  }
+}
+
+/// Options for reading ion text and binary ion.
+///
+/// The limits guard against input made to use up memory or time, and
+/// input beyond them fails with `LimitExceeded`. By default only the
+/// nesting depth is limited, to `DEFAULT_MAX_DEPTH`, as containers are read
+/// recursively and deeper input could overflow the stack. Set the others
+/// needed with struct update syntax:
+///
+/// ```
+/// use anion::ReaderOptions;
+///
+/// let options = ReaderOptions { max_depth: 32, max_string_length: 1 << 20, ..ReaderOptions::default() };
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReaderOptions {
+  /// Also return system values - version markers and local symbol tables
+  /// - which are otherwise processed and dropped
+  pub system_values: bool,
+
+  /// Most containers a value may be nested in, counting itself; a top
+  /// level list is at depth 1
+  pub max_depth: usize,
+
+  /// Most values in a list or sexp, or fields in a struct
+  pub max_container_length: usize,
+
+  /// Most bytes of UTF-8 in a string, symbol or field name
+  pub max_string_length: usize,
+
+  /// Most bytes in a blob or clob
+  pub max_lob_length: usize,
+
+  /// Most digits in an int or a decimal's coefficient; checked before the
+  /// number is converted
+  pub max_int_digits: usize,
+
+  /// Largest exponent, positive or negative, of a decimal or written in a
+  /// float
+  pub max_exponent: usize,
+
+  /// Most digits in a timestamp's fraction of a second
+  pub max_fraction_digits: usize,
+
+  /// Most symbols a symbol table may define, the system symbols included
+  pub max_symbols: usize,
+}
+
+/// Default `max_depth`
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+impl Default for ReaderOptions {
+  fn default() -> ReaderOptions
+  {
+    ReaderOptions {
+      system_values: false,
+      max_depth: DEFAULT_MAX_DEPTH,
+      max_container_length: usize::max_value(),
+      max_string_length: usize::max_value(),
+      max_lob_length: usize::max_value(),
+      max_int_digits: usize::max_value(),
+      max_exponent: usize::max_value(),
+      max_fraction_digits: usize::max_value(),
+      max_symbols: usize::max_value(),
+    }
+  }
+}
+
+/// Fail with `limit` if `value` is over `max`
+pub(crate) fn check_limit(value: usize, max: usize, limit: Limit) -> Result<(), ParseError>
+{
+  if value > max { Err(ParseError::LimitExceeded(limit)) } else { Ok(()) }
+}
+
+/// What an identifier token stands for
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum SymbolToken<'a> {
+  /// A symbol with this text
+  Text(&'a str),
+
+  /// `$` followed by digits: a symbol ID, `$0` having unknown text
+  SymbolId(usize),
+
+  /// `$ion_<major>_<minor>`, which at top level marks the ion version
+  VersionMarker(&'a str),
+}
+
+fn classify_identifier<'a>(token: &'a str) -> SymbolToken<'a>
+{
+  let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+  if token.starts_with('$') && all_digits(&token[1..]) {
+    return match usize::from_str(&token[1..]) {
+      Ok(sid) => SymbolToken::SymbolId(sid),
+      // too large to be defined
+      Err(_) => SymbolToken::SymbolId(usize::max_value()),
+    };
+  }
+
+  if token.starts_with("$ion_") {
+    let mut version = token["$ion_".len()..].splitn(2, '_');
+    if version.next().map_or(false, all_digits) && version.next().map_or(false, all_digits) {
+      return SymbolToken::VersionMarker(token);
+    }
+  }
+  return SymbolToken::Text(token);
+}
+
+/// `null`, or a typed null, by type name
+fn null_value<'a>(type_name: &str) -> AnionValueRef<'a>
+{
+  match type_name {
+    "bool" => AnionValueRef::Boolean(None),
+    "int" => AnionValueRef::Integer(None),
+    "float" => AnionValueRef::Float(None),
+    "decimal" => AnionValueRef::Decimal(None),
+    "timestamp" => AnionValueRef::Timestamp(None),
+    "string" => AnionValueRef::String(None),
+    "symbol" => AnionValueRef::Symbol(None),
+    "blob" => AnionValueRef::Blob(None),
+    "clob" => AnionValueRef::Clob(None),
+    "struct" => AnionValueRef::Struct(None),
+    "list" => AnionValueRef::List(None),
+    "sexp" => AnionValueRef::SExp(None),
+    _ => AnionValueRef::Null,
+  }
+}
+
+fn unexpected(token: Token) -> ParseError
+{
+  match token {
+    Token::End => ParseError::UnexpectedEnd,
+    token => ParseError::Syntax(token.describe()),
+  }
+}
+
+/// Tokens a tolerant reader resynchronises at
+fn is_boundary(token: &Token) -> bool
+{
+  match *token {
+    Token::Comma | Token::ListEnd | Token::SExpEnd | Token::StructEnd => true,
+    _ => false,
+  }
+}
+
+/// Errors a tolerant reader can carry on after; not those for running
+/// out of input, failing to read it, or going over limits
+fn is_recoverable(error: &ParseError) -> bool
+{
+  match *error {
+    ParseError::UnexpectedEnd | ParseError::Incomplete | ParseError::Io(..) | ParseError::LimitExceeded(_) => false,
+    _ => true,
+  }
+}
+
+/// Reads the top level values of ion text.
+///
+/// A `$ion_1_0` version marker resets the symbol table, and a
+/// `$ion_symbol_table::{...}` struct replaces it; symbol IDs are looked up
+/// in the current table. Neither is returned unless
+/// `options.system_values` is set.
+///
+/// As an iterator, reading stops after the first error; running out of
+/// data in incremental reading also ends the iteration, but it continues
+/// once more data is fed. A `tolerant` reader only stops after errors it
+/// can't carry on after.
+pub struct TextReader<'a, S> {
+  lexer: Lexer<'a, S>,
+  symbols: SymbolTable,
+  options: ReaderOptions,
+
+  /// Containers the value being read is in
+  depth: usize,
+
+  /// Spans of the values read so far in the value being read, when
+  /// reading with spans
+  spans: Option<Vec<Spans>>,
+
+  /// Errors carried on after, when reading tolerantly
+  diagnostics: Option<Vec<Diagnostic>>,
+
+  /// Number of `diagnostics` before the value being read, which are kept
+  /// when it's read again from the start
+  checkpoint_diagnostics: usize,
+
+  /// A token to read again, which an error was found at, while
+  /// recovering from it
+  unread: Option<Token<'a>>,
+
+  /// Whether the last token was read again, having been reported already
+  reread: bool,
+  failed: bool,
+}
+
+impl<'a> TextReader<'a, StrSource<'a>> {
+  /// Read a string; values borrow from it
+  pub fn from_text(text: &'a str) -> TextReader<'a, StrSource<'a>>
+  {
+    TextReader::new(StrSource::new(text), ReaderOptions::default())
+  }
+}
+
+impl<R: Read> TextReader<'static, ReadSource<R>> {
+  /// Read from `reader`, which needn't be buffered
+  pub fn from_reader(reader: R) -> TextReader<'static, ReadSource<R>>
+  {
+    TextReader::new(ReadSource::new(reader), ReaderOptions::default())
+  }
+}
+
+impl TextReader<'static, ChunkSource> {
+  /// Read data fed in chunks with `feed`.
+  ///
+  /// Until `finish` is called, `next_value` returns
+  /// `ParseError::Incomplete` when it needs more data. Values already
+  /// returned are dropped from the buffer.
+  ///
+  /// Reading doesn't resume part way through a value, as the lexer's state
+  /// isn't kept between chunks. A value split between chunks waits until
+  /// data which may end it arrives (found by scanning each chunk once),
+  /// and is then lexed again from its start, so the part of it in earlier
+  /// chunks is lexed more than once.
+  pub fn incremental(options: ReaderOptions) -> TextReader<'static, ChunkSource>
+  {
+    TextReader::new(ChunkSource::new(), options)
+  }
+
+  /// Add the next chunk of input
+  pub fn feed(&mut self, data: &[u8])
+  {
+    self.lexer.source_mut().feed(data);
+  }
+
+  /// Mark the end of input, after which values must be complete
+  pub fn finish(&mut self)
+  {
+    self.lexer.source_mut().finish();
+  }
+}
+
+impl<'a, S: Source<'a>> TextReader<'a, S> {
+  pub fn new(source: S, options: ReaderOptions) -> TextReader<'a, S>
+  {
+    let mut lexer = Lexer::new(source);
+    lexer.limit(&options);
+    TextReader {
+      lexer: lexer,
+      symbols: SymbolTable::system(),
+      options: options,
+      depth: 0,
+      spans: None,
+      diagnostics: None,
+      checkpoint_diagnostics: 0,
+      unread: None,
+      reread: false,
+      failed: false,
+    }
+  }
+
+  /// Start from `symbols` rather than the system table, as when reading
+  /// from part way through a stream
+  pub(crate) fn with_symbols(mut self, symbols: SymbolTable) -> TextReader<'a, S>
+  {
+    self.symbols = symbols;
+    self
+  }
+
+  /// The symbol table in effect
+  pub(crate) fn symbols(&self) -> &SymbolTable
+  {
+    &self.symbols
+  }
+
+  /// Carry on after syntax errors, undefined symbols and invalid symbol
+  /// tables, collecting them as diagnostics rather than returning them.
+  ///
+  /// After an error in a list or struct, the reader skips to the next
+  /// comma or the end of the container; in an s-expression, to the next
+  /// token; at top level, to the next value. Values with errors in them
+  /// are dropped, while the containers around them are kept.
+  pub fn tolerant(mut self) -> TextReader<'a, S>
+  {
+    self.diagnostics = Some(Vec::new());
+    self
+  }
+
+  /// The errors carried on after so far, when reading tolerantly
+  pub fn diagnostics(&self) -> &[Diagnostic]
+  {
+    self.diagnostics.as_ref().map_or(&[], Vec::as_slice)
+  }
+
+  /// Take the errors carried on after so far
+  pub fn take_diagnostics(&mut self) -> Vec<Diagnostic>
+  {
+    self.checkpoint_diagnostics = 0;
+    self.diagnostics.as_mut().map_or(Vec::new(), |diagnostics| diagnostics.split_off(0))
+  }
+
+  /// The next top level value, or `None` at the end of input
+  pub fn next_value(&mut self) -> Result<Option<AnionValueRef<'a>>, ParseError>
+  {
+    loop {
+      match self.read_next() {
+        Err(ParseError::Incomplete) => {
+          self.lexer.source_mut().rewind();
+          self.unread = None;
+          if let Some(ref mut diagnostics) = self.diagnostics {
+            diagnostics.truncate(self.checkpoint_diagnostics);
+          }
+          return Err(ParseError::Incomplete);
+        },
+        Err(error) => {
+          self.diagnose(error)?;
+          // a stray comma or closing bracket is dropped
+          self.unread = None;
+        },
+        result => return result,
+      }
+    }
+  }
+
+  /// The next top level value and the spans of it and its parts, or
+  /// `None` at the end of input
+  pub fn next_value_with_spans(&mut self) -> Result<Option<(AnionValueRef<'a>, Spans)>, ParseError>
+  {
+    self.spans = Some(Vec::new());
+    let result = self.next_value();
+    let spans = self.spans.take().and_then(|mut spans| spans.pop());
+    match result? {
+      Some(value) => Ok(Some((value, spans.expect("a value read with spans has them")))),
+      None => Ok(None),
+    }
+  }
+
+  /// Where the next byte is; after an error, about where it was found
+  pub fn location(&self) -> Location
+  {
+    self.lexer.location()
+  }
+
+  fn read_next(&mut self) -> Result<Option<AnionValueRef<'a>>, ParseError>
+  {
+    loop {
+      if let Some(ref mut spans) = self.spans {
+        spans.clear();
+      }
+      self.lexer.source_mut().checkpoint();
+      self.checkpoint_diagnostics = self.diagnostics.as_ref().map_or(0, Vec::len);
+      if !self.lexer.source_mut().ready() {
+        return Err(ParseError::Incomplete);
+      }
+      let token = self.next_token(false)?;
+      if token == Token::End {
+        return Ok(None);
+      }
+
+      if let Token::Identifier(ref text) = token {
+        if let SymbolToken::VersionMarker(marker) = classify_identifier(text) {
+          // unless it's an annotation
+          if !self.lexer.at_double_colon()? {
+            if marker != "$ion_1_0" {
+              return Err(ParseError::UnsupportedVersion(String::from(marker)));
+            }
+            self.symbols = SymbolTable::system();
+            if self.options.system_values {
+              let span = self.lexer.span();
+              self.push_spans(span, Vec::new(), 0);
+              return Ok(Some(AnionValueRef::Symbol(Some(text.clone()))));
+            }
+            continue;
+          }
+        }
+      }
+
+      let value = self.read_value(token, false)?;
+      // only likely symbol tables are copied to be checked
+      let table = match value {
+        AnionValueRef::Annotated(ref annotations, _) if annotations[0] == "$ion_symbol_table" => Some(value.to_owned()),
+        _ => None,
+      };
+      if let Some(fields) = table.as_ref().and_then(symbol_table_fields) {
+        self.symbols = self.symbols.local(fields, self.options.max_symbols)?;
+        if !self.options.system_values {
+          continue;
+        }
+      }
+      return Ok(Some(value));
+    }
+  }
+
+  /// The value starting with `token`, and any annotations before it
+  fn read_value(&mut self, token: Token<'a>, in_sexp: bool) -> Result<AnionValueRef<'a>, ParseError>
+  {
+    let first = self.spans.as_ref().map_or(0, Vec::len);
+    let value = self.read_annotated_value(token, in_sexp, first);
+    if value.is_err() {
+      // drop the spans of the parts read before the error
+      if let Some(ref mut spans) = self.spans {
+        spans.truncate(first);
+      }
+    }
+    return value;
+  }
+
+  /// `read_value`, whose parts' spans start at `first`
+  fn read_annotated_value(&mut self, token: Token<'a>, in_sexp: bool, first: usize) -> Result<AnionValueRef<'a>, ParseError>
+  {
+    let mut token = token;
+    let mut annotations = Vec::new();
+    let mut annotation_spans = Vec::new();
+    while self.is_symbol(&token) && self.lexer.at_double_colon()? {
+      if self.spans.is_some() {
+        annotation_spans.push(self.lexer.span());
+      }
+      annotations.push(self.symbol_text(token)?);
+      self.next_token(false)?;
+      token = self.next_token(false)?;
+    }
+    let start = self.lexer.span().start;
+
+    let value = match token {
+      Token::Null(type_name) => null_value(type_name),
+      Token::Bool(b) => AnionValueRef::Boolean(Some(b)),
+      Token::Int(i) => AnionValueRef::Integer(Some(i)),
+      Token::Float(f) => AnionValueRef::Float(Some(f)),
+      Token::Decimal(d) => AnionValueRef::Decimal(Some(d)),
+      Token::Timestamp(t) => AnionValueRef::Timestamp(Some(t)),
+      Token::String(s) => AnionValueRef::String(Some(self.check_string(s)?)),
+      Token::Identifier(_) | Token::QuotedSymbol(_) => AnionValueRef::Symbol(Some(self.symbol_text(token)?)),
+      Token::Operator(s) if in_sexp && annotations.is_empty() => AnionValueRef::Symbol(Some(s)),
+      Token::Blob(b) => AnionValueRef::Blob(Some(Cow::Owned(self.check_lob(b)?))),
+      Token::Clob(b) => AnionValueRef::Clob(Some(Cow::Owned(self.check_lob(b)?))),
+      Token::ListStart => AnionValueRef::List(Some(self.nested(Self::read_list)?)),
+      Token::SExpStart => AnionValueRef::SExp(Some(self.nested(Self::read_sexp)?)),
+      Token::StructStart => AnionValueRef::Struct(Some(self.nested(Self::read_struct)?)),
+      token => return Err(self.unexpected_token(token)),
+    };
+    let span = Span { start: start, end: self.lexer.span().end };
+    self.push_spans(span, annotation_spans, first);
+
+    if annotations.is_empty() {
+      return Ok(value);
+    }
+    return Ok(AnionValueRef::Annotated(annotations, Box::new(value)));
+  }
+
+  /// When reading with spans, add those of a value, whose parts' spans
+  /// start at `first`
+  fn push_spans(&mut self, value: Span, annotations: Vec<Span>, first: usize)
+  {
+    if let Some(ref mut spans) = self.spans {
+      let children = spans.split_off(first);
+      spans.push(Spans { value: value, annotations: annotations, field_name: None, children: children });
+    }
+  }
+
+  /// Read the contents of a container with `read`, one level deeper
+  fn nested<T, F>(&mut self, read: F) -> Result<T, ParseError>
+    where F: FnOnce(&mut Self) -> Result<T, ParseError>
+  {
+    check_limit(self.depth + 1, self.options.max_depth, Limit::Depth)?;
+    self.depth += 1;
+    let result = read(self);
+    self.depth -= 1;
+    return result;
+  }
+
+  fn read_list(&mut self) -> Result<Vec<AnionValueRef<'a>>, ParseError>
+  {
+    let mut values = Vec::new();
+    let mut token = self.next_token(false);
+    if token == Ok(Token::ListEnd) {
+      return Ok(values);
+    }
+    loop {
+      check_limit(values.len() + 1, self.options.max_container_length, Limit::ContainerLength)?;
+      let ended = match token.and_then(|token| self.read_value(token, false)) {
+        Ok(value) => {
+          values.push(value);
+          match self.next_token(false)? {
+            Token::Comma => false,
+            Token::ListEnd => true,
+            other => {
+              let error = self.unexpected_token(other);
+              self.recover(error, Token::ListEnd)?
+            },
+          }
+        },
+        Err(error) => self.recover(error, Token::ListEnd)?,
+      };
+      if ended {
+        return Ok(values);
+      }
+      token = self.next_token(false);
+    }
+  }
+
+  fn read_sexp(&mut self) -> Result<Vec<AnionValueRef<'a>>, ParseError>
+  {
+    let mut values = Vec::new();
+    loop {
+      let token = match self.next_token(true) {
+        Ok(Token::SExpEnd) => return Ok(values),
+        token => token,
+      };
+      check_limit(values.len() + 1, self.options.max_container_length, Limit::ContainerLength)?;
+      match token.and_then(|token| self.read_value(token, true)) {
+        Ok(value) => values.push(value),
+        Err(error) => {
+          if self.recover(error, Token::SExpEnd)? {
+            return Ok(values);
+          }
+        },
+      }
+    }
+  }
+
+  fn read_struct(&mut self) -> Result<Vec<(Cow<'a, str>, AnionValueRef<'a>)>, ParseError>
+  {
+    let mut fields = Vec::new();
+    let mut token = self.next_token(false);
+    if token == Ok(Token::StructEnd) {
+      return Ok(fields);
+    }
+    loop {
+      check_limit(fields.len() + 1, self.options.max_container_length, Limit::ContainerLength)?;
+      let ended = match token.and_then(|token| self.read_field(token)) {
+        Ok(field) => {
+          fields.push(field);
+          match self.next_token(false)? {
+            Token::Comma => false,
+            Token::StructEnd => true,
+            other => {
+              let error = self.unexpected_token(other);
+              self.recover(error, Token::StructEnd)?
+            },
+          }
+        },
+        Err(error) => self.recover(error, Token::StructEnd)?,
+      };
+      if ended {
+        return Ok(fields);
+      }
+      token = self.next_token(false);
+    }
+  }
+
+  /// The struct field whose name is `token`
+  fn read_field(&mut self, token: Token<'a>) -> Result<(Cow<'a, str>, AnionValueRef<'a>), ParseError>
+  {
+    let name_span = self.lexer.span();
+    let name = match token {
+      Token::String(name) => self.check_string(name)?,
+      Token::Identifier(_) | Token::QuotedSymbol(_) => self.symbol_text(token)?,
+      other => return Err(self.unexpected_token(other)),
+    };
+    match self.next_token(false)? {
+      Token::Colon => {},
+      other => return Err(self.unexpected_token(other)),
+    }
+    let value = self.next_token(false)?;
+    let value = self.read_value(value, false)?;
+    if let Some(spans) = self.spans.as_mut().and_then(|spans| spans.last_mut()) {
+      spans.field_name = Some(name_span);
+    }
+    return Ok((name, value));
+  }
+
+  /// The next token, which may be one read again while recovering
+  fn next_token(&mut self, in_sexp: bool) -> Result<Token<'a>, ParseError>
+  {
+    self.reread = self.unread.is_some();
+    match self.unread.take() {
+      Some(token) => Ok(token),
+      None => self.lexer.next_token(in_sexp),
+    }
+  }
+
+  /// The error for an unexpected token; a tolerant reader keeps a comma
+  /// or closing bracket to resynchronise at
+  fn unexpected_token(&mut self, token: Token<'a>) -> ParseError
+  {
+    if self.diagnostics.is_some() && is_boundary(&token) {
+      self.unread = Some(token.clone());
+    }
+    unexpected(token)
+  }
+
+  /// When reading tolerantly, note an error to carry on after; otherwise,
+  /// or if it can't be carried on after, fail with it
+  fn diagnose(&mut self, error: ParseError) -> Result<(), ParseError>
+  {
+    if self.diagnostics.is_none() || !is_recoverable(&error) {
+      return Err(error);
+    }
+    // a token read again has been reported already
+    if !self.reread {
+      let diagnostic = Diagnostic { error: error, span: self.lexer.span() };
+      if let Some(ref mut diagnostics) = self.diagnostics {
+        diagnostics.push(diagnostic);
+      }
+    }
+    return Ok(());
+  }
+
+  /// Carry on after `error` in a container ending with `end`, skipping
+  /// to the next element; true if the container ended. A different
+  /// closing bracket also ends it, and is left for the container it
+  /// closes.
+  fn recover(&mut self, error: ParseError, end: Token<'a>) -> Result<bool, ParseError>
+  {
+    self.diagnose(error)?;
+
+    // every token starts an element of an s-expression
+    if end == Token::SExpEnd {
+      if self.unread == Some(Token::Comma) {
+        self.unread = None;
+      }
+      return match self.unread {
+        Some(Token::SExpEnd) => {
+          self.unread = None;
+          Ok(true)
+        },
+        Some(_) => Ok(true),
+        None => Ok(false),
+      };
+    }
+
+    let mut depth = 0;
+    loop {
+      let token = match self.next_token(true) {
+        Ok(token) => token,
+        // part of what's skipped
+        Err(ref error) if is_recoverable(error) => continue,
+        Err(error) => return Err(error),
+      };
+      match token {
+        Token::ListStart | Token::SExpStart | Token::StructStart => depth += 1,
+        Token::ListEnd | Token::SExpEnd | Token::StructEnd if depth > 0 => depth -= 1,
+        Token::Comma if depth == 0 => return Ok(false),
+        Token::ListEnd | Token::SExpEnd | Token::StructEnd => {
+          if token != end {
+            self.unread = Some(token);
+          }
+          return Ok(true);
+        },
+        Token::End => return Err(ParseError::UnexpectedEnd),
+        _ => {},
+      }
+    }
+  }
+
+  fn check_string(&self, text: Cow<'a, str>) -> Result<Cow<'a, str>, ParseError>
+  {
+    check_limit(text.len(), self.options.max_string_length, Limit::StringLength)?;
+    return Ok(text);
+  }
+
+  fn check_lob(&self, bytes: Vec<u8>) -> Result<Vec<u8>, ParseError>
+  {
+    check_limit(bytes.len(), self.options.max_lob_length, Limit::LobLength)?;
+    return Ok(bytes);
+  }
+
+  fn is_symbol(&self, token: &Token<'a>) -> bool
+  {
+    match *token {
+      Token::Identifier(_) | Token::QuotedSymbol(_) => true,
+      _ => false,
+    }
+  }
+
+  /// Text of a symbol token; symbol IDs are looked up in the symbol table,
+  /// and symbols with unknown text are `$0`
+  fn symbol_text(&self, token: Token<'a>) -> Result<Cow<'a, str>, ParseError>
+  {
+    let identifier = match token {
+      Token::Identifier(identifier) => identifier,
+      Token::QuotedSymbol(text) => return self.check_string(text),
+      other => return Err(unexpected(other)),
+    };
+    check_limit(identifier.len(), self.options.max_string_length, Limit::StringLength)?;
+
+    let sid = match classify_identifier(&identifier) {
+      SymbolToken::SymbolId(sid) => sid,
+      _ => return Ok(identifier),
+    };
+    let text = self.symbols.text(sid)?.unwrap_or(::UNKNOWN_SYMBOL_TEXT);
+    return Ok(Cow::Owned(String::from(text)));
+  }
+}
+
+impl<'a, S: Source<'a>> Iterator for TextReader<'a, S> {
+  type Item = Result<AnionValueRef<'a>, ParseError>;
+
+  fn next(&mut self) -> Option<Self::Item>
+  {
+    if self.failed {
+      return None;
+    }
+    match self.next_value() {
+      Ok(value) => value.map(Ok),
+      Err(ParseError::Incomplete) => None,
+      Err(error) => {
+        self.failed = true;
+        Some(Err(error))
+      },
+    }
+  }
+}
+
+/// Parse text holding a single ion value
+pub fn parse_string(a_string: &str) -> Result<AnionValue, ParseError>
+{
+  let mut values = parse_values(a_string)?;
+  if values.len() != 1 {
+    return Err(ParseError::Syntax(String::from(a_string)));
+  }
+  return Ok(values.remove(0));
+}
+
+/// Parse the top level user values of ion text
+pub fn parse_values(text: &str) -> Result<Vec<AnionValue>, ParseError>
+{
+  parse_values_with(text, &ReaderOptions::default())
+}
+
+/// Parse the top level values of ion text; see `TextReader`
+pub fn parse_values_with(text: &str, options: &ReaderOptions) -> Result<Vec<AnionValue>, ParseError>
+{
+  let values = parse_values_ref_with(text, options)?;
+  return Ok(values.into_iter().map(AnionValueRef::into_owned).collect());
+}
+
+/// Parse the top level user values of ion text, borrowing from it
+pub fn parse_values_ref<'a>(text: &'a str) -> Result<Vec<AnionValueRef<'a>>, ParseError>
+{
+  parse_values_ref_with(text, &ReaderOptions::default())
+}
+
+/// Parse the top level values of ion text, borrowing from it; see
+/// `TextReader`
+pub fn parse_values_ref_with<'a>(text: &'a str, options: &ReaderOptions) -> Result<Vec<AnionValueRef<'a>>, ParseError>
+{
+  TextReader::new(StrSource::new(text), options.clone()).collect()
+}
+
+/// Parse the top level user values of ion text, carrying on after errors
+/// as a `tolerant` reader does; returns the values read and every error
+/// found, in order
+pub fn parse_values_tolerant(text: &str) -> (Vec<AnionValue>, Vec<Diagnostic>)
+{
+  let mut reader = TextReader::from_text(text).tolerant();
+  let mut values = Vec::new();
+  let mut failure = None;
+  loop {
+    match reader.next_value() {
+      Ok(Some(value)) => values.push(value.into_owned()),
+      Ok(None) => break,
+      Err(error) => {
+        failure = Some(Diagnostic { error: error, span: reader.lexer.span() });
+        break;
+      },
+    }
+  }
+  let mut diagnostics = reader.take_diagnostics();
+  diagnostics.extend(failure);
+  return (values, diagnostics);
+}
+
+/// Parse the top level user values of ion text, with the spans of each
+/// value and its parts
+pub fn parse_values_with_spans(text: &str) -> Result<Vec<(AnionValue, Spans)>, ParseError>
+{
+  let mut reader = TextReader::from_text(text);
+  let mut values = Vec::new();
+  while let Some((value, spans)) = reader.next_value_with_spans()? {
+    values.push((value.into_owned(), spans));
+  }
+  return Ok(values);
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use Decimal;
+  use num_bigint::BigInt;
+  use std::f64;
+
+  fn sym(s: &str) -> AnionValue
+  {
+    AnionValue::Symbol(Some(String::from(s)))
+  }
+
+  #[test]
+  fn numbers_without_stop_characters_are_errors()
+  {
+    let error = |number: &str, rest: &str| Err(ParseError::NumberNotStopped(String::from(number), String::from(rest)));
+
+    assert_eq!(parse_string("123abc"), error("123", "abc"));
+    assert_eq!(parse_string("1.0e5x"), error("1.0e5", "x"));
+    assert_eq!(parse_string("-0x1fg"), error("-0x1f", "g"));
+    assert_eq!(parse_string("1.5.5"), error("1.5", ".5"));
+    assert_eq!(parse_string("12:30"), error("12", ":30"));
+    assert_eq!(parse_string("+1"), Err(ParseError::Syntax(String::from("+1"))));
+    assert_eq!(parse_string("12 "), Ok(AnionValue::from(12)));
+  }
+
+  #[test]
+  fn numbers()
+  {
+    assert_eq!(parse_string("[0x1F, -0b11, 1_000, 1.5, 2d1]"), Ok(AnionValue::List(Some(vec![
+      AnionValue::from(31), AnionValue::from(-3), AnionValue::from(1000),
+      AnionValue::from(Decimal::from_str("1.5").unwrap()), AnionValue::from(Decimal::from_str("20").unwrap()),
+    ]))));
+    assert_eq!(parse_string("-0x8000_0000_0000_0000"), Ok(AnionValue::from(i64::min_value())));
+    assert_eq!(parse_string("123456789012345678901234567890").map(|v| v.to_string()), Ok(String::from("123456789012345678901234567890")));
+    assert_eq!(parse_string("-1.5e1"), Ok(AnionValue::from(-15.0)));
+    assert_eq!(parse_string("null.int"), Ok(AnionValue::Integer(None)));
+    assert!(parse_string("0x").is_err());
+  }
+
+  #[test]
+  fn number_forms()
+  {
+    let ints = [
+      ("42", 42), ("0", 0), ("-1000", -1000), ("3_141_592_6", 31415926), ("0x42", 66), ("-0x10101", -65793),
+      ("0XfF_fF", 65535), ("0b10101", 21), ("-0b1_1", -3), ("0b1101_1111_0101", 3573),
+    ];
+    for &(text, int) in ints.iter() {
+      assert_eq!(parse_string(text), Ok(AnionValue::from(int)), "{}", text);
+    }
+    let big = BigInt::parse_bytes(b"123456789abcdef0123456789abcdef", 16).unwrap();
+    assert_eq!(parse_string("-0x1234_5678_9ABC_DEF0_1234_5678_9ABC_DEF"), Ok(AnionValue::from(-big)));
+
+    let floats = [("-12.21e1", -122.1), ("1e5", 1e5), ("1.e-1", 0.1), ("-0e0", -0.0), ("1_000.5E+0", 1000.5)];
+    for &(text, float) in floats.iter() {
+      assert_eq!(parse_string(text), Ok(AnionValue::from(float)), "{}", text);
+    }
+
+    let decimals = [
+      ("0.", "0"), ("-0.0", "-0.0"), ("0d5", "0d5"), ("-0d0", "-0d0"), ("42.", "42"), ("-12.21", "-12.21"),
+      ("1D+2", "1d2"), ("1_000.000_1", "1000.0001"), ("-1_2.3_4d-5", "-12.34d-5"),
+    ];
+    for &(text, decimal) in decimals.iter() {
+      let expected = AnionValue::from(Decimal::from_str(decimal).unwrap());
+      assert!(parse_string(text).unwrap().ion_eq(&expected), "{}", text);
+    }
+
+    // each is an error or some other kind of value
+    let not_numbers = [
+      ("_42", "int"), ("-_90", "int"), ("3_1__41", "int"), ("12_", "int"), ("00", "int"), ("0x_42", "int"),
+      ("0x42_", "int"), ("0b102", "int"), ("0o42", "int"), ("+0x42", "int"), ("--1", "int"), ("-0x-1", "int"),
+      ("+1e0", "float"), (".5e1", "float"), ("01e0", "float"), ("1e_1", "float"), ("1_e1", "float"),
+      (".0", "decimal"), ("+3.1415", "decimal"), ("01.0", "decimal"), ("00d0", "decimal"), ("1_.0", "decimal"),
+      ("1._0", "decimal"), ("1.0_", "decimal"), ("1.0__1", "decimal"), ("1.0d", "decimal"), ("1d1_0", "decimal"),
+    ];
+    for &(text, kind) in not_numbers.iter() {
+      let is_kind = match parse_string(text) {
+        Ok(AnionValue::Integer(_)) => kind == "int",
+        Ok(AnionValue::Float(_)) => kind == "float",
+        Ok(AnionValue::Decimal(_)) => kind == "decimal",
+        _ => false,
+      };
+      assert!(!is_kind, "{}", text);
+    }
+
+    let stops = [" ", "\t", "\n", ",", "]", ")", "}", "[", "(", "{", "//c", "/*c*/", "\"s\"", "'s'", ""];
+    for &stop in stops.iter() {
+      for &number in ["42", "0x2a", "0b101", "1.5", "1.5e0", "nan", "2007T"].iter() {
+        let text = format!("{}{}", number, stop);
+        assert!(TextReader::from_text(&text).next_value().is_ok(), "{}", text);
+      }
+    }
+  }
+
+  #[test]
+  fn float_special_values_need_sign()
+  {
+    assert_eq!(parse_string("inf"), Ok(sym("inf")));
+    assert_eq!(parse_string("nano"), Ok(sym("nano")));
+    assert!(parse_string("nan").unwrap().ion_eq(&AnionValue::from(f64::NAN)));
+    assert!(parse_string("+infinity").is_err());
+    assert!(parse_string("nan_").unwrap() != AnionValue::from(f64::NAN));
+  }
+
+  #[test]
+  fn float_special_values_round_trip()
+  {
+    for &x in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN].iter() {
+      let text = AnionValue::from(x).to_string();
+      assert!(parse_string(&text).unwrap().ion_eq(&AnionValue::from(x)));
+    }
+  }
+
+  #[test]
+  fn containers()
+  {
+    assert_eq!(parse_string("[1, a, \"b\", [], null]"),
+               Ok(AnionValue::List(Some(vec![AnionValue::from(1), sym("a"), AnionValue::from("b"), AnionValue::List(Some(vec![])), AnionValue::Null]))));
+    assert_eq!(parse_string("(f [1.5]\n  (g))"),
+               Ok(AnionValue::SExp(Some(vec![sym("f"), AnionValue::List(Some(vec![AnionValue::Decimal(Some(Decimal::from_str("1.5").unwrap()))])),
+                                             AnionValue::SExp(Some(vec![sym("g")]))]))));
+    assert!(parse_string("[1 2]").is_err());
+    assert!(parse_string("[1,]").is_err());
+    assert_eq!(parse_string("(a"), Err(ParseError::UnexpectedEnd));
+    assert!(parse_string("1 2").is_err());
+  }
+
+  #[test]
+  fn strings_lobs_and_comments()
+  {
+    assert_eq!(parse_string("'''multi''' // joined\n '''part'''"), Ok(AnionValue::from("multipart")));
+    assert_eq!(parse_string("\"tab\\tand \\u00e9\""), Ok(AnionValue::from("tab\tand é")));
+    assert_eq!(parse_string("/* a blob */ {{ aGk= }}"), Ok(AnionValue::Blob(Some(b"hi".to_vec()))));
+    assert_eq!(parse_string("{{\"hi\"}}"), Ok(AnionValue::Clob(Some(b"hi".to_vec()))));
+    assert_eq!(parse_string("{'''a''': [null.blob, null.clob]}"),
+               Ok(AnionValue::Struct(Some(vec![(String::from("a"), AnionValue::List(Some(vec![AnionValue::Blob(None), AnionValue::Clob(None)])))]))));
+  }
+
+  #[test]
+  fn operators_in_sexps()
+  {
+    let sexp = |values: Vec<AnionValue>| AnionValue::SExp(Some(values));
+
+    assert_eq!(parse_string("(+ a (* b 2))"),
+               Ok(sexp(vec![sym("+"), sym("a"), sexp(vec![sym("*"), sym("b"), AnionValue::from(2)])])));
+    assert_eq!(parse_string("(>= x 10)"), Ok(sexp(vec![sym(">="), sym("x"), AnionValue::from(10)])));
+    assert_eq!(parse_string("(a+b)"), Ok(sexp(vec![sym("a"), sym("+"), sym("b")])));
+    assert_eq!(parse_string("(- 1)"), Ok(sexp(vec![sym("-"), AnionValue::from(1)])));
+    assert_eq!(parse_string("(-1)"), Ok(sexp(vec![AnionValue::from(-1)])));
+    assert_eq!(parse_string("(!%&|~^`?@;)"), Ok(sexp(vec![sym("!%&|~^`?@;")])));
+    assert!(parse_string("(+inf)").unwrap().ion_eq(&sexp(vec![AnionValue::from(f64::INFINITY)])));
+
+    // anywhere else they're errors
+    for &src in ["+", ">=", "[+]", "[a, *]", "(1+2)", "(a::+)"].iter() {
+      assert!(parse_string(src).is_err(), "{}", src);
+    }
+  }
+
+  #[test]
+  fn keywords_are_not_symbols()
+  {
+    for &src in ["true", "false", "null", "nan", "null.int", "null.bool"].iter() {
+      assert!(parse_string(src).unwrap().type_name() != "symbol", "{}", src);
+    }
+
+    assert_eq!(parse_string("(true nan null.list null.null)").map(|v| v.to_string()),
+               Ok(String::from("(true nan null.list null)")));
+    for &src in ["trueish", "falsey", "nullable", "nan_value", "null_int", "infinity"].iter() {
+      assert_eq!(parse_string(src), Ok(sym(src)));
+    }
+    for &src in ["null.", "null.integer", "null.int.x", "null.bool_"].iter() {
+      assert!(parse_string(src).is_err(), "{}", src);
+    }
+  }
+
+  #[test]
+  fn symbol_ids()
+  {
+    assert_eq!(classify_identifier("$0"), SymbolToken::SymbolId(0));
+    assert_eq!(classify_identifier("$12"), SymbolToken::SymbolId(12));
+    assert_eq!(classify_identifier("$1a"), SymbolToken::Text("$1a"));
+    assert_eq!(classify_identifier("$"), SymbolToken::Text("$"));
+    assert_eq!(classify_identifier("$ion_1_0"), SymbolToken::VersionMarker("$ion_1_0"));
+    assert_eq!(classify_identifier("$ion_2_x"), SymbolToken::Text("$ion_2_x"));
+
+    assert_eq!(parse_string("$0"), Ok(sym(::UNKNOWN_SYMBOL_TEXT)));
+    assert_eq!(parse_string("$4"), Ok(sym("name")));
+    assert_eq!(parse_string("($3 $9)"), Ok(AnionValue::SExp(Some(vec![sym("$ion_symbol_table"), sym("$ion_shared_symbol_table")]))));
+    assert_eq!(parse_string("$10"), Err(ParseError::UnknownSymbolId(10)));
+    assert_eq!(parse_string("$99999999999999999999999"), Err(ParseError::UnknownSymbolId(usize::max_value())));
+  }
+
+  #[test]
+  fn version_markers()
+  {
+    assert_eq!(parse_values("$ion_1_0 1 $ion_1_0 a"), Ok(vec![AnionValue::from(1), sym("a")]));
+    assert_eq!(parse_values("$ion_1_0"), Ok(vec![]));
+    assert_eq!(parse_values("[$ion_1_0]"), Ok(vec![AnionValue::List(Some(vec![sym("$ion_1_0")]))]));
+    assert_eq!(parse_values("$ion_2_0 1"), Err(ParseError::UnsupportedVersion(String::from("$ion_2_0"))));
+    assert!(parse_string("$ion_1_0").is_err());
+  }
+
+  #[test]
+  fn structs_annotations_and_quoted_symbols()
+  {
+    assert_eq!(parse_string("{a: 1, 'b c': x, \"d\": [], $4: null.struct}"),
+               Ok(AnionValue::Struct(Some(vec![(String::from("a"), AnionValue::from(1)), (String::from("b c"), sym("x")),
+                                               (String::from("d"), AnionValue::List(Some(vec![]))), (String::from("name"), AnionValue::Struct(None))]))));
+    assert_eq!(parse_string("a :: 'b'::[c::d]"),
+               Ok(AnionValue::Annotated(vec![String::from("a"), String::from("b")],
+                                        Box::new(AnionValue::List(Some(vec![AnionValue::Annotated(vec![String::from("c")], Box::new(sym("d")))]))))));
+    assert_eq!(parse_string("'it\\'s'"), Ok(sym("it's")));
+    assert_eq!(parse_string("'true'"), Ok(sym("true")));
+    assert_eq!(parse_string("'$ion_1_0'"), Ok(sym("$ion_1_0")));
+
+    for &src in ["{a 1}", "{a: 1,}", "{true: 1}", "a::", "a::(+)::b"].iter() {
+      assert!(parse_string(src).is_err(), "{}", src);
+    }
+  }
+
+  #[test]
+  fn local_symbol_tables()
+  {
+    let text = "$ion_symbol_table::{symbols: [\"a\", \"b\"]} $10 {$11: $4}";
+    assert_eq!(parse_values(text), Ok(vec![sym("a"), AnionValue::Struct(Some(vec![(String::from("b"), sym("name"))]))]));
+
+    // appending to and replacing the current table
+    let text = "$ion_symbol_table::{symbols: [\"a\"]} \
+                $ion_symbol_table::{imports: $ion_symbol_table, symbols: [\"b\"]} ($10 $11) \
+                $3::{symbols: [\"c\"]} $10";
+    assert_eq!(parse_values(text), Ok(vec![AnionValue::SExp(Some(vec![sym("a"), sym("b")])), sym("c")]));
+
+    // version markers reset it
+    assert_eq!(parse_values("$ion_symbol_table::{symbols: [\"a\"]} $10 $ion_1_0 $10"), Err(ParseError::UnknownSymbolId(10)));
+
+    // symbols with unknown text
+    let text = "$ion_symbol_table::{imports: [{name: \"shared\", max_id: 1}], symbols: [1, \"a\"]} $10 $11 $12";
+    assert_eq!(parse_values(text), Ok(vec![sym(::UNKNOWN_SYMBOL_TEXT), sym(::UNKNOWN_SYMBOL_TEXT), sym("a")]));
+
+    // only top level structs are symbol tables
+    assert_eq!(parse_values("[$ion_symbol_table::{symbols: [\"a\"]}] $ion_symbol_table::[]").map(|values| values.len()), Ok(2));
+    assert!(parse_values("[$ion_symbol_table::{symbols: [\"a\"]}] $10").is_err());
+  }
+
+  #[test]
+  fn system_values_on_request()
+  {
+    let options = ReaderOptions { system_values: true, ..ReaderOptions::default() };
+    let text = "$ion_1_0 $ion_symbol_table::{symbols: [\"a\"]} $10";
+
+    let values = parse_values_with(text, &options).unwrap();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values[0], AnionValue::Symbol(Some(String::from("$ion_1_0"))));
+    assert!(symbol_table_fields(&values[1]).is_some());
+    assert_eq!(values[2], AnionValue::Symbol(Some(String::from("a"))));
+
+    assert_eq!(parse_values(text).map(|values| values.len()), Ok(1));
+  }
+
+  #[test]
+  fn limits()
+  {
+    let limited = |options: ReaderOptions, text: &str| parse_values_with(text, &options);
+    let exceeded = |limit| Err(ParseError::LimitExceeded(limit));
+    let default = ReaderOptions::default;
+
+    assert!(limited(ReaderOptions { max_depth: 2, ..default() }, "[(1)] {a: []} 1").is_ok());
+    assert_eq!(limited(ReaderOptions { max_depth: 2, ..default() }, "[({a: 1})]"), exceeded(Limit::Depth));
+    assert_eq!(limited(ReaderOptions { max_depth: 0, ..default() }, "a::[]"), exceeded(Limit::Depth));
+    // deep nesting fails instead of overflowing the stack by default
+    let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    assert!(parse_values(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+    assert_eq!(parse_values(&nested(DEFAULT_MAX_DEPTH + 1)), exceeded(Limit::Depth));
+    assert_eq!(parse_values(&"{a: (".repeat(100000)), exceeded(Limit::Depth));
+
+    let options = ReaderOptions { max_container_length: 2, ..default() };
+    assert!(limited(options.clone(), "[1, 2] (a b) {a: 1, b: 2}").is_ok());
+    assert_eq!(limited(options.clone(), "[1, 2, 3]"), exceeded(Limit::ContainerLength));
+    assert_eq!(limited(options.clone(), "(a + b)"), exceeded(Limit::ContainerLength));
+    assert_eq!(limited(options.clone(), "{a: 1, b: 2, c: 3}"), exceeded(Limit::ContainerLength));
+
+    let options = ReaderOptions { max_string_length: 3, ..default() };
+    assert!(limited(options.clone(), "\"abc\" abc \'abc\' {abc: abc::1}").is_ok());
+    for text in &["\"abcd\"", "'''ab''' '''cd'''", "abcd", "'abcd'", "{abcd: 1}", "{\"abcd\": 1}", "abcd::1"] {
+      assert_eq!(limited(options.clone(), text), exceeded(Limit::StringLength), "{}", text);
+    }
+    // escapes count as what they stand for, and long text fails before
+    // it's all read
+    assert_eq!(limited(options.clone(), "\"\\u00e9\\n\" '''\\x41\\\n\\U0001F600'''"), exceeded(Limit::StringLength));
+    assert!(limited(options.clone(), "\"\\u00e9\\n\" '''\\x41\\\n\\u0042'''").is_ok());
+    assert_eq!(limited(options.clone(), "\"\\u00e9\\u00e9\""), exceeded(Limit::StringLength));
+    for text in &["\"abcd", "'''ab''' '''cd", "'abcd"] {
+      assert_eq!(limited(options.clone(), text), exceeded(Limit::StringLength), "{}", text);
+    }
+
+    let options = ReaderOptions { max_lob_length: 2, ..default() };
+    assert!(limited(options.clone(), "{{\"ab\"}} {{YWI=}}").is_ok());
+    assert_eq!(limited(options.clone(), "{{\"abc\"}}"), exceeded(Limit::LobLength));
+    assert_eq!(limited(options.clone(), "{{YWJj}}"), exceeded(Limit::LobLength));
+    assert_eq!(limited(options.clone(), "{{YWJjYWJj"), exceeded(Limit::LobLength));
+    assert_eq!(limited(options.clone(), "{{'''a''' '''bc"), exceeded(Limit::LobLength));
+
+    let options = ReaderOptions { max_int_digits: 4, ..default() };
+    assert!(limited(options.clone(), "1234 -1_234 0x1234 12.34 12.34e100").is_ok());
+    for text in &["12345", "-12_345", "0x12345", "123.45", "12345d-2", "0b10101"] {
+      assert_eq!(limited(options.clone(), text), exceeded(Limit::IntDigits), "{}", text);
+    }
+
+    let options = ReaderOptions { max_exponent: 3, ..default() };
+    assert!(limited(options.clone(), "1d3 1.5e-3 1.5 0.001 12345").is_ok());
+    for text in &["1d4", "1e4", "1.5e-4", "1d99999999999999999999999", "0.0001", "1.5d-3"] {
+      assert_eq!(limited(options.clone(), text), exceeded(Limit::Exponent), "{}", text);
+    }
+
+    let options = ReaderOptions { max_fraction_digits: 4, ..default() };
+    assert!(limited(options.clone(), "2007-02-23T12:14:33.1234Z 12345.678901").is_ok());
+    assert_eq!(limited(options.clone(), "2007-02-23T12:14:33.12345"), exceeded(Limit::FractionDigits));
+
+    let options = ReaderOptions { max_symbols: 10, ..default() };
+    assert!(limited(options.clone(), "$ion_symbol_table::{symbols: [\"a\"]} $10").is_ok());
+    assert_eq!(limited(options.clone(), "$ion_symbol_table::{symbols: [\"a\", \"b\"]}"), exceeded(Limit::Symbols));
+    let shared = "$ion_symbol_table::{imports: [{name: \"big\", max_id: 4000000000}]}";
+    assert_eq!(limited(options.clone(), shared), exceeded(Limit::Symbols));
+  }
+
+  #[test]
+  fn borrowed_values()
+  {
+    let is_borrowed = |text: &Cow<str>| match *text { Cow::Borrowed(_) => true, Cow::Owned(_) => false };
+    let text = String::from("$ion_symbol_table::{symbols: [\"s\"]} {plain: \"text\", escaped: \"a\\tb\"} a::$10");
+    let values = parse_values_ref(&text).unwrap();
+
+    match values[0] {
+      AnionValueRef::Struct(Some(ref fields)) => {
+        assert!(is_borrowed(&fields[0].0));
+        match fields[0].1 {
+          AnionValueRef::String(Some(ref s)) => assert!(is_borrowed(s) && s == "text"),
+          ref other => panic!("{:?}", other),
+        }
+        match fields[1].1 {
+          AnionValueRef::String(Some(ref s)) => assert!(!is_borrowed(s) && s == "a\tb"),
+          ref other => panic!("{:?}", other),
+        }
+      },
+      ref other => panic!("{:?}", other),
+    }
+
+    // symbol IDs resolve to copies of the table's text
+    match values[1] {
+      AnionValueRef::Annotated(ref annotations, ref value) => {
+        assert!(is_borrowed(&annotations[0]));
+        assert_eq!(**value, AnionValueRef::Symbol(Some(Cow::Owned(String::from("s")))));
+      },
+      ref other => panic!("{:?}", other),
+    }
+
+    let owned: Vec<AnionValue> = values.into_iter().map(AnionValueRef::into_owned).collect();
+    assert_eq!(Ok(owned), parse_values(&text));
+  }
+
+  #[test]
+  fn streaming_from_a_reader()
+  {
+    let text = "$ion_symbol_table::{symbols: [\"s\"]} {a: [1, $10]} (b 'c') \"d\"";
+    let streamed: Result<Vec<AnionValue>, _> = TextReader::from_reader(text.as_bytes()).map(|value| value.map(AnionValue::from)).collect();
+    assert_eq!(streamed, parse_values(text));
+
+    // one byte at a time
+    struct Trickle<'t>(&'t [u8]);
+    impl<'t> Read for Trickle<'t> {
+      fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>
+      {
+        if self.0.is_empty() || buf.is_empty() {
+          return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+      }
+    }
+    let trickled: Result<Vec<AnionValue>, _> = TextReader::from_reader(Trickle(text.as_bytes())).map(|value| value.map(AnionValue::from)).collect();
+    assert_eq!(trickled, parse_values(text));
+
+    let mut reader = TextReader::from_reader("1 [2".as_bytes());
+    assert_eq!(reader.next().map(|value| value.map(AnionValue::from)), Some(Ok(AnionValue::from(1))));
+    assert_eq!(reader.next(), Some(Err(ParseError::UnexpectedEnd)));
+    assert_eq!(reader.next(), None);
+  }
+
+  #[test]
+  fn incremental_reading()
+  {
+    let text = "$ion_symbol_table::{symbols: [\"s\"]} {a: [1, $10]} 'c'::12.5 \"d\" '''e''' '''f''' g::h // done";
+    let expected = parse_values(text).unwrap();
+
+    // split everywhere, including inside tokens
+    for chunk_size in 1..8 {
+      let mut reader = TextReader::incremental(ReaderOptions::default());
+      let mut values = Vec::new();
+      for chunk in text.as_bytes().chunks(chunk_size) {
+        reader.feed(chunk);
+        loop {
+          match reader.next_value() {
+            Ok(Some(value)) => values.push(value.into_owned()),
+            Err(ParseError::Incomplete) => break,
+            other => panic!("{:?}", other),
+          }
+        }
+      }
+      reader.finish();
+      while let Some(value) = reader.next_value().unwrap() {
+        values.push(value.into_owned());
+      }
+      assert_eq!(values, expected, "chunks of {}", chunk_size);
+    }
+  }
+
+  #[test]
+  fn incomplete_values_wait_for_more_data()
+  {
+    let mut reader = TextReader::incremental(ReaderOptions::default());
+
+    // the number might go on
+    reader.feed(b"[1] 12");
+    assert_eq!(reader.next().map(|value| value.map(AnionValue::from)), Some(Ok(ion!([1]))));
+    assert_eq!(reader.next_value(), Err(ParseError::Incomplete));
+    assert_eq!(reader.next(), None);
+
+    reader.feed(b"34 {a: ");
+    assert_eq!(reader.next_value().map(|value| value.map(AnionValue::from)), Ok(Some(AnionValue::from(1234))));
+    assert_eq!(reader.next_value(), Err(ParseError::Incomplete));
+
+    reader.feed(b"b}");
+    assert_eq!(reader.next_value().map(|value| value.map(AnionValue::from)), Ok(Some(ion!({ a: b }))));
+
+    // a value left unfinished is an error at the end of input
+    reader.feed(b" (x");
+    assert_eq!(reader.next_value(), Err(ParseError::Incomplete));
+    reader.finish();
+    assert_eq!(reader.next_value(), Err(ParseError::UnexpectedEnd));
+  }
+
+  #[test]
+  fn spans()
+  {
+    let text = "a::b::{x: [1, \"two\"],\n  'y': c::(d)}\n$ion_symbol_table::{symbols: [\"s\"]}\n  $10";
+    let values = parse_values_with_spans(text).unwrap();
+    assert_eq!(values.len(), 2);
+    let slice = |span: Span| &text[span.range()];
+    let slices = |spans: &[Span]| spans.iter().map(|&span| slice(span)).collect::<Vec<_>>();
+
+    let spans = &values[0].1;
+    assert_eq!(slices(&spans.annotations), vec!["a", "b"]);
+    assert_eq!(slice(spans.value), "{x: [1, \"two\"],\n  'y': c::(d)}");
+    assert_eq!(spans.start(), Location::start());
+    assert_eq!(spans.field_name, None);
+
+    let x = &spans.children[0];
+    assert_eq!(x.field_name.map(slice), Some("x"));
+    assert_eq!(slice(x.value), "[1, \"two\"]");
+    let elements: Vec<Span> = x.children.iter().map(|element| element.value).collect();
+    assert_eq!(slices(&elements), vec!["1", "\"two\""]);
+
+    let y = &spans.children[1];
+    assert_eq!(y.field_name.map(slice), Some("'y'"));
+    assert_eq!(slices(&y.annotations), vec!["c"]);
+    assert_eq!(y.annotations[0].start, Location { offset: 29, line: 2, column: 8 });
+    assert_eq!(y.value.start, Location { offset: 32, line: 2, column: 11 });
+    assert_eq!(slice(y.children[0].value), "d");
+
+    assert_eq!(values[1].0, sym("s"));
+    assert_eq!(values[1].1.value.start, Location { offset: 75, line: 4, column: 3 });
+    assert!(values[1].1.children.is_empty());
+
+    // long strings end at their last part's quotes
+    let long = "['''a''' /* b */ '''c''' // d\n , e]";
+    let values = parse_values_with_spans(long).unwrap();
+    let elements: Vec<&str> = values[0].1.children.iter().map(|element| &long[element.value.range()]).collect();
+    assert_eq!(elements, vec!["'''a''' /* b */ '''c'''", "e"]);
+    assert_eq!(parse_values_with_spans("'''a'''  ").unwrap()[0].1.value.range(), 0..7);
+
+    // columns count characters
+    let values = parse_values_with_spans("\"\u{e9}\u{e9}\" x").unwrap();
+    assert_eq!(values[1].1.value.start, Location { offset: 7, line: 1, column: 6 });
+
+    // locations go on across chunks
+    let mut reader = TextReader::incremental(ReaderOptions::default());
+    reader.feed(b"1\n [2");
+    let (_, spans) = reader.next_value_with_spans().unwrap().unwrap();
+    assert_eq!(spans.value.range(), 0..1);
+    assert_eq!(reader.next_value_with_spans(), Err(ParseError::Incomplete));
+    reader.feed(b", 3]");
+    let (value, spans) = reader.next_value_with_spans().unwrap().unwrap();
+    assert_eq!(value.into_owned(), ion!([2, 3]));
+    assert_eq!(spans.value, Span { start: Location { offset: 3, line: 2, column: 2 }, end: Location { offset: 9, line: 2, column: 8 } });
+    assert_eq!(spans.children[1].value.range(), 7..8);
+  }
+
+  #[test]
+  fn tolerant_reading()
+  {
+    let text = "1 [2, 3a, 4] ] {a: 5, b 6, c: 7} (8 , 9) $99 10\n[11 12] {d: (1]} 14";
+    let (values, diagnostics) = parse_values_tolerant(text);
+    assert_eq!(values, vec![
+      ion!(1), ion!([2, 4]), ion!({ a: 5, c: 7 }), ion!((8 9)), ion!(10), ion!([11]), ion!({ d: (1) }), ion!(14),
+    ]);
+    let found: Vec<&str> = diagnostics.iter().map(|diagnostic| &text[diagnostic.span.range()]).collect();
+    assert_eq!(found, vec!["3a", "]", "6", ",", "$99", "12", "]", "}"]);
+    assert_eq!(diagnostics[1].error, ParseError::Syntax(String::from("]")));
+    assert_eq!(diagnostics[4].error, ParseError::UnknownSymbolId(99));
+    assert_eq!(diagnostics[5].to_string(), "2:5: invalid ion value '12'");
+
+    // errors in the skipped text aren't reported, and running out ends
+    // reading
+    let (values, diagnostics) = parse_values_tolerant("[1, 2a 3b, 4] [5");
+    assert_eq!(values, vec![ion!([1, 4])]);
+    let errors: Vec<ParseError> = diagnostics.into_iter().map(|diagnostic| diagnostic.error).collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[1], ParseError::UnexpectedEnd);
+
+    // without tolerance, the first error ends reading
+    let mut reader = TextReader::from_text("[1, 2a] 3");
+    assert!(reader.next_value().is_err());
+    assert!(reader.diagnostics().is_empty());
+
+    // the spans of what's dropped are dropped too
+    let mut reader = TextReader::from_text("[1, {a: 2a}, 3]").tolerant();
+    let (value, spans) = reader.next_value_with_spans().unwrap().unwrap();
+    assert_eq!(value.into_owned(), ion!([1, {}, 3]));
+    assert_eq!(spans.children.iter().map(|child| child.value.range()).collect::<Vec<_>>(), vec![1..2, 4..11, 13..14]);
+    assert!(spans.children[1].children.is_empty());
+
+    // diagnostics found before running out of data are kept once
+    let mut reader = TextReader::incremental(ReaderOptions::default()).tolerant();
+    reader.feed(b"1 ] [2, 2a");
+    assert_eq!(reader.next_value(), Ok(Some(AnionValueRef::from(ion!(1)))));
+    assert_eq!(reader.next_value(), Err(ParseError::Incomplete));
+    assert_eq!(reader.diagnostics().len(), 1);
+    reader.feed(b", 3]");
+    reader.finish();
+    assert_eq!(reader.next_value().map(|value| value.map(AnionValueRef::into_owned)), Ok(Some(ion!([2, 3]))));
+    assert_eq!(reader.next_value(), Ok(None));
+    assert_eq!(reader.take_diagnostics().len(), 2);
+  }
+}
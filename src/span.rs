@@ -0,0 +1,94 @@
+//! Where values were in ion text
+//!
+//! `TextReader::next_value_with_spans` returns a `Spans` tree along with
+//! each value, giving the place in the text of the value, its annotations
+//! and, in a struct, its field name. The tree is only built on request, so
+//! plain reading pays nothing for it and `AnionValue` stays as it is.
+
+use std::fmt;
+
+/// A place in ion text: a byte offset, and the line and column (from 1)
+/// it's on; columns count characters, not bytes
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Location {
+  pub offset: usize,
+  pub line: usize,
+  pub column: usize,
+}
+
+impl Location {
+  /// The start of the text
+  pub fn start() -> Location
+  {
+    Location { offset: 0, line: 1, column: 1 }
+  }
+
+  /// Move past `byte`
+  pub(crate) fn advance(&mut self, byte: u8)
+  {
+    self.offset += 1;
+    if byte == b'\n' {
+      self.line += 1;
+      self.column = 1;
+    } else if byte & 0xc0 != 0x80 {
+      // not a UTF-8 continuation byte
+      self.column += 1;
+    }
+  }
+}
+
+impl Default for Location {
+  fn default() -> Location
+  {
+    Location::start()
+  }
+}
+
+/// `line:column`
+impl fmt::Display for Location {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
+/// The text from `start` up to `end`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+  pub start: Location,
+  pub end: Location,
+}
+
+impl Span {
+  /// The byte range of the text
+  pub fn range(&self) -> ::std::ops::Range<usize>
+  {
+    self.start.offset..self.end.offset
+  }
+}
+
+/// Spans of a value and what's in it, mirroring the value
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Spans {
+  /// The value itself, from its first byte to its last, not including
+  /// annotations
+  pub value: Span,
+
+  /// Each annotation, in order
+  pub annotations: Vec<Span>,
+
+  /// The field name, for the value of a struct field
+  pub field_name: Option<Span>,
+
+  /// The elements of a list or s-expression, or the fields of a struct,
+  /// in order
+  pub children: Vec<Spans>,
+}
+
+impl Spans {
+  /// Where the value starts, annotations included
+  pub fn start(&self) -> Location
+  {
+    self.annotations.first().unwrap_or(&self.value).start
+  }
+}
@@ -0,0 +1,313 @@
+//! Symbol tables
+//!
+//! A symbol ID such as `$10` names an entry in the current symbol table.
+//! Every table starts with the nine system symbols; a top level
+//! `$ion_symbol_table::{...}` struct defines a new table, and a `$ion_1_0`
+//! version marker resets it to the system table.
+//!
+//! There is no catalog of shared symbol tables, so imported symbols have
+//! unknown text.
+
+use super::AnionValue;
+use reader::{Limit, ParseError};
+
+use std::convert::TryFrom;
+
+/// Text of the system symbols `$1` to `$9`
+pub const SYSTEM_SYMBOLS: [&'static str; 9] = [
+  "$ion", "$ion_1_0", "$ion_symbol_table", "name", "version", "imports", "symbols", "max_id", "$ion_shared_symbol_table",
+];
+
+/// Symbols by ID, from `$1`; `None` for symbols with unknown text
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolTable {
+  /// Consecutive runs of symbols, the first from `$1`
+  runs: Vec<Run>,
+}
+
+/// Symbols from ID `first` on
+#[derive(Debug, Clone, PartialEq)]
+struct Run {
+  first: usize,
+  symbols: Symbols,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Symbols {
+  /// Symbols listed in a table
+  Listed(Vec<Option<String>>),
+
+  /// This many symbols imported from a shared table, whose text is
+  /// unknown; only counted, as `max_id` may be large
+  Imported(usize),
+}
+
+impl Run {
+  fn len(&self) -> usize
+  {
+    match self.symbols {
+      Symbols::Listed(ref symbols) => symbols.len(),
+      Symbols::Imported(count) => count,
+    }
+  }
+}
+
+impl SymbolTable {
+  /// The table in effect at the start of a stream and after `$ion_1_0`
+  pub fn system() -> SymbolTable
+  {
+    let symbols = SYSTEM_SYMBOLS.iter().map(|&text| Some(String::from(text))).collect();
+    SymbolTable {
+      runs: vec![Run { first: 1, symbols: Symbols::Listed(symbols) }],
+    }
+  }
+
+  /// The largest symbol ID defined
+  pub fn max_id(&self) -> usize
+  {
+    self.runs.last().map_or(0, |run| run.first + run.len() - 1)
+  }
+
+  /// Text of symbol `sid`; `None` for symbols with unknown text, which
+  /// includes `$0`
+  pub fn text(&self, sid: usize) -> Result<Option<&str>, ParseError>
+  {
+    if sid == 0 {
+      return Ok(None);
+    }
+    if sid > self.max_id() {
+      return Err(ParseError::UnknownSymbolId(sid));
+    }
+    let index = match self.runs.binary_search_by_key(&sid, |run| run.first) {
+      Ok(index) => index,
+      Err(index) => index - 1,
+    };
+    let run = &self.runs[index];
+    match run.symbols {
+      Symbols::Listed(ref symbols) => Ok(symbols[sid - run.first].as_ref().map(|text| text.as_str())),
+      Symbols::Imported(_) => Ok(None),
+    }
+  }
+
+  /// Add a listed symbol after the others
+  fn push(&mut self, text: Option<String>)
+  {
+    let first = self.max_id() + 1;
+    match self.runs.last_mut() {
+      Some(&mut Run { symbols: Symbols::Listed(ref mut symbols), .. }) => {
+        symbols.push(text);
+        return;
+      },
+      _ => (),
+    }
+    self.runs.push(Run { first: first, symbols: Symbols::Listed(vec![text]) });
+  }
+
+  /// The table defined by a `$ion_symbol_table` struct's fields, read
+  /// while this table is current.
+  ///
+  /// `imports: $ion_symbol_table` appends to this table; a list of
+  /// imports starts from the system table. Entries of `symbols` which
+  /// aren't strings define symbols with unknown text, and other fields are
+  /// ignored. The table may have at most `max_symbols` symbols, the system
+  /// symbols included.
+  pub fn local(&self, fields: &[(String, AnionValue)], max_symbols: usize) -> Result<SymbolTable, ParseError>
+  {
+    let mut imports = None;
+    let mut symbols = None;
+    for &(ref name, ref value) in fields {
+      let field = match name.as_str() {
+        "imports" => &mut imports,
+        "symbols" => &mut symbols,
+        _ => continue,
+      };
+      if field.is_some() {
+        return Err(ParseError::InvalidSymbolTable(format!("more than one '{}' field", name)));
+      }
+      *field = Some(value);
+    }
+
+    let mut table = match imports {
+      Some(&AnionValue::Symbol(Some(ref name))) if name == "$ion_symbol_table" => self.clone(),
+      Some(&AnionValue::List(Some(ref imports))) => {
+        let mut table = SymbolTable::system();
+        for import in imports {
+          table.import(import, max_symbols)?;
+        }
+        table
+      },
+      _ => SymbolTable::system(),
+    };
+
+    if let Some(&AnionValue::List(Some(ref symbols))) = symbols {
+      if table.max_id().saturating_add(symbols.len()) > max_symbols {
+        return Err(ParseError::LimitExceeded(Limit::Symbols));
+      }
+      for symbol in symbols {
+        table.push(match *symbol {
+          AnionValue::String(Some(ref text)) => Some(text.clone()),
+          _ => None,
+        });
+      }
+    }
+    return Ok(table);
+  }
+
+  /// Add the symbols of a shared table import, which need a `max_id` as
+  /// their text is unknown; imports without a name are skipped
+  fn import(&mut self, import: &AnionValue, max_symbols: usize) -> Result<(), ParseError>
+  {
+    let fields = match *import {
+      AnionValue::Struct(Some(ref fields)) => fields,
+      _ => return Ok(()),
+    };
+    let field = |name: &str| fields.iter().find(|field| field.0 == name).map(|field| &field.1);
+
+    let name = match field("name") {
+      Some(&AnionValue::String(Some(ref name))) if !name.is_empty() && name != "$ion" => name,
+      _ => return Ok(()),
+    };
+
+    let max_id = match field("max_id").map(u32::try_from) {
+      Some(Ok(max_id)) => max_id,
+      _ => return Err(ParseError::InvalidSymbolTable(format!("import '{}' needs a valid max_id", name))),
+    };
+    let count = max_id as usize;
+    if self.max_id().checked_add(count).map_or(true, |max_id| max_id > max_symbols) {
+      return Err(ParseError::LimitExceeded(Limit::Symbols));
+    }
+    if count == 0 {
+      return Ok(());
+    }
+    let first = self.max_id() + 1;
+    match self.runs.last_mut() {
+      Some(&mut Run { symbols: Symbols::Imported(ref mut imported), .. }) => {
+        *imported += count;
+        return Ok(());
+      },
+      _ => (),
+    }
+    self.runs.push(Run { first: first, symbols: Symbols::Imported(count) });
+    return Ok(());
+  }
+}
+
+/// Fields of a top level value which is a local symbol table: a struct
+/// whose first annotation is `$ion_symbol_table`
+pub fn symbol_table_fields(value: &AnionValue) -> Option<&[(String, AnionValue)]>
+{
+  match *value {
+    AnionValue::Annotated(ref annotations, ref value) if annotations.first().map_or(false, |a| a == "$ion_symbol_table") => {
+      match **value {
+        AnionValue::Struct(Some(ref fields)) => Some(fields),
+        _ => None,
+      }
+    },
+    _ => None,
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn string(s: &str) -> AnionValue
+  {
+    AnionValue::String(Some(String::from(s)))
+  }
+
+  fn table(fields: Vec<(&str, AnionValue)>) -> Vec<(String, AnionValue)>
+  {
+    fields.into_iter().map(|(name, value)| (String::from(name), value)).collect()
+  }
+
+  #[test]
+  fn system_symbols()
+  {
+    let system = SymbolTable::system();
+    assert_eq!(system.max_id(), 9);
+    assert_eq!(system.text(0), Ok(None));
+    assert_eq!(system.text(1), Ok(Some("$ion")));
+    assert_eq!(system.text(9), Ok(Some("$ion_shared_symbol_table")));
+    assert_eq!(system.text(10), Err(ParseError::UnknownSymbolId(10)));
+  }
+
+  #[test]
+  fn local_symbols()
+  {
+    let local = SymbolTable::system().local(&table(vec![("symbols", AnionValue::List(Some(vec![string("a"), AnionValue::from(1), string("b")])))]), usize::max_value()).unwrap();
+    assert_eq!(local.max_id(), 12);
+    assert_eq!(local.text(10), Ok(Some("a")));
+    assert_eq!(local.text(11), Ok(None));
+    assert_eq!(local.text(12), Ok(Some("b")));
+
+    // appending to the current table, or replacing it
+    let appended = local.local(&table(vec![
+      ("imports", ion!(#(AnionValue::Symbol(Some(String::from("$ion_symbol_table")))))),
+      ("symbols", AnionValue::List(Some(vec![string("c")]))),
+    ]), usize::max_value()).unwrap();
+    assert_eq!(appended.text(10), Ok(Some("a")));
+    assert_eq!(appended.text(13), Ok(Some("c")));
+
+    let replaced = local.local(&table(vec![("symbols", AnionValue::List(Some(vec![string("c")])))]), usize::max_value()).unwrap();
+    assert_eq!(replaced.text(10), Ok(Some("c")));
+    assert_eq!(replaced.max_id(), 10);
+
+    let duplicated = table(vec![("symbols", ion!([])), ("symbols", ion!([]))]);
+    assert!(SymbolTable::system().local(&duplicated, usize::max_value()).is_err());
+  }
+
+  #[test]
+  fn shared_imports_have_unknown_text()
+  {
+    let import = |fields: Vec<(&str, AnionValue)>| AnionValue::Struct(Some(table(fields)));
+
+    let local = SymbolTable::system().local(&table(vec![
+      ("imports", AnionValue::List(Some(vec![import(vec![("name", string("shared")), ("version", ion!(1)), ("max_id", ion!(2))])]))),
+      ("symbols", AnionValue::List(Some(vec![string("a")]))),
+    ]), usize::max_value()).unwrap();
+    assert_eq!(local.text(10), Ok(None));
+    assert_eq!(local.text(11), Ok(None));
+    assert_eq!(local.text(12), Ok(Some("a")));
+
+    let without_max_id = table(vec![("imports", AnionValue::List(Some(vec![import(vec![("name", string("shared"))])])))]);
+    assert!(SymbolTable::system().local(&without_max_id, usize::max_value()).is_err());
+
+    // a limit on symbols, counting the system symbols
+    let shared = table(vec![("imports", AnionValue::List(Some(vec![ion!({ name: "shared", max_id: 1000 })])))]);
+    assert_eq!(SymbolTable::system().local(&shared, 1009).map(|table| table.max_id()), Ok(1009));
+    assert_eq!(SymbolTable::system().local(&shared, 1008), Err(ParseError::LimitExceeded(Limit::Symbols)));
+    let local = table(vec![("symbols", AnionValue::List(Some(vec![string("a"), string("b")])))]);
+    assert!(SymbolTable::system().local(&local, 11).is_ok());
+    assert_eq!(SymbolTable::system().local(&local, 10), Err(ParseError::LimitExceeded(Limit::Symbols)));
+
+    // large imports are only counted
+    let large = table(vec![
+      ("imports", AnionValue::List(Some(vec![ion!({ name: "x", max_id: 4000000000u32 }), ion!({ name: "y", max_id: 5 })]))),
+      ("symbols", AnionValue::List(Some(vec![string("a")]))),
+    ]);
+    let local = SymbolTable::system().local(&large, usize::max_value()).unwrap();
+    assert_eq!(local.max_id(), 4000000015);
+    assert_eq!(local.text(9), Ok(Some("$ion_shared_symbol_table")));
+    assert_eq!(local.text(10), Ok(None));
+    assert_eq!(local.text(4000000014), Ok(None));
+    assert_eq!(local.text(4000000015), Ok(Some("a")));
+    assert_eq!(local.text(4000000016), Err(ParseError::UnknownSymbolId(4000000016)));
+  }
+
+  #[test]
+  fn symbol_table_values()
+  {
+    let annotated = |annotations: &[&str], value: AnionValue| {
+      AnionValue::Annotated(annotations.iter().map(|&a| String::from(a)).collect(), Box::new(value))
+    };
+
+    assert!(symbol_table_fields(&annotated(&["$ion_symbol_table"], ion!({ symbols: ["a"] }))).is_some());
+    assert!(symbol_table_fields(&annotated(&["$ion_symbol_table", "a"], ion!({}))).is_some());
+    assert!(symbol_table_fields(&annotated(&["a", "$ion_symbol_table"], ion!({}))).is_none());
+    assert!(symbol_table_fields(&annotated(&["$ion_symbol_table"], ion!([]))).is_none());
+    assert!(symbol_table_fields(&annotated(&["$ion_symbol_table"], ion!(null.struct))).is_none());
+    assert!(symbol_table_fields(&ion!({})).is_none());
+  }
+}
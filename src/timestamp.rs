@@ -0,0 +1,195 @@
+//! Ion timestamp values
+//!
+//! Timestamps are kept exactly as written - the local date/time fields,
+//! the precision they were given with and the local offset - so no
+//! information is lost converting to and from ion documents.
+
+use std::fmt;
+use std::cmp::{self, Ordering};
+
+/// The finest time unit a timestamp was specified with.
+///
+/// Ion considers `2007T` and `2007-01-01` to be different values, so the
+/// precision is part of the timestamp's identity.
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum TimestampPrecision {
+  Year,
+  Month,
+  Day,
+  Minute,
+  Second,
+}
+
+/// An ion timestamp.
+///
+/// Fields below the precision are zero (or one, for month and day).
+/// The offset is given in minutes east of UTC; `None` is the 'unknown'
+/// offset, written `-00:00`.
+///
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Timestamp {
+  pub precision: TimestampPrecision,
+  pub year: u16,
+  pub month: u8,
+  pub day: u8,
+  pub hour: u8,
+  pub minute: u8,
+  pub second: u8,
+
+  /// Digits following the decimal point of the seconds field, if any.
+  /// Stored as text so trailing zeros (precision) are kept.
+  pub fraction: Option<String>,
+
+  /// Local offset from UTC in minutes
+  pub offset: Option<i16>,
+}
+
+impl Timestamp {
+  /// Create a timestamp with year precision
+  pub fn year(year: u16) -> Timestamp
+  {
+    Timestamp {
+      precision: TimestampPrecision::Year,
+      year: year,
+      month: 1,
+      day: 1,
+      hour: 0,
+      minute: 0,
+      second: 0,
+      fraction: None,
+      offset: None,
+    }
+  }
+
+  /// Create a timestamp with day precision
+  pub fn day(year: u16, month: u8, day: u8) -> Timestamp
+  {
+    Timestamp {
+      precision: TimestampPrecision::Day,
+      month: month,
+      day: day,
+      ..Timestamp::year(year)
+    }
+  }
+}
+
+impl fmt::Display for Timestamp {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    match self.precision {
+      TimestampPrecision::Year => return write!(f, "{:04}T", self.year),
+      TimestampPrecision::Month => return write!(f, "{:04}-{:02}T", self.year, self.month),
+      TimestampPrecision::Day => return write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day),
+      _ => (),
+    }
+
+    write!(f, "{:04}-{:02}-{:02}T{:02}:{:02}", self.year, self.month, self.day, self.hour, self.minute)?;
+
+    if self.precision == TimestampPrecision::Second {
+      write!(f, ":{:02}", self.second)?;
+      if let Some(ref fraction) = self.fraction {
+        write!(f, ".{}", fraction)?;
+      }
+    }
+
+    match self.offset {
+      None => write!(f, "-00:00"),
+      Some(0) => write!(f, "Z"),
+      Some(offset) => {
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs();
+        write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+      },
+    }
+  }
+}
+
+impl Timestamp {
+  /// Minutes since the unix epoch, in UTC, of the timestamp's
+  /// hour/minute fields (unknown offsets are treated as UTC).
+  fn utc_minutes(&self) -> i64
+  {
+    // days from civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let (month, day) = (self.month as i64, self.day as i64);
+    let year = self.year as i64 - if month <= 2 { 1 } else { 0 };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    return days * 1440 + self.hour as i64 * 60 + self.minute as i64 - self.offset.unwrap_or(0) as i64;
+  }
+}
+
+/// Number of days in a month of the proleptic Gregorian calendar
+pub(crate) fn days_in_month(year: i64, month: i64) -> i64
+{
+  let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+  match month {
+    2 if leap => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+/// Timestamps are ordered by the instant they represent, then by
+/// precision, offset and number of fractional digits, and last by their
+/// fields as given, so that only identical timestamps compare equal.
+impl Ord for Timestamp {
+  fn cmp(&self, other: &Timestamp) -> Ordering
+  {
+    let empty = String::new();
+    let (a, b) = (self.fraction.as_ref().unwrap_or(&empty), other.fraction.as_ref().unwrap_or(&empty));
+
+    // compare fractions numerically by padding the shorter with zeros
+    let width = cmp::max(a.len(), b.len());
+    let fraction_order = format!("{:0<1$}", a, width).cmp(&format!("{:0<1$}", b, width));
+
+    return self.utc_minutes()
+      .cmp(&other.utc_minutes())
+      .then(self.second.cmp(&other.second))
+      .then(fraction_order)
+      .then(self.precision.cmp(&other.precision))
+      .then(self.offset.cmp(&other.offset))
+      .then(a.len().cmp(&b.len()))
+      // an empty fraction isn't no fraction, and fields may be out of range
+      .then(self.fraction.is_some().cmp(&other.fraction.is_some()))
+      .then((self.year, self.month, self.day, self.hour, self.minute).cmp(&(other.year, other.month, other.day, other.hour, other.minute)));
+  }
+}
+
+impl PartialOrd for Timestamp {
+  fn partial_cmp(&self, other: &Timestamp) -> Option<Ordering>
+  {
+    Some(self.cmp(other))
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn ordering_agrees_with_equality()
+  {
+    let second = Timestamp { precision: TimestampPrecision::Second, offset: Some(0), ..Timestamp::day(2020, 2, 29) };
+    let empty_fraction = Timestamp { fraction: Some(String::new()), ..second.clone() };
+    assert!(second != empty_fraction);
+    assert_eq!(second.cmp(&empty_fraction), Ordering::Less);
+    assert_eq!(empty_fraction.cmp(&empty_fraction.clone()), Ordering::Equal);
+
+    // the same instant written with out of range fields
+    let overflowed = Timestamp { month: 2, day: 30, ..second.clone() };
+    let next_day = Timestamp { month: 3, day: 1, ..second.clone() };
+    assert!(overflowed != next_day);
+    assert!(overflowed.cmp(&next_day) != Ordering::Equal);
+
+    let mut sorted = vec![empty_fraction.clone(), second.clone(), Timestamp { fraction: Some(String::from("5")), ..second.clone() }];
+    sorted.sort();
+    assert_eq!(sorted[..2], [second, empty_fraction][..]);
+  }
+}
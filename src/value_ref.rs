@@ -0,0 +1,172 @@
+//! Values borrowing from their source
+//!
+//! `AnionValueRef` mirrors `AnionValue`, but text and bytes may borrow from
+//! the buffer they were read from. Strings, symbols and field names
+//! without escapes in the input then need no allocation. Use
+//! `to_owned`/`into_owned` to get an `AnionValue`.
+
+use super::{AnionValue, Decimal, Int, Timestamp};
+
+use std::borrow::Cow;
+
+/// An ion value whose text and bytes may be borrowed; see `AnionValue`
+#[derive(Debug, PartialEq, Clone)]
+pub enum AnionValueRef<'a> {
+  Null,
+  Boolean(Option<bool>),
+  Integer(Option<Int>),
+  Float(Option<f64>),
+  Decimal(Option<Decimal>),
+  Timestamp(Option<Timestamp>),
+  String(Option<Cow<'a, str>>),
+  Symbol(Option<Cow<'a, str>>),
+  Blob(Option<Cow<'a, [u8]>>),
+  Clob(Option<Cow<'a, [u8]>>),
+  List(Option<Vec<AnionValueRef<'a>>>),
+  SExp(Option<Vec<AnionValueRef<'a>>>),
+  Struct(Option<Vec<(Cow<'a, str>, AnionValueRef<'a>)>>),
+  Annotated(Vec<Cow<'a, str>>, Box<AnionValueRef<'a>>),
+}
+
+impl<'a> AnionValueRef<'a> {
+  /// Copy into an owned value
+  pub fn to_owned(&self) -> AnionValue
+  {
+    self.clone().into_owned()
+  }
+
+  /// Convert into an owned value, copying only borrowed data
+  pub fn into_owned(self) -> AnionValue
+  {
+    use self::AnionValueRef::*;
+
+    let text = |text: Option<Cow<str>>| text.map(Cow::into_owned);
+    let bytes = |bytes: Option<Cow<[u8]>>| bytes.map(Cow::into_owned);
+    let values = |values: Option<Vec<AnionValueRef>>| values.map(|values| values.into_iter().map(AnionValueRef::into_owned).collect());
+
+    match self {
+      Null => AnionValue::Null,
+      Boolean(b) => AnionValue::Boolean(b),
+      Integer(i) => AnionValue::Integer(i),
+      Float(f) => AnionValue::Float(f),
+      Decimal(d) => AnionValue::Decimal(d),
+      Timestamp(t) => AnionValue::Timestamp(t),
+      String(s) => AnionValue::String(text(s)),
+      Symbol(s) => AnionValue::Symbol(text(s)),
+      Blob(b) => AnionValue::Blob(bytes(b)),
+      Clob(b) => AnionValue::Clob(bytes(b)),
+      List(l) => AnionValue::List(values(l)),
+      SExp(l) => AnionValue::SExp(values(l)),
+      Struct(fields) => {
+        AnionValue::Struct(fields.map(|fields| fields.into_iter().map(|(name, value)| (name.into_owned(), value.into_owned())).collect()))
+      },
+      Annotated(annotations, value) => {
+        AnionValue::Annotated(annotations.into_iter().map(Cow::into_owned).collect(), Box::new(value.into_owned()))
+      },
+    }
+  }
+}
+
+impl<'a> From<AnionValueRef<'a>> for AnionValue {
+  fn from(value: AnionValueRef<'a>) -> Self
+  {
+    value.into_owned()
+  }
+}
+
+/// Take ownership of a value, borrowing nothing
+impl<'a> From<AnionValue> for AnionValueRef<'a> {
+  fn from(value: AnionValue) -> Self
+  {
+    use AnionValue::*;
+
+    let values = |values: Option<Vec<AnionValue>>| values.map(|values| values.into_iter().map(AnionValueRef::from).collect());
+
+    match value {
+      Null => AnionValueRef::Null,
+      Boolean(b) => AnionValueRef::Boolean(b),
+      Integer(i) => AnionValueRef::Integer(i),
+      Float(f) => AnionValueRef::Float(f),
+      Decimal(d) => AnionValueRef::Decimal(d),
+      Timestamp(t) => AnionValueRef::Timestamp(t),
+      String(s) => AnionValueRef::String(s.map(Cow::Owned)),
+      Symbol(s) => AnionValueRef::Symbol(s.map(Cow::Owned)),
+      Blob(b) => AnionValueRef::Blob(b.map(Cow::Owned)),
+      Clob(b) => AnionValueRef::Clob(b.map(Cow::Owned)),
+      List(l) => AnionValueRef::List(values(l)),
+      SExp(l) => AnionValueRef::SExp(values(l)),
+      Struct(fields) => {
+        AnionValueRef::Struct(fields.map(|fields| fields.into_iter().map(|(name, value)| (Cow::Owned(name), AnionValueRef::from(value))).collect()))
+      },
+      Annotated(annotations, value) => {
+        AnionValueRef::Annotated(annotations.into_iter().map(Cow::Owned).collect(), Box::new(AnionValueRef::from(*value)))
+      },
+    }
+  }
+}
+
+/// Borrow all the text and bytes of an owned value
+impl<'a> From<&'a AnionValue> for AnionValueRef<'a> {
+  fn from(value: &'a AnionValue) -> Self
+  {
+    use AnionValue::*;
+
+    let text = |text: &'a Option<::std::string::String>| text.as_ref().map(|text| Cow::Borrowed(text.as_str()));
+    let bytes = |bytes: &'a Option<Vec<u8>>| bytes.as_ref().map(|bytes| Cow::Borrowed(&bytes[..]));
+    let values = |values: &'a Option<Vec<AnionValue>>| values.as_ref().map(|values| values.iter().map(AnionValueRef::from).collect());
+
+    match *value {
+      Null => AnionValueRef::Null,
+      Boolean(b) => AnionValueRef::Boolean(b),
+      Integer(ref i) => AnionValueRef::Integer(i.clone()),
+      Float(f) => AnionValueRef::Float(f),
+      Decimal(ref d) => AnionValueRef::Decimal(d.clone()),
+      Timestamp(ref t) => AnionValueRef::Timestamp(t.clone()),
+      String(ref s) => AnionValueRef::String(text(s)),
+      Symbol(ref s) => AnionValueRef::Symbol(text(s)),
+      Blob(ref b) => AnionValueRef::Blob(bytes(b)),
+      Clob(ref b) => AnionValueRef::Clob(bytes(b)),
+      List(ref l) => AnionValueRef::List(values(l)),
+      SExp(ref l) => AnionValueRef::SExp(values(l)),
+      Struct(ref fields) => {
+        AnionValueRef::Struct(fields.as_ref().map(|fields| {
+          fields.iter().map(|&(ref name, ref value)| (Cow::Borrowed(name.as_str()), AnionValueRef::from(value))).collect()
+        }))
+      },
+      Annotated(ref annotations, ref value) => {
+        AnionValueRef::Annotated(annotations.iter().map(|a| Cow::Borrowed(a.as_str())).collect(), Box::new(AnionValueRef::from(&**value)))
+      },
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod test {
+  use super::AnionValueRef;
+  use AnionValue;
+  use std::borrow::Cow;
+
+  #[test]
+  fn owned_round_trip()
+  {
+    let value = ion!(a::[1, "two", three, (f 2.5), { four: null.blob, "five": null.int }]);
+    let borrowed = AnionValueRef::from(&value);
+    assert_eq!(borrowed.to_owned(), value);
+    assert_eq!(AnionValue::from(borrowed), value);
+    assert_eq!(AnionValueRef::from(value.clone()).into_owned(), value);
+  }
+
+  #[test]
+  fn borrows_text()
+  {
+    let value = ion!({ name: "text" });
+    match AnionValueRef::from(&value) {
+      AnionValueRef::Struct(Some(fields)) => {
+        assert!(match fields[0].0 { Cow::Borrowed(_) => true, Cow::Owned(_) => false });
+        assert!(match fields[0].1 { AnionValueRef::String(Some(Cow::Borrowed("text"))) => true, _ => false });
+      },
+      other => panic!("not a struct: {:?}", other),
+    }
+  }
+}
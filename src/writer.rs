@@ -0,0 +1,280 @@
+//! Writing ion text
+//!
+//! `AnionValue` implements `Display` by writing the value as ion text,
+//! which reads back as an equal value - except that at top level, a struct
+//! annotated `$ion_symbol_table` is read as a symbol table:
+//!
+//! ```ignore
+//! assert_eq!(ion!(a::[1, 2.5, "x", {b: null.int}]).to_string(), r#"a::[1, 2.5e0, "x", {b: null.int}]"#);
+//! ```
+
+use super::{AnionValue, UNKNOWN_SYMBOL_TEXT};
+
+use std::fmt;
+
+impl fmt::Display for AnionValue {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+  {
+    use AnionValue::*;
+
+    match *self {
+      Null => write!(f, "null"),
+      Boolean(Some(b)) => write!(f, "{}", b),
+      Integer(Some(ref i)) => write!(f, "{}", i),
+      Float(Some(x)) => write_float(f, x),
+      Decimal(Some(ref d)) => write!(f, "{}", d),
+      Timestamp(Some(ref t)) => write!(f, "{}", t),
+      String(Some(ref s)) => write_string(f, s, '"'),
+      Symbol(Some(ref s)) => write_symbol(f, s),
+      Blob(Some(ref b)) => write!(f, "{{{{{}}}}}", base64(b)),
+      Clob(Some(ref b)) => write_clob(f, b),
+      List(Some(ref values)) => write_sequence(f, values, "[", ", ", "]"),
+      SExp(Some(ref values)) => {
+        write!(f, "(")?;
+        for (i, value) in values.iter().enumerate() {
+          if i != 0 {
+            write!(f, " ")?;
+          }
+          match *value {
+            // operators are only symbols inside s-expressions
+            Symbol(Some(ref s)) if is_operator(s) => write!(f, "{}", s)?,
+            _ => write!(f, "{}", value)?,
+          }
+        }
+        write!(f, ")")
+      },
+      Struct(Some(ref fields)) => {
+        write!(f, "{{")?;
+        for (i, &(ref name, ref value)) in fields.iter().enumerate() {
+          if i != 0 {
+            write!(f, ", ")?;
+          }
+          write_symbol(f, name)?;
+          write!(f, ": {}", value)?;
+        }
+        write!(f, "}}")
+      },
+      Annotated(ref annotations, ref value) => {
+        for annotation in annotations {
+          write_symbol(f, annotation)?;
+          write!(f, "::")?;
+        }
+        write!(f, "{}", value)
+      },
+      // typed nulls
+      _ => write!(f, "null.{}", self.type_name()),
+    }
+  }
+}
+
+/// Floats always have an exponent, so they're not read back as decimals
+fn write_float(f: &mut fmt::Formatter, x: f64) -> fmt::Result
+{
+  if x.is_nan() {
+    write!(f, "nan")
+  } else if x.is_infinite() {
+    write!(f, "{}inf", if x > 0.0 { '+' } else { '-' })
+  } else {
+    write!(f, "{:e}", x)
+  }
+}
+
+fn write_sequence(f: &mut fmt::Formatter, values: &[AnionValue], open: &str, separator: &str, close: &str) -> fmt::Result
+{
+  write!(f, "{}", open)?;
+  for (i, value) in values.iter().enumerate() {
+    if i != 0 {
+      write!(f, "{}", separator)?;
+    }
+    write!(f, "{}", value)?;
+  }
+  write!(f, "{}", close)
+}
+
+/// Symbols are written bare if they're identifiers which read back as the
+/// same symbol, otherwise quoted: keywords such as `true`, symbol IDs such
+/// as `$4` and version markers such as `$ion_1_0` all need quotes. The
+/// unknown symbol is written `$0`.
+fn write_symbol(f: &mut fmt::Formatter, symbol: &str) -> fmt::Result
+{
+  if symbol == UNKNOWN_SYMBOL_TEXT || is_identifier(symbol) && !is_reserved(symbol) {
+    write!(f, "{}", symbol)
+  } else {
+    write_string(f, symbol, '\'')
+  }
+}
+
+fn is_identifier(symbol: &str) -> bool
+{
+  let is_start = |c: char| c.is_ascii_alphabetic() || c == '_' || c == '$';
+
+  match symbol.chars().next() {
+    Some(c) if is_start(c) => symbol.chars().all(|c| is_start(c) || c.is_ascii_digit()),
+    _ => false,
+  }
+}
+
+/// Whether a symbol can be written bare in an s-expression; not if it
+/// would start a comment
+fn is_operator(symbol: &str) -> bool
+{
+  let all_operator = symbol.chars().all(|c| "!#%&*+-./;<=>?@^`|~".contains(c));
+  !symbol.is_empty() && all_operator && !symbol.contains("//") && !symbol.contains("/*")
+}
+
+fn is_reserved(symbol: &str) -> bool
+{
+  let all_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+  match symbol {
+    "true" | "false" | "null" | "nan" => true,
+    _ if symbol.starts_with('$') && all_digits(&symbol[1..]) => true,
+    _ if symbol.starts_with("$ion_") => {
+      let mut version = symbol["$ion_".len()..].splitn(2, '_');
+      version.next().map_or(false, all_digits) && version.next().map_or(false, all_digits)
+    },
+    _ => false,
+  }
+}
+
+/// Write text between quotes, escaping the quote, backslash and
+/// control characters
+fn write_string(f: &mut fmt::Formatter, text: &str, quote: char) -> fmt::Result
+{
+  write!(f, "{}", quote)?;
+  for c in text.chars() {
+    match c {
+      '\n' => write!(f, "\\n")?,
+      '\t' => write!(f, "\\t")?,
+      '\r' => write!(f, "\\r")?,
+      '\\' => write!(f, "\\\\")?,
+      c if c == quote => write!(f, "\\{}", c)?,
+      c if (c as u32) < 0x20 || c == '\u{7f}' => write!(f, "\\x{:02x}", c as u32)?,
+      c => write!(f, "{}", c)?,
+    }
+  }
+  write!(f, "{}", quote)
+}
+
+/// Clobs are written as a string of their bytes, escaping anything which
+/// isn't printable ascii
+fn write_clob(f: &mut fmt::Formatter, bytes: &[u8]) -> fmt::Result
+{
+  write!(f, "{{{{\"")?;
+  for &byte in bytes {
+    match byte {
+      b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+      0x20..=0x7e => write!(f, "{}", byte as char)?,
+      _ => write!(f, "\\x{:02x}", byte)?,
+    }
+  }
+  write!(f, "\"}}}}")
+}
+
+fn base64(bytes: &[u8]) -> String
+{
+  const ALPHABET: &'static [u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut result = String::new();
+  for chunk in bytes.chunks(3) {
+    let group = chunk.iter().enumerate().fold(0u32, |group, (i, &byte)| group | (byte as u32) << (16 - 8 * i));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        result.push(ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+      } else {
+        result.push('=');
+      }
+    }
+  }
+  return result;
+}
+
+
+#[cfg(test)]
+mod test {
+  use AnionValue;
+  use reader::parse_string;
+  use std::f64;
+
+  #[test]
+  fn scalars()
+  {
+    assert_eq!(ion!(null).to_string(), "null");
+    assert_eq!(ion!(null.struct).to_string(), "null.struct");
+    assert_eq!(ion!(-12).to_string(), "-12");
+    assert_eq!(ion!("a\"b\n").to_string(), r#""a\"b\n""#);
+    assert_eq!(AnionValue::Blob(Some(b"hello".to_vec())).to_string(), "{{aGVsbG8=}}");
+    assert_eq!(AnionValue::Clob(Some(b"a\"\x01".to_vec())).to_string(), r#"{{"a\"\x01"}}"#);
+  }
+
+  #[test]
+  fn decimals()
+  {
+    let dec = |s: &str| AnionValue::Decimal(Some(s.parse().unwrap()));
+    assert_eq!(dec("1.00").to_string(), "1.00");
+    assert_eq!(dec("1.0d0").to_string(), "1.0");
+    assert_eq!(dec("12d0").to_string(), "12d0");
+    assert_eq!(dec("-0d-2").to_string(), "-0.00");
+  }
+
+  #[test]
+  fn float_special_values()
+  {
+    assert_eq!(ion!(2.5).to_string(), "2.5e0");
+    assert_eq!(ion!(-0.0).to_string(), "-0e0");
+    assert_eq!(ion!(#(f64::NAN)).to_string(), "nan");
+    assert_eq!(ion!(#(f64::INFINITY)).to_string(), "+inf");
+    assert_eq!(ion!(#(f64::NEG_INFINITY)).to_string(), "-inf");
+  }
+
+  #[test]
+  fn symbols_and_containers()
+  {
+    assert_eq!(ion!(abc).to_string(), "abc");
+    assert_eq!(AnionValue::Symbol(Some(String::from("a b'"))).to_string(), r"'a b\''");
+    assert_eq!(ion!(a::[1, (f x), { "b c": null.int, d: [] }]).to_string(), "a::[1, (f x), {'b c': null.int, d: []}]");
+  }
+
+  #[test]
+  fn reserved_symbols_are_quoted()
+  {
+    let sym = |s: &str| AnionValue::Symbol(Some(String::from(s)));
+
+    for &text in ["true", "false", "null", "nan", "$4", "$00", "$ion_1_0", "$ion_2_10"].iter() {
+      assert_eq!(sym(text).to_string(), format!("'{}'", text));
+    }
+    for &text in ["truest", "nan_", "$", "$ion", "$ion_1", "$ion_x_0", "$4a"].iter() {
+      assert_eq!(sym(text).to_string(), text);
+    }
+    assert_eq!(sym("$0").to_string(), "$0");
+    assert_eq!(ion!({ true: [nan] }).to_string(), "{'true': ['nan']}");
+  }
+
+  #[test]
+  fn operators()
+  {
+    assert_eq!(ion!((+ a (* b 2))).to_string(), "(+ a (* b 2))");
+    assert_eq!(ion!((>= x -10)).to_string(), "(>= x -10)");
+    let plus = AnionValue::Symbol(Some(String::from("+")));
+    assert_eq!(plus.to_string(), "'+'");
+    assert_eq!(ion!([#(plus.clone())]).to_string(), "['+']");
+    assert_eq!(ion!((a::#(plus))).to_string(), "(a::'+')");
+    assert_eq!(ion!([(a)]).to_string(), "[(a)]");
+  }
+
+  #[test]
+  fn operators_like_comments_are_quoted()
+  {
+    let sym = |s: &str| AnionValue::Symbol(Some(String::from(s)));
+    let sexp = |s: &str| AnionValue::SExp(Some(vec![ion!(a), sym(s), ion!(b)]));
+
+    assert_eq!(sexp("//").to_string(), "(a '//' b)");
+    assert_eq!(sexp("/*").to_string(), "(a '/*' b)");
+    assert_eq!(sexp("+//").to_string(), "(a '+//' b)");
+    assert_eq!(sexp("*/").to_string(), "(a */ b)");
+    assert_eq!(sexp("/").to_string(), "(a / b)");
+    for &text in ["//", "/*", "+//", "-/*-", "*/", "/", "/+/"].iter() {
+      assert_eq!(parse_string(&sexp(text).to_string()), Ok(sexp(text)), "{}", text);
+    }
+  }
+}
@@ -8,7 +8,6 @@ use std::io::prelude::*;
 use std::io::{BufReader, Lines};
 use std::fs::File;
 extern crate anion;
-use pest::prelude::*;
 use anion::AnionValue;
 
 pub fn good_filename(filename: &str) -> String
@@ -61,7 +60,5 @@ pub fn get_equivs(filename: &str) -> Vec<Vec<String>>
 pub fn parse_value(src: &String) -> AnionValue
 {
   println!("> '{}'", src);
-  let mut parser = anion::Rdp::new(StringInput::new(src));
-  assert!(parser.ion());
-  return parser.ion_value();
+  return anion::reader::parse_string(src).unwrap();
 }
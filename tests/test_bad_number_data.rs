@@ -0,0 +1,45 @@
+extern crate anion;
+
+use anion::AnionValue;
+use anion::reader::parse_string;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+mod common;
+
+use common::bad_filename;
+
+
+/// True if the text is a single int, decimal or float
+fn is_number(src: &str) -> bool
+{
+  match parse_string(src) {
+    Ok(AnionValue::Integer(_)) | Ok(AnionValue::Decimal(_)) | Ok(AnionValue::Float(_)) => true,
+    _ => false,
+  }
+}
+
+#[test]
+fn bad_numbers()
+{
+  let bad_dir = bad_filename("");
+
+  for entry in fs::read_dir(Path::new(&bad_dir)).unwrap() {
+    let path = entry.unwrap().path();
+    let name = String::from(path.file_name().unwrap().to_str().unwrap());
+
+    let is_number_file = ["int", "decimal", "float"].iter().any(|prefix| name.starts_with(prefix));
+    if !is_number_file || !name.ends_with(".ion") {
+      continue;
+    }
+
+    let mut contents = String::new();
+    if File::open(&path).unwrap().read_to_string(&mut contents).is_err() {
+      continue;
+    }
+
+    println!("> {}: '{}'", name, contents.trim());
+    assert!(!is_number(contents.trim()));
+  }
+}
@@ -1,10 +1,7 @@
 extern crate anion;
-extern crate pest;
-extern crate num_bigdecimal;
 
-use pest::prelude::*;
-use anion::AnionValue;
-use num_bigdecimal::BigDecimal;
+use anion::{AnionValue, Decimal};
+use anion::reader::parse_string;
 use std::str::FromStr;
 
 mod common;
@@ -14,12 +11,11 @@ use common::{get_file_lines, good_filename, get_equivs};
 fn parse_value(src: &String) -> AnionValue
 {
   println!("> '{}'", src);
-  let mut parser = anion::Rdp::new(StringInput::new(src.trim_left()));
-  assert!(parser.decimal());
-  let val = parser.decimal_value();
+  let val = parse_string(src.trim_start()).unwrap();
   match &val {
     &AnionValue::Decimal(Some(ref x)) => println!("  -> {}", x),
-    _ => println!("  -> None"),
+    &AnionValue::Decimal(None) => println!("  -> None"),
+    _ => panic!("not a decimal"),
   }
   return val;
 }
@@ -33,19 +29,12 @@ fn good_decimal()
 
   for line in get_file_lines(filename) {
     let line = line.unwrap();
-
-    let formatted_string = line.replace("d", "e").replace("D", "e");
-
     let line = line.as_str();
 
-
-    let expected = BigDecimal::from_str(formatted_string.as_str()).unwrap();
+    let expected = Decimal::from_str(line).unwrap();
     println!("'{}' => {}", line, expected);
 
-    let mut parser = anion::Rdp::new(StringInput::new(line));
-    assert!(parser.decimal());
-
-    let a_val = parser.decimal_value();
+    let a_val = parse_string(line).unwrap();
     assert_eq!(a_val, AnionValue::Decimal(Some(expected)));
 
     match a_val {
@@ -68,7 +57,7 @@ fn test_equiv_decimals()
     for b in i {
       let y = parse_value(b);
       println!("{} ?= {}", f, b);
-      assert_eq!(x, y);
+      assert!(x.ion_eq(&y));
     }
   }
 }
@@ -93,15 +82,15 @@ fn test_zero_decimals()
   let eqiv_vec = get_equivs("zeroDecimals.ion");
   for a in eqiv_vec {
     let mut i = a.iter();
-    let f = String::from(i.next().unwrap().trim_left());
+    let f = String::from(i.next().unwrap().trim_start());
     println!("=> '{}'", f);
     let x = parse_value(&f);
     for b in i {
-      let b = String::from(b.trim_left());
+      let b = String::from(b.trim_start());
       println!("~> '{}'", b);
       let y = parse_value(&b);
       println!("{} ?= {}", f, b);
-      assert_eq!(x, y);
+      assert!(x.ion_eq(&y));
     }
   }
 }
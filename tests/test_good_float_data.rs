@@ -1,8 +1,7 @@
 extern crate anion;
-extern crate pest;
 
-use pest::prelude::*;
 use anion::AnionValue;
+use anion::reader::parse_string;
 
 mod common;
 
@@ -12,12 +11,11 @@ use common::{get_file_lines, good_filename, get_equivs};
 fn parse_value(src: &String) -> AnionValue
 {
   println!("> '{}'", src);
-  let mut parser = anion::Rdp::new(StringInput::new(src.trim_left()));
-  assert!(parser.float());
-  let val = parser.float_value();
+  let val = parse_string(src.trim_start()).unwrap();
   match &val {
     &AnionValue::Float(Some(ref x)) => println!("  -> {}", x),
-    _ => println!("  -> None"),
+    &AnionValue::Float(None) => println!("  -> None"),
+    _ => panic!("not a float"),
   }
   return val;
 }
@@ -38,10 +36,7 @@ fn good_floats()
     let expected: f64 = line.parse().unwrap();
     println!("'{}' => {}", line, expected);
 
-    let mut parser = anion::Rdp::new(StringInput::new(line));
-    assert!(parser.float());
-
-    let a_val = parser.float_value();
+    let a_val = parse_string(line).unwrap();
     assert_eq!(a_val, AnionValue::from(expected));
 
     match a_val {
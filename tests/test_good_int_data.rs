@@ -1,8 +1,7 @@
 extern crate anion;
-extern crate pest;
 
 use anion::AnionValue;
-use pest::prelude::*;
+use anion::reader::parse_string;
 
 mod common;
 use common::{get_file_lines, good_filename, get_equivs};
@@ -10,12 +9,11 @@ use common::{get_file_lines, good_filename, get_equivs};
 fn parse_value(src: &String) -> AnionValue
 {
   println!("> '{}'", src);
-  let mut parser = anion::Rdp::new(StringInput::new(src.trim_left()));
-  assert!(parser.bin_int() || parser.hex_int() || parser.int());
-  let int_val = parser.int_value();
+  let int_val = parse_string(src.trim_start()).unwrap();
   match &int_val {
     &AnionValue::Integer(Some(ref x)) => println!("  -> {}", x),
-    _ => println!("  -> None"),
+    &AnionValue::Integer(None) => println!("  -> None"),
+    _ => panic!("not an int"),
   }
   return int_val;
 }
@@ -32,9 +30,7 @@ fn good_binary()
     let line = line.unwrap();
     println!(">> {}", line);
     let line = line.as_str();
-    let mut parser = anion::Rdp::new(StringInput::new(line));
-    assert!(parser.bin_int());
-    let a_val = parser.int_value();
+    let a_val = parse_string(line).unwrap();
     assert_eq!(a_val, AnionValue::from(*e));
     match a_val {
       AnionValue::Integer(_) => (),
@@ -0,0 +1,17 @@
+//! [\file]: # (tests/test_macro_path.rs)
+//! The `ion!` macro imported by path rather than with `#[macro_use]`.
+
+extern crate anion;
+
+use anion::ion;
+use anion::AnionValue;
+
+#[test]
+fn nested_values_expand()
+{
+  let value = ion!({ sizes: [1, (gte size 10)], tagged: units::7 });
+  match value {
+    AnionValue::Struct(Some(ref fields)) => assert_eq!(fields.len(), 2),
+    _ => panic!("expected a struct, got {:?}", value),
+  }
+}
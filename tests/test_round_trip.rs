@@ -0,0 +1,168 @@
+//! [\file]: # (tests/test_round_trip.rs)
+//!
+//! Values of every type, made up from a fixed seed, are written as ion
+//! text and read back equal
+
+extern crate anion;
+
+use anion::{AnionValue, Decimal, Int, Timestamp, TimestampPrecision};
+use anion::reader::parse_string;
+use std::f64;
+
+fn days_in_month(year: u16, month: u8) -> u64
+{
+  let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+  match month {
+    2 if leap => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+/// Pseudo-random values of every type (xorshift), for round trips
+struct Values {
+  state: u64,
+}
+
+impl Values {
+  fn below(&mut self, n: u64) -> u64
+  {
+    self.state ^= self.state << 13;
+    self.state ^= self.state >> 7;
+    self.state ^= self.state << 17;
+    return self.state % n;
+  }
+
+  fn digits(&mut self, count: u64) -> String
+  {
+    (0..count).map(|_| (b'0' + self.below(10) as u8) as char).collect()
+  }
+
+  fn text(&mut self) -> String
+  {
+    const WORDS: [&str; 12] = ["a", "b_1", "$", "$0", "$12", "true", "null", "nan", "+", "//", "/*", "*/"];
+    const CHARS: [char; 16] = ['a', 'Z', '0', ' ', '\'', '"', '\\', '/', '*', '+', '$', '\n', '\t', '\u{7f}', 'é', '\u{1f600}'];
+
+    match self.below(3) {
+      0 => String::from(WORDS[self.below(WORDS.len() as u64) as usize]),
+      _ => (0..self.below(6)).map(|_| CHARS[self.below(CHARS.len() as u64) as usize]).collect(),
+    }
+  }
+
+  fn int(&mut self) -> Int
+  {
+    match self.below(3) {
+      0 => {
+        let count = 1 + self.below(40);
+        let digits = format!("-{}", self.digits(count));
+        Int::parse_bytes(&digits.as_bytes()[self.below(2) as usize..], 10).unwrap()
+      },
+      _ => Int::from((self.below(u64::max_value()) as i64) >> self.below(64)),
+    }
+  }
+
+  fn timestamp(&mut self) -> Timestamp
+  {
+    let precisions = [TimestampPrecision::Year, TimestampPrecision::Month, TimestampPrecision::Day,
+                      TimestampPrecision::Minute, TimestampPrecision::Second];
+    let precision = precisions[self.below(5) as usize];
+    let mut timestamp = Timestamp::year(1 + self.below(9999) as u16);
+    timestamp.precision = precision;
+    if precision >= TimestampPrecision::Month {
+      timestamp.month = 1 + self.below(12) as u8;
+    }
+    if precision >= TimestampPrecision::Day {
+      timestamp.day = 1 + self.below(days_in_month(timestamp.year, timestamp.month)) as u8;
+    }
+    if precision >= TimestampPrecision::Minute {
+      timestamp.hour = self.below(24) as u8;
+      timestamp.minute = self.below(60) as u8;
+      timestamp.offset = match self.below(3) {
+        0 => None,
+        _ => Some(self.below(2879) as i16 - 1439),
+      };
+    }
+    if precision == TimestampPrecision::Second {
+      timestamp.second = self.below(60) as u8;
+      timestamp.fraction = match self.below(2) {
+        0 => None,
+        _ => {
+          let count = 1 + self.below(12);
+          Some(self.digits(count))
+        },
+      };
+    }
+    return timestamp;
+  }
+
+  fn values(&mut self, depth: u32) -> Vec<AnionValue>
+  {
+    (0..self.below(4)).map(|_| self.value(depth + 1)).collect()
+  }
+
+  fn value(&mut self, depth: u32) -> AnionValue
+  {
+    let null = self.below(8) == 0;
+    let kind = self.below(if depth < 3 { 16 } else { 12 });
+    match kind {
+      0 => AnionValue::Null,
+      1 if null => AnionValue::Boolean(None),
+      1 => AnionValue::Boolean(Some(self.below(2) == 0)),
+      2 if null => AnionValue::Integer(None),
+      2 => AnionValue::Integer(Some(self.int())),
+      3 if null => AnionValue::Float(None),
+      3 => {
+        let float = f64::from_bits(self.below(u64::max_value()));
+        AnionValue::Float(Some(if float.is_nan() { f64::INFINITY } else { float }))
+      },
+      4 if null => AnionValue::Decimal(None),
+      4 => {
+        let exponent = self.below(61) as i64 - 30;
+        AnionValue::Decimal(Some(match self.below(8) {
+          0 => Decimal::negative_zero(exponent),
+          _ => format!("{}d{}", self.int(), exponent).parse().unwrap(),
+        }))
+      },
+      5 if null => AnionValue::Timestamp(None),
+      5 => AnionValue::Timestamp(Some(self.timestamp())),
+      6 if null => AnionValue::String(None),
+      6 => AnionValue::String(Some(self.text())),
+      7 if null => AnionValue::Symbol(None),
+      7 => AnionValue::Symbol(Some(self.text())),
+      8 if null => AnionValue::Blob(None),
+      8 => AnionValue::Blob(Some((0..self.below(8)).map(|_| self.below(256) as u8).collect())),
+      9 if null => AnionValue::Clob(None),
+      9 => AnionValue::Clob(Some((0..self.below(8)).map(|_| self.below(256) as u8).collect())),
+      10 | 11 => {
+        // annotations on annotations read back as one list of them
+        let value = match self.value(depth) {
+          AnionValue::Annotated(_, value) => *value,
+          value => value,
+        };
+        let annotations = (0..1 + self.below(2)).map(|_| self.text()).collect();
+        AnionValue::Annotated(annotations, Box::new(value))
+      },
+      12 if null => AnionValue::List(None),
+      12 => AnionValue::List(Some(self.values(depth))),
+      13 if null => AnionValue::SExp(None),
+      13 => AnionValue::SExp(Some(self.values(depth))),
+      14 if null => AnionValue::Struct(None),
+      _ => AnionValue::Struct(Some((0..self.below(4)).map(|_| (self.text(), self.value(depth + 1))).collect())),
+    }
+  }
+}
+
+#[test]
+fn written_values_read_back()
+{
+  let mut values = Values { state: 0x2545_f491_4f6c_dd1d };
+  for _ in 0..5000 {
+    let value = values.value(0);
+    let text = value.to_string();
+    let read = parse_string(&text);
+    assert_eq!(read, Ok(value), "{}", text);
+    // decimals equal by value; the text keeps their precision
+    assert_eq!(read.unwrap().to_string(), text);
+  }
+}